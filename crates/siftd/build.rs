@@ -0,0 +1,4 @@
+// Embedded migrations are only picked up on rebuild, so make sure new ones trigger one.
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
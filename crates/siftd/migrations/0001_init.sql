@@ -0,0 +1,32 @@
+CREATE TABLE IF NOT EXISTS feeds (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    url        TEXT    NOT NULL UNIQUE,
+    title      TEXT    NOT NULL,
+    site_link  TEXT,
+    created_at TEXT    NOT NULL,
+    updated_at TEXT    NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS entries (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    url        TEXT    NOT NULL UNIQUE,
+    feed_id    INTEGER REFERENCES feeds (id) ON DELETE SET NULL,
+    title      TEXT    NOT NULL,
+    origin     TEXT    NOT NULL,
+    author     TEXT    NOT NULL,
+    content    TEXT    NOT NULL,
+    created_at TEXT    NOT NULL,
+    updated_at TEXT    NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS entries_feed_id ON entries (feed_id);
+
+CREATE TABLE IF NOT EXISTS entry_metadata (
+    entry_id       INTEGER PRIMARY KEY REFERENCES entries (id) ON DELETE CASCADE,
+    summary        TEXT,
+    thumbnail_url  TEXT,
+    published_time TEXT,
+    updated_time   TEXT
+);
+
+CREATE INDEX IF NOT EXISTS entry_metadata_published_time ON entry_metadata (published_time);
@@ -0,0 +1,20 @@
+-- Entries are identified by their canonicalised URL (see `libsift::canonical`), and every URL an
+-- entry has been observed under is kept as an alias. Existing rows get their raw URL as key.
+ALTER TABLE entries ADD COLUMN canonical_url TEXT;
+
+UPDATE entries SET canonical_url = url WHERE canonical_url IS NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS entries_canonical_url ON entries (canonical_url);
+
+CREATE TABLE IF NOT EXISTS entry_aliases (
+    url           TEXT    PRIMARY KEY,
+    canonical_url TEXT    NOT NULL,
+    entry_id      INTEGER NOT NULL REFERENCES entries (id) ON DELETE CASCADE,
+    first_seen    TEXT    NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS entry_aliases_canonical_url ON entry_aliases (canonical_url);
+CREATE INDEX IF NOT EXISTS entry_aliases_entry_id ON entry_aliases (entry_id);
+
+INSERT OR IGNORE INTO entry_aliases (url, canonical_url, entry_id, first_seen)
+SELECT url, canonical_url, id, created_at FROM entries;
@@ -0,0 +1,6 @@
+-- SimHash of the entry content (see `libsift::dedup`), bit-cast to a signed integer, and the
+-- entry this one is a near-duplicate of, if any.
+ALTER TABLE entries ADD COLUMN fingerprint INTEGER;
+ALTER TABLE entries ADD COLUMN duplicate_of INTEGER REFERENCES entries (id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS entries_duplicate_of ON entries (duplicate_of);
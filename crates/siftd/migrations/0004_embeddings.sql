@@ -0,0 +1,12 @@
+-- One vector per entry and embedding model, so that switching models only means embedding
+-- every entry again under the new model id.
+CREATE TABLE IF NOT EXISTS embeddings (
+    entry_id   INTEGER NOT NULL REFERENCES entries (id) ON DELETE CASCADE,
+    model_id   TEXT    NOT NULL,
+    dimension  INTEGER NOT NULL,
+    vector     BLOB    NOT NULL,
+    created_at TEXT    NOT NULL,
+    PRIMARY KEY (entry_id, model_id)
+);
+
+CREATE INDEX IF NOT EXISTS embeddings_model_id ON embeddings (model_id);
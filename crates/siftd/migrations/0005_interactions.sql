@@ -0,0 +1,23 @@
+-- Every read/bookmark/like/dislike event and its undoing, in order. `active` is 0 for undos.
+CREATE TABLE IF NOT EXISTS interactions (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    entry_id    INTEGER NOT NULL REFERENCES entries (id) ON DELETE CASCADE,
+    kind        TEXT    NOT NULL,
+    active      INTEGER NOT NULL,
+    occurred_at TEXT    NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS interactions_entry_id ON interactions (entry_id);
+
+-- Current state of each entry as of its latest interactions, each column holding when the entry
+-- entered that state, or NULL if it is not in it. Liked and disliked are mutually exclusive.
+CREATE TABLE IF NOT EXISTS entry_state (
+    entry_id      INTEGER PRIMARY KEY REFERENCES entries (id) ON DELETE CASCADE,
+    read_at       TEXT,
+    bookmarked_at TEXT,
+    liked_at      TEXT,
+    disliked_at   TEXT
+);
+
+CREATE INDEX IF NOT EXISTS entry_state_liked_at ON entry_state (liked_at);
+CREATE INDEX IF NOT EXISTS entry_state_disliked_at ON entry_state (disliked_at);
@@ -0,0 +1,14 @@
+-- Nearest-neighbour list of every entry in the similarity graph of each embedding model. The
+-- mutual graph holds an edge wherever two entries list each other.
+CREATE TABLE IF NOT EXISTS knn_graphs (
+    model_id TEXT    PRIMARY KEY,
+    k        INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS knn_neighbours (
+    model_id     TEXT    NOT NULL REFERENCES knn_graphs (model_id) ON DELETE CASCADE,
+    entry_id     INTEGER NOT NULL REFERENCES entries (id) ON DELETE CASCADE,
+    neighbour_id INTEGER NOT NULL REFERENCES entries (id) ON DELETE CASCADE,
+    similarity   REAL    NOT NULL,
+    PRIMARY KEY (model_id, entry_id, neighbour_id)
+);
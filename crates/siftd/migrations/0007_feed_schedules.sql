@@ -0,0 +1,11 @@
+-- Refresh schedule of each feed, learnt from when its items were posted.
+CREATE TABLE IF NOT EXISTS feed_schedules (
+    feed_id         INTEGER PRIMARY KEY REFERENCES feeds (id) ON DELETE CASCADE,
+    -- Smoothed time between posts, in seconds.
+    mean_gap_secs   REAL,
+    last_post_at    TEXT,
+    last_refresh_at TEXT,
+    next_refresh_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS feed_schedules_next_refresh_at ON feed_schedules (next_refresh_at);
@@ -0,0 +1,3 @@
+-- Validators of the last response for each feed, for conditional refreshes.
+ALTER TABLE feeds ADD COLUMN etag TEXT;
+ALTER TABLE feeds ADD COLUMN last_modified TEXT;
@@ -0,0 +1,2 @@
+-- Feeds whose owner opted out of robots.txt checks for the feed and its items.
+ALTER TABLE feeds ADD COLUMN ignore_robots INTEGER NOT NULL DEFAULT 0;
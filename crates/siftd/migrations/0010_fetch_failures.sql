@@ -0,0 +1,12 @@
+-- URLs whose last fetch failed for good, and why.
+CREATE TABLE IF NOT EXISTS fetch_failures (
+    url             TEXT PRIMARY KEY,
+    class           TEXT NOT NULL,
+    -- Status of the response, for HTTP errors.
+    status          INTEGER,
+    message         TEXT NOT NULL,
+    -- Failures in a row.
+    failures        INTEGER NOT NULL DEFAULT 1,
+    first_failed_at TEXT NOT NULL,
+    last_failed_at  TEXT NOT NULL
+);
@@ -0,0 +1,3 @@
+-- The entry content as plain text, for embedding and fingerprinting; `content` is Markdown.
+-- Entries stored before content was Markdown have none, their content being plain text already.
+ALTER TABLE entries ADD COLUMN text TEXT;
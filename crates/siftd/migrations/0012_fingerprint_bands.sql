@@ -0,0 +1,19 @@
+-- The fingerprint split into four 16-bit bands (see `libsift::dedup`). Fingerprints within three
+-- bits of each other agree on at least one band, so near-duplicate candidates are looked up by
+-- band instead of comparing against every fingerprint.
+ALTER TABLE entries ADD COLUMN fingerprint_band0 INTEGER;
+ALTER TABLE entries ADD COLUMN fingerprint_band1 INTEGER;
+ALTER TABLE entries ADD COLUMN fingerprint_band2 INTEGER;
+ALTER TABLE entries ADD COLUMN fingerprint_band3 INTEGER;
+
+UPDATE entries SET
+    fingerprint_band0 = fingerprint & 65535,
+    fingerprint_band1 = (fingerprint >> 16) & 65535,
+    fingerprint_band2 = (fingerprint >> 32) & 65535,
+    fingerprint_band3 = (fingerprint >> 48) & 65535
+WHERE fingerprint IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS entries_fingerprint_band0 ON entries (fingerprint_band0);
+CREATE INDEX IF NOT EXISTS entries_fingerprint_band1 ON entries (fingerprint_band1);
+CREATE INDEX IF NOT EXISTS entries_fingerprint_band2 ON entries (fingerprint_band2);
+CREATE INDEX IF NOT EXISTS entries_fingerprint_band3 ON entries (fingerprint_band3);
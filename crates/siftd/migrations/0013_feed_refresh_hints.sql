@@ -0,0 +1,5 @@
+-- What the last full response for each feed said about how often to poll it, in seconds, so
+-- that a refresh answered with 304 Not Modified is scheduled as if the feed had been served.
+ALTER TABLE feeds ADD COLUMN ttl_secs INTEGER;
+ALTER TABLE feeds ADD COLUMN update_period_secs INTEGER;
+ALTER TABLE feeds ADD COLUMN max_age_secs INTEGER;
@@ -1,6 +1,21 @@
 use std::path::PathBuf;
 
 use clap::{Parser, ValueEnum};
+use libsift::{
+    dedup::NearDuplicates,
+    embedding::OpenAiEmbedder,
+    fetcher::FetchLimits,
+    graph::{FastPpr, KnnGraph},
+    handler::AppState,
+    http::HttpConfig,
+    ratelimit::HostLimits,
+    recommender::Recommender,
+    retry::Backoff,
+    robots::RobotsCache,
+    scheduler::Scheduler,
+};
+use reqwest::header::{HeaderName, HeaderValue};
+use url::Url;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -8,6 +23,147 @@ pub struct Cli {
     #[arg(short, long, default_value_t = 3000)]
     pub port: i16,
 
+    /// SQLite database to store feeds and entries in; created if missing
+    #[arg(long, default_value = "sqlite://sift.db")]
+    pub database_url: String,
+
+    /// Maximum SimHash distance (in bits) at which two entries count as near-duplicates; above 3,
+    /// every new entry is compared against every other
+    #[arg(long, default_value_t = NearDuplicates::DEFAULT_MAX_DISTANCE)]
+    pub duplicate_distance: u32,
+
+    /// Backend used to embed entries
+    #[arg(long, value_enum, default_value_t = EmbedderKind::Hashing)]
+    pub embedder: EmbedderKind,
+
+    /// Vector length of the hashing embedder
+    #[arg(long, default_value_t = 256)]
+    pub hashing_dimension: usize,
+
+    /// Directory holding config.json, tokenizer.json and model.safetensors of the local embedder
+    #[arg(long)]
+    pub embedding_model_dir: Option<PathBuf>,
+
+    /// Base URL of the OpenAI-compatible embedding API, e.g. http://localhost:8080/v1
+    #[arg(long)]
+    pub embedding_url: Option<Url>,
+
+    /// Model requested from the OpenAI-compatible embedding API
+    #[arg(long)]
+    pub embedding_model: Option<String>,
+
+    /// API key for the OpenAI-compatible embedding API; falls back to OPENAI_API_KEY
+    #[arg(long)]
+    pub embedding_api_key: Option<String>,
+
+    /// Texts sent per request to the OpenAI-compatible embedding API
+    #[arg(long, default_value_t = OpenAiEmbedder::DEFAULT_BATCH_SIZE)]
+    pub embedding_batch_size: usize,
+
+    /// Timeout in seconds of each request to the OpenAI-compatible embedding API
+    #[arg(long, default_value_t = OpenAiEmbedder::DEFAULT_TIMEOUT.as_secs())]
+    pub embedding_timeout_secs: u64,
+
+    /// Retries of failed requests to the OpenAI-compatible embedding API
+    #[arg(long, default_value_t = Backoff::DEFAULT_RETRIES)]
+    pub embedding_retries: u32,
+
+    /// Neighbours per entry in the mutual k-nearest-neighbour similarity graph
+    #[arg(long, default_value_t = KnnGraph::DEFAULT_K)]
+    pub knn_k: usize,
+
+    /// Days after which a like or dislike counts half as much in recommendations
+    #[arg(long, default_value_t = Recommender::DEFAULT_INTERACTION_HALF_LIFE.num_days())]
+    pub interaction_half_life_days: i64,
+
+    /// Hours after publication after which an entry counts as half as fresh in recommendations
+    #[arg(long, default_value_t = Recommender::DEFAULT_FRESHNESS_HALF_LIFE.num_hours())]
+    pub freshness_half_life_hours: i64,
+
+    /// Probability of the Personalised PageRank walks teleporting back to liked entries, in (0, 1]
+    #[arg(long, default_value_t = FastPpr::DEFAULT_TELEPORT, value_parser = parse_teleport)]
+    pub ppr_teleport: f64,
+
+    /// Maximum number of background fetches in flight
+    #[arg(long, default_value_t = FetchLimits::DEFAULT_GLOBAL)]
+    pub fetch_concurrency: usize,
+
+    /// Maximum number of background fetches in flight per host
+    #[arg(long, default_value_t = FetchLimits::DEFAULT_PER_HOST)]
+    pub fetch_concurrency_per_host: usize,
+
+    /// Minimum time between refreshes of a feed, in minutes
+    #[arg(long, default_value_t = Scheduler::DEFAULT_MIN_INTERVAL.num_minutes())]
+    pub min_refresh_minutes: i64,
+
+    /// Maximum time between refreshes of a feed, in minutes
+    #[arg(long, default_value_t = Scheduler::DEFAULT_MAX_INTERVAL.num_minutes())]
+    pub max_refresh_minutes: i64,
+
+    /// Seconds to wait for a connection to be established
+    #[arg(long, default_value_t = HttpConfig::DEFAULT_CONNECT_TIMEOUT.as_secs())]
+    pub http_connect_timeout_secs: u64,
+
+    /// Seconds to wait for the next bytes of a response
+    #[arg(long, default_value_t = HttpConfig::DEFAULT_READ_TIMEOUT.as_secs())]
+    pub http_read_timeout_secs: u64,
+
+    /// Seconds a whole request, body included, may take
+    #[arg(long, default_value_t = HttpConfig::DEFAULT_TIMEOUT.as_secs())]
+    pub http_timeout_secs: u64,
+
+    /// Redirects followed before giving up; 0 follows none
+    #[arg(long, default_value_t = HttpConfig::DEFAULT_MAX_REDIRECTS)]
+    pub http_max_redirects: usize,
+
+    /// Proxy for every request, e.g. http://proxy:3128 or socks5://127.0.0.1:9050
+    #[arg(long)]
+    pub http_proxy: Option<Url>,
+
+    /// User-Agent header sent with every request
+    #[arg(long, default_value = HttpConfig::DEFAULT_USER_AGENT)]
+    pub user_agent: String,
+
+    /// Extra header sent with every request, as "NAME: VALUE"; may be repeated
+    #[arg(long = "http-header", value_name = "NAME: VALUE", value_parser = parse_header)]
+    pub http_headers: Vec<(HeaderName, HeaderValue)>,
+
+    /// Responses longer than this many bytes are refused
+    #[arg(long, default_value_t = HttpConfig::DEFAULT_MAX_BODY_BYTES)]
+    pub max_body_bytes: usize,
+
+    /// Product token matched against `User-agent` lines in robots.txt
+    #[arg(long, default_value = RobotsCache::DEFAULT_USER_AGENT)]
+    pub robots_user_agent: String,
+
+    /// Longest crawl delay honoured, in seconds; longer ones are shortened to this
+    #[arg(long, default_value_t = RobotsCache::DEFAULT_MAX_CRAWL_DELAY.as_secs())]
+    pub max_crawl_delay_secs: u64,
+
+    /// Sustained requests per second to any one host
+    #[arg(long, default_value_t = HostLimits::DEFAULT_RATE, value_parser = parse_rate)]
+    pub host_rate: f64,
+
+    /// Requests to one host allowed in a burst after a quiet spell
+    #[arg(long, default_value_t = HostLimits::DEFAULT_BURST)]
+    pub host_burst: u32,
+
+    /// Maximum number of requests in flight to one host, from any source
+    #[arg(long, default_value_t = HostLimits::DEFAULT_MAX_IN_FLIGHT)]
+    pub host_max_in_flight: usize,
+
+    /// Limits for one host, as HOST=RATE[/BURST[/IN_FLIGHT]]; may be repeated
+    #[arg(long = "host-limit", value_name = "HOST=RATE[/BURST[/IN_FLIGHT]]", value_parser = parse_host_limit)]
+    pub host_limits: Vec<HostLimitArg>,
+
+    /// Pages of a multi-page article followed and stitched into one entry, the first included
+    #[arg(long, default_value_t = AppState::DEFAULT_MAX_ARTICLE_PAGES)]
+    pub max_article_pages: usize,
+
+    /// JSON file of per-site extraction rules, for sites the generic heuristics get wrong
+    #[arg(long)]
+    pub site_rules: Option<PathBuf>,
+
     // Logging controls
     /// Base log level for `siftd` and `libsift` (others remain warn)
     #[arg(long, value_enum)]
@@ -42,6 +198,84 @@ pub struct Cli {
     pub log_queries: bool,
 }
 
+/// Per-host override of the defaults; omitted parts keep their default.
+#[derive(Clone, Debug)]
+pub struct HostLimitArg {
+    pub host: String,
+    pub rate: f64,
+    pub burst: Option<u32>,
+    pub max_in_flight: Option<usize>,
+}
+
+fn parse_host_limit(s: &str) -> Result<HostLimitArg, String> {
+    let (host, limits) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected HOST=RATE[/BURST[/IN_FLIGHT]], got {s:?}"))?;
+    let mut parts = limits.split('/');
+    let rate = parse_rate(parts.next().unwrap_or_default())
+        .map_err(|e| format!("invalid rate in {s:?}: {e}"))?;
+    let burst = parts
+        .next()
+        .map(str::parse)
+        .transpose()
+        .map_err(|e| format!("invalid burst in {s:?}: {e}"))?;
+    let max_in_flight = parts
+        .next()
+        .map(str::parse)
+        .transpose()
+        .map_err(|e| format!("invalid in-flight limit in {s:?}: {e}"))?;
+    if parts.next().is_some() {
+        return Err(format!("too many limits in {s:?}"));
+    }
+    Ok(HostLimitArg {
+        host: host.trim().to_string(),
+        rate,
+        burst,
+        max_in_flight,
+    })
+}
+
+fn parse_rate(s: &str) -> Result<f64, String> {
+    let rate = s.parse::<f64>().map_err(|e| e.to_string())?;
+    if rate.is_finite() && rate > 0.0 {
+        Ok(rate)
+    } else {
+        Err(format!("must be positive, got {rate}"))
+    }
+}
+
+fn parse_teleport(s: &str) -> Result<f64, String> {
+    let teleport = s.parse::<f64>().map_err(|e| e.to_string())?;
+    if teleport > 0.0 && teleport <= 1.0 {
+        Ok(teleport)
+    } else {
+        Err(format!("must be in (0, 1], got {teleport}"))
+    }
+}
+
+fn parse_header(s: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected NAME: VALUE, got {s:?}"))?;
+    let name = HeaderName::from_bytes(name.trim().as_bytes())
+        .map_err(|e| format!("invalid header name in {s:?}: {e}"))?;
+    let value = HeaderValue::from_str(value.trim())
+        .map_err(|e| format!("invalid header value in {s:?}: {e}"))?;
+    Ok((name, value))
+}
+
+#[derive(Copy, Clone, Debug, Default, ValueEnum)]
+pub enum EmbedderKind {
+    /// Deterministic hashing trick over words; no model required
+    #[default]
+    Hashing,
+    /// Sentence-transformer run on the CPU (requires the `candle` feature)
+    Local,
+    /// Any server speaking the OpenAI embeddings protocol
+    #[value(name = "openai")]
+    OpenAi,
+}
+
 #[derive(Copy, Clone, Debug, ValueEnum)]
 pub enum LogLevel {
     Error,
@@ -1,9 +1,34 @@
-use std::{io::IsTerminal as _, path::Path};
+use std::{io::IsTerminal as _, path::Path, sync::Arc, time::Duration};
 
-use axum::{routing::post, Router};
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use chrono::TimeDelta;
 use clap::Parser;
 use color_eyre::{eyre::eyre, Result};
-use libsift::handler::url::handle_url;
+use libsift::{
+    dedup::NearDuplicates,
+    embedding::{self, Embedder, HashingEmbedder, OpenAiEmbedder},
+    fetcher::{FetchLimits, FetchQueue, Fetcher},
+    graph::{FastPpr, SimilarityGraph},
+    handler::{
+        entries::{get_entry, get_neighbours, list_entries},
+        feed::{handle_feed, refresh_feed},
+        interactions::{list_interactions, record_interaction, undo_interaction},
+        recommendations::get_recommendations,
+        url::handle_url,
+        AppState,
+    },
+    http::{HttpClient, HttpConfig},
+    parser::rules::SiteRules,
+    ratelimit::{HostLimiter, HostLimits},
+    recommender::Recommender,
+    retry::Backoff,
+    robots::RobotsCache,
+    scheduler::Scheduler,
+    storage::Storage,
+};
 use tower_http::{
     request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
     trace::TraceLayer,
@@ -13,7 +38,7 @@ use tracing_error::ErrorLayer;
 use tracing_log::LogTracer;
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
-use crate::cli::{Cli, ColorChoice, LogFormat, LogLevel};
+use crate::cli::{Cli, ColorChoice, EmbedderKind, LogFormat, LogLevel};
 
 mod cli;
 
@@ -28,6 +53,97 @@ async fn main() -> Result<()> {
     LogTracer::init().ok();
     init_tracing(&cli)?;
 
+    let host_limits = HostLimits {
+        rate: cli.host_rate,
+        burst: cli.host_burst,
+        max_in_flight: cli.host_max_in_flight,
+    };
+    let limiter = cli
+        .host_limits
+        .iter()
+        .fold(HostLimiter::new(host_limits), |limiter, arg| {
+            limiter.with_override(
+                arg.host.clone(),
+                HostLimits {
+                    rate: arg.rate,
+                    burst: arg.burst.unwrap_or(host_limits.burst),
+                    max_in_flight: arg.max_in_flight.unwrap_or(host_limits.max_in_flight),
+                },
+            )
+        });
+
+    let site_rules = match &cli.site_rules {
+        Some(path) => {
+            let site_rules = SiteRules::load(path)?;
+            info!(rules = site_rules.len(), path = %path.display(), "site rules loaded");
+            site_rules
+        }
+        None => SiteRules::default(),
+    };
+
+    let http = HttpConfig {
+        connect_timeout: Duration::from_secs(cli.http_connect_timeout_secs),
+        read_timeout: Duration::from_secs(cli.http_read_timeout_secs),
+        timeout: Duration::from_secs(cli.http_timeout_secs),
+        max_redirects: cli.http_max_redirects,
+        proxy: cli.http_proxy.clone(),
+        user_agent: cli.user_agent.clone(),
+        headers: cli.http_headers.iter().cloned().collect(),
+        max_body_bytes: cli.max_body_bytes,
+    }
+    .build()?
+    .with_robots(
+        RobotsCache::new(cli.robots_user_agent.clone())
+            .with_max_crawl_delay(Duration::from_secs(cli.max_crawl_delay_secs)),
+    )
+    .with_limiter(limiter);
+
+    let storage = Storage::connect(&cli.database_url)
+        .await?
+        .with_near_duplicates(NearDuplicates::new(
+            cli.duplicate_distance,
+            NearDuplicates::DEFAULT_MIN_WORDS,
+        ));
+    let embedder = build_embedder(&cli, &http).await?;
+    info!(model = embedder.model_id(), "embedder ready");
+    let graph = SimilarityGraph::load(&storage, embedder.model_id(), cli.knn_k).await?;
+    tokio::spawn(embedding::embed_missing(
+        embedder.clone(),
+        storage.clone(),
+        graph.clone(),
+    ));
+    let recommender = Recommender {
+        ppr: FastPpr {
+            teleport: cli.ppr_teleport,
+            ..FastPpr::default()
+        },
+        interaction_half_life: TimeDelta::days(cli.interaction_half_life_days),
+        freshness_half_life: TimeDelta::hours(cli.freshness_half_life_hours),
+        ..Recommender::default()
+    };
+    let scheduler = Scheduler {
+        min_interval: TimeDelta::minutes(cli.min_refresh_minutes),
+        max_interval: TimeDelta::minutes(cli.max_refresh_minutes),
+        ..Scheduler::default()
+    };
+    let fetch_queue = FetchQueue::default();
+    let state = AppState::new(storage, embedder, graph)
+        .with_recommender(recommender)
+        .with_fetch_queue(fetch_queue.clone())
+        .with_scheduler(scheduler)
+        .with_http_client(http)
+        .with_site_rules(site_rules)
+        .with_max_article_pages(cli.max_article_pages);
+    let fetcher = Fetcher::new(
+        fetch_queue.clone(),
+        FetchLimits {
+            global: cli.fetch_concurrency,
+            per_host: cli.fetch_concurrency_per_host,
+        },
+    );
+    tokio::spawn(fetcher.run(state.clone()));
+    tokio::spawn(scheduler.run(state.storage().clone(), fetch_queue));
+
     let header = axum::http::HeaderName::from_static("x-request-id");
     let include_queries = cli.log_queries;
     let trace_layer = {
@@ -73,9 +189,21 @@ async fn main() -> Result<()> {
 
     let app = Router::new()
         .route("/url", post(handle_url))
+        .route("/feed", post(handle_feed))
+        .route("/feeds/{id}/refresh", post(refresh_feed))
+        .route("/entries", get(list_entries))
+        .route("/entries/{id}", get(get_entry))
+        .route("/recommendations", get(get_recommendations))
+        .route("/entries/{id}/interactions", get(list_interactions))
+        .route("/entries/{id}/neighbours", get(get_neighbours))
+        .route(
+            "/entries/{id}/{interaction}",
+            post(record_interaction).delete(undo_interaction),
+        )
         .layer(PropagateRequestIdLayer::new(header.clone()))
         .layer(SetRequestIdLayer::new(header, MakeRequestUuid))
-        .layer(trace_layer);
+        .layer(trace_layer)
+        .with_state(state);
 
     let bind_addr = format!("localhost:{}", cli.port);
     let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
@@ -85,6 +213,49 @@ async fn main() -> Result<()> {
     result
 }
 
+async fn build_embedder(cli: &Cli, http: &HttpClient) -> Result<Arc<dyn Embedder>> {
+    match cli.embedder {
+        EmbedderKind::Hashing => Ok(Arc::new(HashingEmbedder::new(cli.hashing_dimension))),
+        #[cfg(feature = "candle")]
+        EmbedderKind::Local => {
+            let dir = cli
+                .embedding_model_dir
+                .as_ref()
+                .ok_or_else(|| eyre!("--embedding-model-dir is required for the local embedder"))?;
+            Ok(Arc::new(embedding::LocalEmbedder::load(dir)?))
+        }
+        #[cfg(not(feature = "candle"))]
+        EmbedderKind::Local => Err(eyre!(
+            "the local embedder requires siftd to be built with the `candle` feature"
+        )),
+        EmbedderKind::OpenAi => {
+            let url = cli
+                .embedding_url
+                .as_ref()
+                .ok_or_else(|| eyre!("--embedding-url is required for the openai embedder"))?;
+            let model = cli
+                .embedding_model
+                .as_ref()
+                .ok_or_else(|| eyre!("--embedding-model is required for the openai embedder"))?;
+            let api_key = cli
+                .embedding_api_key
+                .clone()
+                .or_else(|| std::env::var("OPENAI_API_KEY").ok());
+            let embedder = OpenAiEmbedder::new(http.client().clone(), url, model)
+                .with_api_key(api_key)
+                .with_batch_size(cli.embedding_batch_size)
+                .with_timeout(Duration::from_secs(cli.embedding_timeout_secs))
+                .with_backoff(Backoff {
+                    retries: cli.embedding_retries,
+                    ..Backoff::default()
+                })
+                .probe()
+                .await?;
+            Ok(Arc::new(embedder))
+        }
+    }
+}
+
 fn init_tracing(cli: &Cli) -> Result<()> {
     // Determine env filter precedence: CLI filter > SIFT_LOG/RUST_LOG > constructed defaults
     let env_filter = if let Some(f) = &cli.log_filter {
@@ -152,6 +323,7 @@ fn init_tracing(cli: &Cli) -> Result<()> {
                 .with(
                     fmt::layer()
                         .event_format(fmt::format().json().flatten_event(true))
+                        .fmt_fields(fmt::format::JsonFields::new())
                         .with_ansi(false)
                         .with_writer(non_blocking),
                 )
@@ -164,6 +336,7 @@ fn init_tracing(cli: &Cli) -> Result<()> {
                 .with(
                     fmt::layer()
                         .event_format(fmt::format().json().flatten_event(true))
+                        .fmt_fields(fmt::format::JsonFields::new())
                         .with_ansi(false)
                         .with_writer(std::io::stderr),
                 )
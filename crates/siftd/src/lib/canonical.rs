@@ -0,0 +1,221 @@
+//! URL canonicalisation, so that the same article reached through tracking links, AMP variants,
+//! trailing slashes or `http`/`https` mirrors maps onto a single entry.
+//!
+//! There are two levels of normalisation: [`clean`] only removes what can never change the page
+//! being served (tracking parameters and fragments), so its result is still safe to fetch, whereas
+//! [`canonicalise`] produces an identity key which need not be fetchable.
+
+use url::Url;
+
+/// Query parameters which only serve to track where a click came from.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid",
+    "gclid",
+    "dclid",
+    "msclkid",
+    "yclid",
+    "twclid",
+    "igshid",
+    "mc_cid",
+    "mc_eid",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "oly_anon_id",
+    "oly_enc_id",
+    "vero_id",
+    "wickedid",
+    "ref_src",
+    "cmpid",
+    "pk_campaign",
+    "pk_kwd",
+    "pk_source",
+];
+
+/// Query parameter prefixes which only serve to track where a click came from.
+const TRACKING_PREFIXES: &[&str] = &["utm_", "mtm_", "__twitter"];
+
+/// Query parameters selecting the AMP rendition of a page.
+const AMP_PARAMS: &[&str] = &["amp", "outputtype", "amp_js_v", "usqp"];
+
+fn is_tracking_param(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    TRACKING_PARAMS.contains(&key.as_str()) || TRACKING_PREFIXES.iter().any(|p| key.starts_with(p))
+}
+
+/// Remove tracking query parameters and the fragment. The result still points at the same
+/// resource and can be fetched in place of `url`.
+pub fn clean(url: &Url) -> Url {
+    let mut out = url.clone();
+    out.set_fragment(None);
+    retain_query(&mut out, |k, _| !is_tracking_param(k));
+    out
+}
+
+/// Produce the identity key of `url`. Two URLs with the same key are considered to refer to the
+/// same article.
+///
+/// On top of [`clean`], this
+/// - upgrades `http` to `https` and drops default ports,
+/// - strips `www.`, `m.` and `amp.` host prefixes,
+/// - strips a leading or trailing `amp` path segment, `.amp` suffixes and AMP query parameters,
+/// - collapses repeated slashes and drops trailing ones, and
+/// - sorts the remaining query parameters.
+pub fn canonicalise(url: &Url) -> Url {
+    let mut out = clean(url);
+    if !matches!(out.scheme(), "http" | "https") {
+        return out;
+    }
+
+    if out.scheme() == "http" {
+        // Both schemes are special, so this cannot fail.
+        let _ = out.set_scheme("https");
+    }
+    if matches!(out.port(), Some(80 | 443)) {
+        let _ = out.set_port(None);
+    }
+
+    if let Some(host) = out.host_str() {
+        let stripped = ["www.", "m.", "amp."]
+            .iter()
+            .find_map(|p| host.strip_prefix(p))
+            .filter(|rest| rest.contains('.'))
+            .map(str::to_owned);
+        if let Some(stripped) = stripped {
+            let _ = out.set_host(Some(&stripped));
+        }
+    }
+
+    let mut segments = out
+        .path()
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    // AMP renditions live at `/amp/<path>` or `<path>/amp`; elsewhere `amp` is just a word.
+    if segments.len() > 1 && segments[segments.len() - 1].eq_ignore_ascii_case("amp") {
+        segments.pop();
+    }
+    if segments.len() > 1 && segments[0].eq_ignore_ascii_case("amp") {
+        segments.remove(0);
+    }
+    let mut path = format!("/{}", segments.join("/"));
+    for suffix in [".amp", ".amp.html"] {
+        if let Some(p) = path.strip_suffix(suffix) {
+            path = p.to_string();
+        }
+    }
+    out.set_path(&path);
+
+    retain_query(&mut out, |k, _| {
+        !AMP_PARAMS.contains(&k.to_ascii_lowercase().as_str())
+    });
+    let mut pairs = out
+        .query_pairs()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect::<Vec<_>>();
+    if !pairs.is_empty() {
+        pairs.sort();
+        out.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    out
+}
+
+fn retain_query(url: &mut Url, keep: impl Fn(&str, &str) -> bool) {
+    if url.query().is_none() {
+        return;
+    }
+    let total = url.query_pairs().count();
+    let pairs = url
+        .query_pairs()
+        .filter(|(k, v)| keep(k, v))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect::<Vec<_>>();
+    // Re-serialising may change the encoding of the remaining pairs, so leave the query alone
+    // unless something was actually removed.
+    if pairs.len() == total {
+        return;
+    }
+    if pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(f: fn(&Url) -> Url, cases: &[(&str, &str)]) {
+        for (input, expected) in cases {
+            let actual = f(&Url::parse(input).unwrap());
+            assert_eq!(actual.as_str(), *expected, "for {input}");
+        }
+    }
+
+    #[test]
+    fn clean_removes_tracking_and_fragments_only() {
+        check(
+            clean,
+            &[
+                (
+                    "https://example.com/a?utm_source=x&id=3&fbclid=y#top",
+                    "https://example.com/a?id=3",
+                ),
+                (
+                    "https://example.com/a?UTM_Medium=x",
+                    "https://example.com/a",
+                ),
+                // Untouched queries keep their encoding.
+                (
+                    "https://example.com/a?q=a+b&x=%7E",
+                    "https://example.com/a?q=a+b&x=%7E",
+                ),
+                (
+                    "http://www.example.com/amp/a/",
+                    "http://www.example.com/amp/a/",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn canonicalise_merges_variants() {
+        check(
+            canonicalise,
+            &[
+                ("http://www.example.com:80/a/", "https://example.com/a"),
+                ("https://m.example.com//a//b", "https://example.com/a/b"),
+                ("https://amp.example.com/a", "https://example.com/a"),
+                ("https://www.com/a", "https://www.com/a"),
+                ("https://example.com/a/amp/", "https://example.com/a"),
+                ("https://example.com/amp/a", "https://example.com/a"),
+                ("https://example.com/a.amp.html", "https://example.com/a"),
+                (
+                    "https://example.com/a?amp=1&b=2&a=1",
+                    "https://example.com/a?a=1&b=2",
+                ),
+                (
+                    "https://example.com/a?utm_campaign=z#c",
+                    "https://example.com/a",
+                ),
+                ("mailto:someone@example.com", "mailto:someone@example.com"),
+            ],
+        );
+    }
+
+    #[test]
+    fn canonicalise_keeps_amp_as_a_word() {
+        check(
+            canonicalise,
+            &[
+                (
+                    "https://example.com/tags/amp/widgets",
+                    "https://example.com/tags/amp/widgets",
+                ),
+                ("https://example.com/amp", "https://example.com/amp"),
+            ],
+        );
+    }
+}
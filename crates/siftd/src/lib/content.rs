@@ -1,11 +1,28 @@
-use std::marker::PhantomData;
+use std::{fmt, marker::PhantomData, time::Duration};
 
 use bytes::Bytes;
+use chrono::TimeDelta;
+use reqwest::{
+    header::{self, HeaderMap},
+    StatusCode,
+};
+use serde::Serialize;
 use thiserror::Error;
-use tracing::{debug, info, instrument};
+use tracing::{debug, info, instrument, warn};
 use url::Url;
 
-use crate::{entry::Entry, metadata::Metadata, parser::identify, HTTP_CLIENT};
+use crate::{
+    canonical,
+    entry::Entry,
+    feed::{self, Feed, RefreshHints},
+    http::{self, HttpClient},
+    metadata::Metadata,
+    parser::{
+        identify, pagination,
+        rules::{SiteRule, SiteRules},
+    },
+    retry::Backoff,
+};
 
 pub trait ContentState {}
 pub struct Content<S>
@@ -14,8 +31,15 @@ where
 {
     url: Url,
     bytes: Option<Bytes>,
-    headers: Option<reqwest::header::HeaderMap>,
+    headers: Option<HeaderMap>,
     metadata: Metadata,
+    /// URLs this content was reached through before `url`, _e.g._ redirects.
+    aliases: Vec<Url>,
+    /// Feed the content was found in, if any.
+    feed_id: Option<i64>,
+    /// Fetch even if `robots.txt` disallows it.
+    ignore_robots: bool,
+    backoff: Backoff,
     _state: PhantomData<S>,
 }
 
@@ -23,22 +47,61 @@ impl<S> Content<S>
 where
     S: ContentState,
 {
+    /// Tracking parameters and fragments are stripped from `url` before it is fetched; the
+    /// original is kept as an alias.
     pub fn new(url: Url, metadata: Option<Metadata>) -> Content<Unfetched> {
         let metadata = metadata.unwrap_or_default();
+        let cleaned = canonical::clean(&url);
+        let aliases = if cleaned == url { vec![] } else { vec![url] };
         Content {
-            url,
+            url: cleaned,
             bytes: None,
             headers: None,
             metadata,
+            aliases,
+            feed_id: None,
+            ignore_robots: false,
+            backoff: Backoff::default(),
             _state: PhantomData::<Unfetched>,
         }
     }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn feed_id(&self) -> Option<i64> {
+        self.feed_id
+    }
+
+    /// Remember that the content was found in feed `feed_id`.
+    pub fn with_feed_id(mut self, feed_id: Option<i64>) -> Self {
+        self.feed_id = feed_id;
+        self
+    }
+
+    /// Skip the `robots.txt` check and crawl delay, for feeds whose owner opted out of them.
+    pub fn with_ignore_robots(mut self, ignore_robots: bool) -> Self {
+        self.ignore_robots = ignore_robots;
+        self
+    }
 }
 
 #[derive(Debug, Error)]
 pub enum ContentError {
-    #[error("Failed to fetch URL {url}: {error}")]
-    FetchError { error: reqwest::Error, url: String },
+    #[error("Failed to fetch URL {url}: {class}{}", status.map(|s| format!(" ({s})")).unwrap_or_default())]
+    FetchError {
+        url: String,
+        class: ErrorClass,
+        /// Status of the response, for HTTP errors.
+        status: Option<StatusCode>,
+        #[source]
+        source: Option<reqwest::Error>,
+    },
+    #[error("Disallowed by robots.txt. URL: {url}")]
+    Disallowed { url: String },
+    #[error("Rate limited, retry after {}s. URL: {url}", retry_after.as_secs())]
+    RateLimited { url: String, retry_after: Duration },
     #[error("Failed to parse body. URL: {url}")]
     ParseError {
         url: String,
@@ -47,58 +110,378 @@ pub enum ContentError {
     },
 }
 
+/// What kind of failure a [`ContentError`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    Dns,
+    Connect,
+    Timeout,
+    Tls,
+    /// The server answered with a 4xx status other than 429, which is [`ErrorClass::RateLimited`].
+    ClientError,
+    /// The server answered with a 5xx status.
+    ServerError,
+    BodyTooLarge,
+    Disallowed,
+    RateLimited,
+    Parse,
+    /// Reading or writing our own database failed; never the class of a [`ContentError`].
+    Storage,
+    Other,
+}
+
+impl ErrorClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorClass::Dns => "dns",
+            ErrorClass::Connect => "connect",
+            ErrorClass::Timeout => "timeout",
+            ErrorClass::Tls => "tls",
+            ErrorClass::ClientError => "client_error",
+            ErrorClass::ServerError => "server_error",
+            ErrorClass::BodyTooLarge => "body_too_large",
+            ErrorClass::Disallowed => "disallowed",
+            ErrorClass::RateLimited => "rate_limited",
+            ErrorClass::Parse => "parse",
+            ErrorClass::Storage => "storage",
+            ErrorClass::Other => "other",
+        }
+    }
+
+    /// Class of a failed request. DNS and TLS failures surface as connection errors, told apart
+    /// only by their causes.
+    fn of(error: &reqwest::Error) -> Self {
+        if error.is_timeout() {
+            return ErrorClass::Timeout;
+        }
+        if error.is_connect() {
+            let mut source = std::error::Error::source(error);
+            while let Some(cause) = source {
+                let message = cause.to_string().to_ascii_lowercase();
+                if message.contains("dns error") || message.contains("failed to lookup address") {
+                    return ErrorClass::Dns;
+                }
+                if ["certificate", "tls", "ssl", "handshake"]
+                    .iter()
+                    .any(|m| message.contains(m))
+                {
+                    return ErrorClass::Tls;
+                }
+                source = cause.source();
+            }
+            return ErrorClass::Connect;
+        }
+        match error.status() {
+            Some(StatusCode::TOO_MANY_REQUESTS) => ErrorClass::RateLimited,
+            Some(status) if status.is_client_error() => ErrorClass::ClientError,
+            Some(status) if status.is_server_error() => ErrorClass::ServerError,
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ContentError {
+    pub fn url(&self) -> &str {
+        match self {
+            ContentError::FetchError { url, .. }
+            | ContentError::Disallowed { url }
+            | ContentError::RateLimited { url, .. }
+            | ContentError::ParseError { url, .. } => url,
+        }
+    }
+
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            ContentError::FetchError { class, .. } => *class,
+            ContentError::Disallowed { .. } => ErrorClass::Disallowed,
+            ContentError::RateLimited { .. } => ErrorClass::RateLimited,
+            ContentError::ParseError { .. } => ErrorClass::Parse,
+        }
+    }
+
+    /// Status of the response that was refused, for HTTP errors.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ContentError::FetchError { status, .. } => *status,
+            ContentError::RateLimited { .. } => Some(StatusCode::TOO_MANY_REQUESTS),
+            _ => None,
+        }
+    }
+
+    /// Whether trying again later might succeed. Anything else is as good as permanent.
+    pub fn is_transient(&self) -> bool {
+        match self.class() {
+            ErrorClass::Dns
+            | ErrorClass::Connect
+            | ErrorClass::Timeout
+            | ErrorClass::ServerError
+            | ErrorClass::RateLimited => true,
+            ErrorClass::ClientError => self.status() == Some(StatusCode::REQUEST_TIMEOUT),
+            _ => false,
+        }
+    }
+
+    fn request(url: &Url, error: reqwest::Error) -> Self {
+        ContentError::FetchError {
+            url: url.to_string(),
+            class: ErrorClass::of(&error),
+            status: error.status(),
+            source: Some(error),
+        }
+    }
+}
+
+/// A response, read in full.
+struct Received {
+    status: StatusCode,
+    headers: HeaderMap,
+    url: Url,
+    bytes: Bytes,
+}
+
 pub struct Unfetched;
 impl ContentState for Unfetched {}
 impl Content<Unfetched> {
-    #[instrument(level = "info", skip(self), fields(url_host, url_path))]
-    pub async fn fetch(self) -> Result<Content<Fetched>, ContentError> {
+    /// Retry transient failures according to `backoff` rather than the default.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    #[instrument(level = "info", skip(self, http), fields(url_host, url_path))]
+    pub async fn fetch(self, http: &HttpClient) -> Result<Content<Fetched>, ContentError> {
+        let received = self.request(http, &Validators::default()).await?;
+        Ok(self.into_fetched(received))
+    }
+
+    /// Fetch unless the content is unchanged since the response `validators` were taken from.
+    #[instrument(
+        level = "info",
+        skip(self, http, validators),
+        fields(url_host, url_path)
+    )]
+    pub async fn fetch_if_modified(
+        self,
+        http: &HttpClient,
+        validators: &Validators,
+    ) -> Result<FetchOutcome, ContentError> {
+        let received = self.request(http, validators).await?;
+        if received.status == StatusCode::NOT_MODIFIED && !validators.is_empty() {
+            debug!("not modified");
+            return Ok(FetchOutcome::NotModified {
+                max_age: cache_max_age(&received.headers),
+            });
+        }
+        Ok(FetchOutcome::Modified(Box::new(
+            self.into_fetched(received),
+        )))
+    }
+
+    /// Make the request, retrying transient failures with backoff. A host asking to be retried
+    /// later than the backoff allows is not retried.
+    async fn request(
+        &self,
+        http: &HttpClient,
+        validators: &Validators,
+    ) -> Result<Received, ContentError> {
         let (host, path) = crate::url_host_and_path(&self.url);
         tracing::Span::current().record("url_host", tracing::field::display(&host));
         tracing::Span::current().record("url_path", tracing::field::display(&path));
-        info!(%host, %path, "fetch start");
-        let raw_response = HTTP_CLIENT
-            .get(self.url.as_str())
+        let mut retry = 0;
+        loop {
+            let error = match self.attempt(http, validators).await {
+                Ok(received) => return Ok(received),
+                Err(e) => e,
+            };
+            let too_late = matches!(
+                error,
+                ContentError::RateLimited { retry_after, .. } if retry_after > self.backoff.max
+            );
+            if !error.is_transient() || too_late {
+                return Err(error);
+            }
+            let Some(delay) = self.backoff.delay(retry, &mut rand::rng()) else {
+                return Err(error);
+            };
+            retry += 1;
+            warn!(
+                %host,
+                %path,
+                error = %error,
+                retry,
+                delay_ms = delay.as_millis() as u64,
+                "fetch failed, retrying"
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Make the request once the host's `robots.txt` and rate limits allow it, and read the
+    /// response.
+    async fn attempt(
+        &self,
+        http: &HttpClient,
+        validators: &Validators,
+    ) -> Result<Received, ContentError> {
+        if !self.ignore_robots {
+            let admitted = http
+                .robots()
+                .admit(http, &self.url)
+                .await
+                .map_err(|e| ContentError::request(&self.url, e))?;
+            if !admitted {
+                return Err(ContentError::Disallowed {
+                    url: self.url.to_string(),
+                });
+            }
+        }
+        let limiter = http.limiter();
+        // Held until the body is read.
+        let _permit = limiter.acquire(&self.url).await;
+        let (host, path) = crate::url_host_and_path(&self.url);
+        info!(%host, %path, conditional = !validators.is_empty(), "fetch start");
+        let mut request = http.client().get(self.url.as_str());
+        if let Some(etag) = &validators.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        let mut response = request
             .send()
             .await
-            .map_err(|error| ContentError::FetchError {
-                error,
-                url: self.url.to_string(),
-            })?;
+            .map_err(|e| ContentError::request(&self.url, e))?;
 
-        // TODO: Can this be destructured instead to prevent cloning?
-        let headers = raw_response.headers().clone();
+        let status = response.status();
+        if let Some(retry_after) = limiter.observe(&self.url, status, response.headers()) {
+            return Err(ContentError::RateLimited {
+                url: self.url.to_string(),
+                retry_after,
+            });
+        }
+        if let Err(e) = response.error_for_status_ref() {
+            return Err(ContentError::request(&self.url, e));
+        }
 
-        let bytes = raw_response
-            .bytes()
+        let too_large = || ContentError::FetchError {
+            url: self.url.to_string(),
+            class: ErrorClass::BodyTooLarge,
+            status: None,
+            source: None,
+        };
+        let max_body_bytes = http.max_body_bytes();
+        if response.content_length().unwrap_or(0) > max_body_bytes as u64 {
+            return Err(too_large());
+        }
+        let body = http::read_body(&mut response, max_body_bytes)
             .await
-            .map_err(|error| ContentError::FetchError {
-                error,
-                url: self.url.to_string(),
-            })?;
+            .map_err(|e| ContentError::request(&self.url, e))?;
+        if body.truncated {
+            return Err(too_large());
+        }
 
+        Ok(Received {
+            status,
+            headers: response.headers().clone(),
+            url: response.url().clone(),
+            bytes: body.bytes,
+        })
+    }
+
+    fn into_fetched(self, received: Received) -> Content<Fetched> {
         let Content {
             url,
             metadata,
+            mut aliases,
+            feed_id,
+            ignore_robots,
+            backoff,
             _state: _,
             bytes: _,
             headers: _,
         } = self;
 
-        Ok(Content {
-            headers: Some(headers),
-            bytes: Some(bytes),
+        // Continue under the URL we were redirected to, so that relative links resolve correctly.
+        let final_url = canonical::clean(&received.url);
+        let url = if final_url != url {
+            debug!(%final_url, "followed redirect");
+            aliases.push(url);
+            final_url
+        } else {
+            url
+        };
+
+        Content {
+            headers: Some(received.headers),
+            bytes: Some(received.bytes),
             _state: PhantomData::<Fetched>,
             url,
             metadata,
-        })
+            aliases,
+            feed_id,
+            ignore_robots,
+            backoff,
+        }
+    }
+}
+
+/// Result of a conditional fetch.
+pub enum FetchOutcome {
+    Modified(Box<Content<Fetched>>),
+    /// The server confirmed that nothing changed, so there is nothing to parse.
+    NotModified {
+        /// `max-age` of the response's `Cache-Control` header, which a 304 may update.
+        max_age: Option<TimeDelta>,
+    },
+}
+
+/// What identifies a version of a resource, for making later requests for it conditional.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let get = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            etag: get(header::ETAG),
+            last_modified: get(header::LAST_MODIFIED),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
     }
 }
 
 pub struct Fetched;
 impl ContentState for Fetched {}
 impl Content<Fetched> {
-    #[instrument(level = "info", skip(self), fields(url_host, url_path))]
-    pub fn parse(self) -> Result<Entry, ContentError> {
+    /// Validators of the response, to make the next fetch of the same URL conditional.
+    pub fn validators(&self) -> Validators {
+        self.headers
+            .as_ref()
+            .map(Validators::from_headers)
+            .unwrap_or_default()
+    }
+
+    /// Parse the content as a single entry, following the first of `rules` that applies to it.
+    #[instrument(level = "info", skip(self, rules), fields(url_host, url_path))]
+    pub fn parse(self, rules: &SiteRules) -> Result<Entry, ContentError> {
         let (host, path) = crate::url_host_and_path(&self.url);
         tracing::Span::current().record("url_host", tracing::field::display(&host));
         tracing::Span::current().record("url_path", tracing::field::display(&path));
@@ -121,16 +504,235 @@ impl Content<Fetched> {
         debug!(headers_present = !headers.is_empty());
 
         if let Some(parser) = identify(bytes, headers, &self.url) {
-            let entry = parser.parse().map_err(|e| ContentError::ParseError {
+            let entry =
+                parser
+                    .parse(rules.find(&self.url))
+                    .map_err(|e| ContentError::ParseError {
+                        url: self.url.to_string(),
+                        source: anyhow::Error::from(e),
+                    })?;
+            Ok(entry
+                .with_fallback_metadata(self.metadata.clone())
+                .with_aliases(self.aliases.clone()))
+        } else {
+            Err(ContentError::ParseError {
+                url: self.url.to_string(),
+                source: anyhow::anyhow!("no suitable parser"),
+            })
+        }
+    }
+
+    /// Parse the content and, if it is the first page of a multi-page article, fetch and append
+    /// the pages that follow, up to `max_pages` pages in all. A page that cannot be fetched or
+    /// parsed ends the article; only the failure of the first one is an error.
+    pub async fn parse_pages(
+        self,
+        http: &HttpClient,
+        rules: &SiteRules,
+        max_pages: usize,
+    ) -> Result<Entry, ContentError> {
+        let ignore_robots = self.ignore_robots;
+        let backoff = self.backoff;
+        let first = self.url.clone();
+        let by_rule = rules.find(&first).is_some_and(SiteRule::has_next_page);
+        let mut seen = vec![first.clone()];
+        let mut entry = self.parse(rules)?;
+        while let Some(next) = entry.next_page().cloned() {
+            if seen.len() >= max_pages {
+                debug!(max_pages, "page limit reached");
+                break;
+            }
+            if !by_rule && !pagination::continues(&first, &next) {
+                debug!(%next, "next page is not of the same article");
+                break;
+            }
+            let page = Content::<Unfetched>::new(next, None)
+                .with_ignore_robots(ignore_robots)
+                .with_backoff(backoff);
+            if seen.contains(page.url()) {
+                debug!(url = %page.url(), "pagination loops back");
+                break;
+            }
+            seen.push(page.url().clone());
+            let url = page.url().clone();
+            match page.fetch(http).await.and_then(|page| page.parse(rules)) {
+                Ok(page) => entry.append_page(page),
+                Err(e) => {
+                    warn!(%url, error = %e, "fetching next page failed");
+                    break;
+                }
+            }
+        }
+        if seen.len() > 1 {
+            debug!(pages = seen.len(), "pages stitched");
+        }
+        Ok(entry.with_next_page(None))
+    }
+
+    /// Parse the fetched body as a feed rather than as a single entry.
+    #[instrument(level = "info", skip(self), fields(url_host, url_path))]
+    pub fn parse_feed(self) -> Result<Feed, ContentError> {
+        let (host, path) = crate::url_host_and_path(&self.url);
+        tracing::Span::current().record("url_host", tracing::field::display(&host));
+        tracing::Span::current().record("url_path", tracing::field::display(&path));
+        let (Some(bytes), Some(headers)) = (self.bytes.as_ref(), self.headers.as_ref()) else {
+            return Err(ContentError::ParseError {
+                url: self.url.to_string(),
+                source: anyhow::anyhow!("missing bytes or headers"),
+            });
+        };
+        debug!(bytes_len = bytes.len());
+
+        if let Some(parser) = feed::identify(bytes, headers, &self.url) {
+            let feed = parser.parse().map_err(|e| ContentError::ParseError {
                 url: self.url.to_string(),
                 source: anyhow::Error::from(e),
             })?;
-            Ok(entry)
+            let hints = RefreshHints {
+                max_age: cache_max_age(headers),
+                ..*feed.refresh_hints()
+            };
+            Ok(feed.with_refresh_hints(hints))
         } else {
             Err(ContentError::ParseError {
                 url: self.url.to_string(),
-                source: anyhow::anyhow!("no suitable parser"),
+                source: anyhow::anyhow!("no suitable feed parser"),
             })
         }
     }
 }
+
+/// `max-age` of a `Cache-Control` header, unless caching is forbidden altogether.
+fn cache_max_age(headers: &HeaderMap) -> Option<TimeDelta> {
+    let value = headers
+        .get(header::CACHE_CONTROL)?
+        .to_str()
+        .ok()?
+        .to_ascii_lowercase();
+    let directives = value.split(',').map(str::trim).collect::<Vec<_>>();
+    if directives
+        .iter()
+        .any(|d| *d == "no-store" || *d == "no-cache")
+    {
+        return None;
+    }
+    directives
+        .iter()
+        .find_map(|d| d.strip_prefix("max-age="))
+        .and_then(|s| s.trim_matches('"').parse::<i64>().ok())
+        .filter(|s| *s > 0)
+        .and_then(TimeDelta::try_seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_age_is_read_from_cache_control() {
+        let cases = [
+            ("max-age=3600", Some(TimeDelta::hours(1))),
+            ("public, MAX-AGE=\"600\"", Some(TimeDelta::minutes(10))),
+            ("max-age=0", None),
+            ("max-age=-5", None),
+            ("max-age=soon", None),
+            ("no-cache, max-age=3600", None),
+            ("max-age=3600, no-store", None),
+            ("public", None),
+            // Too long to represent, rather than a panic.
+            ("max-age=99999999999999999", None),
+        ];
+        for (value, expected) in cases {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CACHE_CONTROL, value.parse().unwrap());
+            assert_eq!(cache_max_age(&headers), expected, "{value}");
+        }
+        assert_eq!(cache_max_age(&HeaderMap::new()), None);
+    }
+
+    fn status_error(status: u16) -> reqwest::Error {
+        let response = axum::http::Response::builder()
+            .status(status)
+            .body("")
+            .unwrap();
+        reqwest::Response::from(response)
+            .error_for_status()
+            .unwrap_err()
+    }
+
+    fn fetch_error(class: ErrorClass, status: Option<u16>) -> ContentError {
+        ContentError::FetchError {
+            url: "https://example.com/".to_string(),
+            class,
+            status: status.map(|s| StatusCode::from_u16(s).unwrap()),
+            source: None,
+        }
+    }
+
+    #[test]
+    fn statuses_are_classified() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let cases = [
+            (400, ErrorClass::ClientError, false),
+            (403, ErrorClass::ClientError, false),
+            (404, ErrorClass::ClientError, false),
+            (408, ErrorClass::ClientError, true),
+            (410, ErrorClass::ClientError, false),
+            (429, ErrorClass::RateLimited, true),
+            (500, ErrorClass::ServerError, true),
+            (502, ErrorClass::ServerError, true),
+            (503, ErrorClass::ServerError, true),
+        ];
+        for (status, class, transient) in cases {
+            let error = ContentError::request(&url, status_error(status));
+            assert_eq!(error.class(), class, "{status}");
+            assert_eq!(error.status().map(|s| s.as_u16()), Some(status));
+            assert_eq!(error.is_transient(), transient, "{status}");
+        }
+    }
+
+    #[test]
+    fn only_failures_that_may_pass_are_transient() {
+        let url = "https://example.com/".to_string();
+        let rate_limited = ContentError::RateLimited {
+            url: url.clone(),
+            retry_after: Duration::from_secs(10),
+        };
+        assert_eq!(rate_limited.class(), ErrorClass::RateLimited);
+        assert_eq!(rate_limited.status(), Some(StatusCode::TOO_MANY_REQUESTS));
+        assert!(rate_limited.is_transient());
+        for class in [ErrorClass::Dns, ErrorClass::Connect, ErrorClass::Timeout] {
+            assert!(fetch_error(class, None).is_transient(), "{class}");
+        }
+
+        let too_large = fetch_error(ErrorClass::BodyTooLarge, None);
+        assert_eq!(too_large.class().as_str(), "body_too_large");
+        assert!(!too_large.is_transient());
+        let parse = ContentError::ParseError {
+            url: url.clone(),
+            source: anyhow::anyhow!("bad"),
+        };
+        let disallowed = ContentError::Disallowed { url };
+        for error in [
+            too_large,
+            parse,
+            disallowed,
+            fetch_error(ErrorClass::Tls, None),
+        ] {
+            assert!(!error.is_transient(), "{}", error.class());
+        }
+    }
+
+    #[tokio::test]
+    async fn refused_connections_are_transient() {
+        // Nothing listens on a port just released.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        drop(listener);
+        let error = reqwest::get(url.as_str()).await.unwrap_err();
+        let error = ContentError::request(&url, error);
+        assert_eq!(error.class(), ErrorClass::Connect);
+        assert_eq!(error.status(), None);
+        assert!(error.is_transient());
+    }
+}
@@ -0,0 +1,161 @@
+//! Near-duplicate detection for entry content, so that syndicated copies of the same story on
+//! different origins can be collapsed into one.
+//!
+//! Content is reduced to a 64-bit SimHash over overlapping word shingles. Copies that differ only
+//! in boilerplate end up a few bits apart, whereas unrelated texts differ in about half the bits.
+//! Fingerprints are also split into [`BANDS`] bands, any two fingerprints fewer than [`BANDS`]
+//! bits apart being equal in at least one, so that candidates can be looked up by band.
+
+use serde::{Deserialize, Serialize};
+
+/// Number of words per shingle.
+const SHINGLE_LEN: usize = 3;
+
+/// Number of equal-width bands fingerprints are split into for lookup.
+pub const BANDS: usize = 4;
+
+/// 64-bit SimHash fingerprint of a text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Fingerprint(u64);
+
+impl Fingerprint {
+    /// Fingerprint `text`, or `None` if it has fewer than `min_words` words, in which case any
+    /// fingerprint would be too unreliable to compare.
+    pub fn of(text: &str, min_words: usize) -> Option<Self> {
+        let words = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        if words.len() < min_words.max(SHINGLE_LEN) {
+            return None;
+        }
+
+        let mut weights = [0i64; 64];
+        for shingle in words.windows(SHINGLE_LEN) {
+            let hash = shingle_hash(shingle);
+            for (bit, weight) in weights.iter_mut().enumerate() {
+                if hash & (1 << bit) != 0 {
+                    *weight += 1;
+                } else {
+                    *weight -= 1;
+                }
+            }
+        }
+
+        let bits = weights
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| **w > 0)
+            .fold(0u64, |acc, (bit, _)| acc | (1 << bit));
+        Some(Self(bits))
+    }
+
+    /// Number of differing bits.
+    pub fn distance(&self, other: &Fingerprint) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+
+    /// SQLite only has signed integers, so fingerprints are stored bit-cast to `i64`.
+    pub(crate) fn to_i64(self) -> i64 {
+        self.0 as i64
+    }
+
+    pub(crate) fn from_i64(v: i64) -> Self {
+        Self(v as u64)
+    }
+
+    /// The fingerprint split into [`BANDS`] bands, lowest bits first.
+    pub(crate) fn bands(self) -> [i64; BANDS] {
+        let width = u64::BITS as usize / BANDS;
+        std::array::from_fn(|i| ((self.0 >> (i * width)) & ((1 << width) - 1)) as i64)
+    }
+}
+
+/// Policy for deciding when two entries are near-duplicates.
+#[derive(Debug, Clone, Copy)]
+pub struct NearDuplicates {
+    max_distance: u32,
+    min_words: usize,
+}
+
+impl NearDuplicates {
+    pub const DEFAULT_MAX_DISTANCE: u32 = 3;
+    pub const DEFAULT_MIN_WORDS: usize = 50;
+
+    pub fn new(max_distance: u32, min_words: usize) -> Self {
+        Self {
+            max_distance,
+            min_words,
+        }
+    }
+
+    pub fn fingerprint(&self, text: &str) -> Option<Fingerprint> {
+        Fingerprint::of(text, self.min_words)
+    }
+
+    pub fn is_duplicate(&self, a: &Fingerprint, b: &Fingerprint) -> bool {
+        a.distance(b) <= self.max_distance
+    }
+
+    /// Whether every near-duplicate shares a band with the fingerprint it duplicates, so that
+    /// looking candidates up by band finds them all.
+    pub(crate) fn banded(&self) -> bool {
+        (self.max_distance as usize) < BANDS
+    }
+}
+
+impl Default for NearDuplicates {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_DISTANCE, Self::DEFAULT_MIN_WORDS)
+    }
+}
+
+/// FNV-1a over the shingle's words, followed by a 64-bit finaliser so that every output bit
+/// depends on every input byte. Must stay stable, since fingerprints are persisted.
+fn shingle_hash(words: &[String]) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut h = OFFSET;
+    for word in words {
+        for b in word.bytes().chain(std::iter::once(b' ')) {
+            h ^= u64::from(b);
+            h = h.wrapping_mul(PRIME);
+        }
+    }
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^ (h >> 33)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bands_cover_the_fingerprint() {
+        let fingerprint = Fingerprint(0xfedc_ba98_7654_3210);
+        assert_eq!(fingerprint.bands(), [0x3210, 0x7654, 0xba98, 0xfedc]);
+        let negative = Fingerprint::from_i64(-1);
+        assert_eq!(negative.bands(), [0xffff; BANDS]);
+    }
+
+    #[test]
+    fn close_fingerprints_share_a_band() {
+        let fingerprint = Fingerprint(0x0123_4567_89ab_cdef);
+        // Flipping one bit in each of three bands leaves the fourth equal, wherever they are.
+        for untouched in 0..BANDS {
+            let flips = (0..BANDS)
+                .filter(|band| *band != untouched)
+                .fold(0u64, |acc, band| acc | 1 << (band * 16 + 7));
+            let other = Fingerprint(fingerprint.0 ^ flips);
+            assert_eq!(fingerprint.distance(&other), 3);
+            assert_eq!(fingerprint.bands()[untouched], other.bands()[untouched]);
+        }
+        assert!(NearDuplicates::default().banded());
+        assert!(!NearDuplicates::new(4, 50).banded());
+    }
+}
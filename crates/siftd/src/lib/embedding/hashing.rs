@@ -0,0 +1,57 @@
+use async_trait::async_trait;
+
+use crate::embedding::{Embedder, Embedding, EmbeddingError};
+
+/// Deterministic embedder using the hashing trick over lowercased words: each word adds ±1 to
+/// one coordinate picked by its hash. It captures nothing beyond word overlap, but needs no
+/// model, which makes it useful for tests and as a fallback.
+#[derive(Debug, Clone)]
+pub struct HashingEmbedder {
+    dimension: usize,
+    model_id: String,
+}
+
+impl HashingEmbedder {
+    pub fn new(dimension: usize) -> Self {
+        let dimension = dimension.max(1);
+        Self {
+            dimension,
+            model_id: format!("hashing-{dimension}"),
+        }
+    }
+
+    fn embed_one(&self, text: &str) -> Embedding {
+        let mut values = vec![0.0f32; self.dimension];
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+        {
+            let hash = fnv1a(word.to_lowercase().as_bytes());
+            let index = (hash % self.dimension as u64) as usize;
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            values[index] += sign;
+        }
+        Embedding::new(values).normalised()
+    }
+}
+
+#[async_trait]
+impl Embedder for HashingEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Embedding>, EmbeddingError> {
+        Ok(texts.iter().map(|t| self.embed_one(t)).collect())
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ u64::from(*b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
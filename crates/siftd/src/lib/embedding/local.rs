@@ -0,0 +1,136 @@
+use std::{path::Path, sync::Arc};
+
+use async_trait::async_trait;
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+use tracing::{debug, info};
+
+use crate::embedding::{Embedder, Embedding, EmbeddingError};
+
+/// Sentence-transformer (BERT family, _e.g._ `all-MiniLM-L6-v2`) run on the CPU. Vectors are the
+/// attention-masked mean of the last hidden states, normalised to unit length.
+#[derive(Debug, Clone)]
+pub struct LocalEmbedder {
+    inner: Arc<Inner>,
+    model_id: String,
+    dimension: usize,
+}
+
+struct Inner {
+    model: BertModel,
+    tokenizer: Tokenizer,
+}
+
+impl std::fmt::Debug for Inner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Inner").finish_non_exhaustive()
+    }
+}
+
+impl LocalEmbedder {
+    /// Load a model from a directory holding `config.json`, `tokenizer.json` and
+    /// `model.safetensors`, as published on the Hugging Face hub. The model id is the directory
+    /// name.
+    pub fn load(dir: &Path) -> Result<Self, EmbeddingError> {
+        let backend = |e: &dyn std::fmt::Display| {
+            EmbeddingError::Backend(anyhow::anyhow!("{}: {e}", dir.display()))
+        };
+
+        let config = std::fs::read_to_string(dir.join("config.json")).map_err(|e| backend(&e))?;
+        let config: Config = serde_json::from_str(&config).map_err(|e| backend(&e))?;
+
+        let mut tokenizer =
+            Tokenizer::from_file(dir.join("tokenizer.json")).map_err(|e| backend(&e))?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: config.max_position_embeddings,
+                ..Default::default()
+            }))
+            .map_err(|e| backend(&e))?;
+        tokenizer.with_padding(Some(PaddingParams::default()));
+
+        let device = Device::Cpu;
+        // SAFETY: the weights file is memory-mapped and must not be modified while loaded.
+        let vb = unsafe {
+            VarBuilder::from_mmaped_safetensors(&[dir.join("model.safetensors")], DTYPE, &device)
+        }
+        .map_err(|e| backend(&e))?;
+        let model = BertModel::load(vb, &config).map_err(|e| backend(&e))?;
+
+        let model_id = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "local".to_string());
+        info!(%model_id, dimension = config.hidden_size, "local embedding model loaded");
+
+        Ok(Self {
+            inner: Arc::new(Inner { model, tokenizer }),
+            model_id,
+            dimension: config.hidden_size,
+        })
+    }
+}
+
+impl Inner {
+    fn embed(&self, texts: &[String]) -> Result<Vec<Embedding>, EmbeddingError> {
+        let backend = |e: &dyn std::fmt::Display| EmbeddingError::Backend(anyhow::anyhow!("{e}"));
+        let device = &self.model.device;
+
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| backend(&e))?;
+        let ids = encodings
+            .iter()
+            .map(|e| Tensor::new(e.get_ids(), device))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| backend(&e))?;
+        let mask = encodings
+            .iter()
+            .map(|e| Tensor::new(e.get_attention_mask(), device))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| backend(&e))?;
+
+        let run = || -> candle_core::Result<Vec<Vec<f32>>> {
+            let ids = Tensor::stack(&ids, 0)?;
+            let mask = Tensor::stack(&mask, 0)?;
+            let token_type_ids = ids.zeros_like()?;
+            let hidden = self.model.forward(&ids, &token_type_ids, Some(&mask))?;
+
+            // Mean over the tokens that are not padding.
+            let mask = mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+            let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+            let counts = mask.sum(1)?.clamp(1e-9, f64::MAX)?;
+            summed.broadcast_div(&counts)?.to_vec2::<f32>()
+        };
+        let vectors = run().map_err(|e| backend(&e))?;
+        debug!(batch = texts.len(), "local embedding batch done");
+
+        Ok(vectors
+            .into_iter()
+            .map(|v| Embedding::new(v).normalised())
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Embedder for LocalEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Embedding>, EmbeddingError> {
+        // Inference is CPU-bound, so keep it off the async workers.
+        let inner = self.inner.clone();
+        let texts = texts.to_vec();
+        tokio::task::spawn_blocking(move || inner.embed(&texts))
+            .await
+            .map_err(|e| EmbeddingError::Backend(anyhow::Error::new(e)))?
+    }
+}
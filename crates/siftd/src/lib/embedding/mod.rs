@@ -0,0 +1,432 @@
+//! Text embeddings, used to score entries by their similarity to one another.
+//!
+//! Backends implement [`Embedder`]; an entry's content is split into overlapping chunks which are
+//! embedded separately and averaged into a single vector per entry and model.
+
+use std::{fmt::Debug, sync::Arc};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+pub use crate::embedding::hashing::HashingEmbedder;
+#[cfg(feature = "candle")]
+pub use crate::embedding::local::LocalEmbedder;
+pub use crate::embedding::openai::OpenAiEmbedder;
+use crate::{
+    graph::SimilarityGraph,
+    storage::{EmbeddingStore, Storage},
+};
+
+mod hashing;
+#[cfg(feature = "candle")]
+mod local;
+mod openai;
+
+#[async_trait]
+pub trait Embedder: Send + Sync + Debug {
+    /// Identifier of the model, under which its vectors are stored. Vectors from different
+    /// models are never compared with each other.
+    fn model_id(&self) -> &str;
+
+    /// Length of the vectors produced.
+    fn dimension(&self) -> usize;
+
+    /// Embed each of `texts`, in order.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Embedding>, EmbeddingError>;
+}
+
+/// An embedding vector.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Embedding(Vec<f32>);
+
+impl Embedding {
+    pub fn new(values: Vec<f32>) -> Self {
+        Self(values)
+    }
+
+    pub fn as_slice(&self) -> &[f32] {
+        &self.0
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Scale to unit length, leaving the zero vector as is.
+    pub fn normalised(mut self) -> Self {
+        let norm = self.0.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            self.0.iter_mut().for_each(|x| *x /= norm);
+        }
+        self
+    }
+
+    /// Dot product, which is the cosine similarity for normalised vectors. 0 if the dimensions
+    /// differ.
+    pub fn dot(&self, other: &Embedding) -> f32 {
+        if self.dimension() != other.dimension() {
+            return 0.0;
+        }
+        self.0.iter().zip(&other.0).map(|(a, b)| a * b).sum()
+    }
+
+    /// Cosine similarity, or 0 if either vector is zero or the dimensions differ.
+    pub fn cosine(&self, other: &Embedding) -> f32 {
+        if self.dimension() != other.dimension() {
+            return 0.0;
+        }
+        let dot = self.0.iter().zip(&other.0).map(|(a, b)| a * b).sum::<f32>();
+        let norms = self.0.iter().map(|x| x * x).sum::<f32>().sqrt()
+            * other.0.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norms > 0.0 {
+            dot / norms
+        } else {
+            0.0
+        }
+    }
+
+    /// Element-wise mean of `embeddings`, all of which must have the same dimension.
+    pub fn mean(embeddings: &[Embedding]) -> Option<Embedding> {
+        let first = embeddings.first()?;
+        let mut sum = vec![0.0f32; first.dimension()];
+        for e in embeddings {
+            if e.dimension() != sum.len() {
+                return None;
+            }
+            sum.iter_mut().zip(&e.0).for_each(|(s, x)| *s += x);
+        }
+        let n = embeddings.len() as f32;
+        Some(Embedding(sum.into_iter().map(|s| s / n).collect()))
+    }
+
+    /// Little-endian `f32`s, as stored in the database.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        self.0.iter().flat_map(|x| x.to_le_bytes()).collect()
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if !bytes.len().is_multiple_of(4) {
+            return None;
+        }
+        Some(Self(
+            bytes
+                .chunks_exact(4)
+                .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                .collect(),
+        ))
+    }
+}
+
+/// How entry content is split up before embedding.
+#[derive(Debug, Clone, Copy)]
+pub struct Chunking {
+    /// Words per chunk. Should stay below the model's context length in tokens.
+    pub max_words: usize,
+    /// Words shared between consecutive chunks.
+    pub overlap: usize,
+    /// Chunks embedded per entry; the rest of very long content is ignored.
+    pub max_chunks: usize,
+}
+
+impl Default for Chunking {
+    fn default() -> Self {
+        Self {
+            max_words: 128,
+            overlap: 32,
+            max_chunks: 32,
+        }
+    }
+}
+
+impl Chunking {
+    /// Split `text` into overlapping chunks of whole words.
+    pub fn chunk(&self, text: &str) -> Vec<String> {
+        let words = text.split_whitespace().collect::<Vec<_>>();
+        let size = self.max_words.max(1);
+        let step = size.saturating_sub(self.overlap).max(1);
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < words.len() && chunks.len() < self.max_chunks {
+            let end = (start + size).min(words.len());
+            chunks.push(words[start..end].join(" "));
+            if end == words.len() {
+                break;
+            }
+            start += step;
+        }
+        chunks
+    }
+}
+
+/// Embed a whole text as the normalised mean of its chunks' embeddings.
+pub async fn embed_text(
+    embedder: &dyn Embedder,
+    chunking: &Chunking,
+    text: &str,
+) -> Result<Embedding, EmbeddingError> {
+    let chunks = chunking.chunk(text);
+    if chunks.is_empty() {
+        return Err(EmbeddingError::Empty);
+    }
+    let embeddings = embedder.embed(&chunks).await?;
+    if embeddings.len() != chunks.len() {
+        return Err(EmbeddingError::Count {
+            expected: chunks.len(),
+            actual: embeddings.len(),
+        });
+    }
+    if let Some(bad) = embeddings
+        .iter()
+        .find(|e| e.dimension() != embedder.dimension())
+    {
+        return Err(EmbeddingError::Dimension {
+            expected: embedder.dimension(),
+            actual: bad.dimension(),
+        });
+    }
+    debug!(
+        chunks = chunks.len(),
+        model = embedder.model_id(),
+        "text embedded"
+    );
+    Embedding::mean(&embeddings)
+        .map(Embedding::normalised)
+        .ok_or(EmbeddingError::Empty)
+}
+
+/// Embed the content of entry `id` and store the vector under the embedder's model id.
+pub async fn embed_entry(
+    embedder: &dyn Embedder,
+    store: &EmbeddingStore,
+    id: i64,
+    content: &str,
+) -> Result<Embedding, EmbeddingError> {
+    let embedding = embed_text(embedder, &Chunking::default(), content).await?;
+    store
+        .put(id, embedder.model_id(), &embedding)
+        .await
+        .map_err(|e| EmbeddingError::Backend(anyhow::Error::new(e)))?;
+    Ok(embedding)
+}
+
+/// Embed every entry that has no vector under the embedder's model yet, _e.g._ after switching
+/// models, and add them to `graph`. Failures are logged and skipped.
+pub async fn embed_missing(embedder: Arc<dyn Embedder>, storage: Storage, graph: SimilarityGraph) {
+    const BATCH: u32 = 64;
+    let store = storage.embeddings();
+    let mut embedded = 0usize;
+    // Entries that failed are skipped by offsetting past them.
+    let mut failed = 0u32;
+    loop {
+        let missing = match store.missing(embedder.model_id(), BATCH, failed).await {
+            Ok(missing) => missing,
+            Err(e) => {
+                warn!(error = %e, "listing entries without embeddings failed");
+                return;
+            }
+        };
+        if missing.is_empty() {
+            break;
+        }
+        for (id, content) in missing {
+            match embed_entry(embedder.as_ref(), &store, id, &content).await {
+                Ok(embedding) => {
+                    embedded += 1;
+                    if let Err(e) = graph.insert(id, embedding).await {
+                        warn!(entry_id = id, error = %e, "adding entry to similarity graph failed");
+                    }
+                }
+                Err(e) => {
+                    warn!(entry_id = id, error = %e, "embedding entry failed");
+                    failed += 1;
+                }
+            }
+        }
+    }
+    info!(
+        embedded,
+        failed,
+        model = embedder.model_id(),
+        "embedding backfill done"
+    );
+}
+
+#[derive(Debug, Error)]
+pub enum EmbeddingError {
+    #[error("Embedding backend failed")]
+    Backend(#[source] anyhow::Error),
+    #[error("Embedding has dimension {actual}, expected {expected}")]
+    Dimension { expected: usize, actual: usize },
+    #[error("Embedder returned {actual} embeddings for {expected} texts")]
+    Count { expected: usize, actual: usize },
+    #[error("Nothing to embed")]
+    Empty,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Embedder returning one vector too few, or of the wrong dimension.
+    #[derive(Debug)]
+    struct Broken {
+        dimension: usize,
+    }
+
+    #[async_trait]
+    impl Embedder for Broken {
+        fn model_id(&self) -> &str {
+            "broken"
+        }
+
+        fn dimension(&self) -> usize {
+            4
+        }
+
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Embedding>, EmbeddingError> {
+            let count = if self.dimension == 4 {
+                texts.len() - 1
+            } else {
+                texts.len()
+            };
+            Ok(vec![Embedding::new(vec![1.0; self.dimension]); count])
+        }
+    }
+
+    fn words(n: usize) -> String {
+        (0..n)
+            .map(|i| format!("w{i}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn chunks_overlap_and_cover_the_text() {
+        let chunking = Chunking {
+            max_words: 4,
+            overlap: 1,
+            max_chunks: 10,
+        };
+        assert_eq!(
+            chunking.chunk(&words(10)),
+            ["w0 w1 w2 w3", "w3 w4 w5 w6", "w6 w7 w8 w9"]
+        );
+        // The last chunk ends with the text, however short.
+        assert_eq!(
+            chunking.chunk(&words(8)),
+            ["w0 w1 w2 w3", "w3 w4 w5 w6", "w6 w7"]
+        );
+        assert_eq!(chunking.chunk(&words(4)), ["w0 w1 w2 w3"]);
+        assert_eq!(chunking.chunk("  \n\t "), Vec::<String>::new());
+        // Whitespace of any kind separates words, and is collapsed.
+        assert_eq!(chunking.chunk("a\n\nb\tc  d e"), ["a b c d", "d e"]);
+    }
+
+    #[test]
+    fn chunking_stops_at_the_chunk_limit() {
+        let chunking = Chunking {
+            max_words: 3,
+            overlap: 0,
+            max_chunks: 2,
+        };
+        assert_eq!(chunking.chunk(&words(100)), ["w0 w1 w2", "w3 w4 w5"]);
+        // An overlap as large as the chunk still moves forward.
+        let chunking = Chunking {
+            max_words: 2,
+            overlap: 5,
+            max_chunks: 10,
+        };
+        assert_eq!(chunking.chunk("a b c"), ["a b", "b c"]);
+    }
+
+    #[test]
+    fn multibyte_words_are_kept_whole() {
+        let chunking = Chunking {
+            max_words: 2,
+            overlap: 1,
+            max_chunks: 10,
+        };
+        let text = "Größe\u{3000}日本語 😀émoji naïve";
+        assert_eq!(
+            chunking.chunk(text),
+            ["Größe 日本語", "日本語 😀émoji", "😀émoji naïve"]
+        );
+        let long = "日本語 ".repeat(1000);
+        let chunks = Chunking::default().chunk(&long);
+        // Chunks of 128 words start every 96.
+        assert_eq!(chunks.len(), 11);
+        assert!(chunks.iter().all(|c| c.split(' ').all(|w| w == "日本語")));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let embedding = Embedding::new(vec![0.0, -1.5, f32::MIN_POSITIVE, 1e30, -0.0]);
+        let bytes = embedding.to_bytes();
+        assert_eq!(bytes.len(), 20);
+        assert_eq!(Embedding::from_bytes(&bytes), Some(embedding));
+        assert_eq!(Embedding::from_bytes(&[]), Some(Embedding::new(Vec::new())));
+        assert_eq!(Embedding::from_bytes(&bytes[..19]), None);
+    }
+
+    #[test]
+    fn mean_averages_equal_dimensions_only() {
+        let a = Embedding::new(vec![1.0, 2.0]);
+        let b = Embedding::new(vec![3.0, -2.0]);
+        assert_eq!(
+            Embedding::mean(&[a.clone(), b]),
+            Some(Embedding::new(vec![2.0, 0.0]))
+        );
+        assert_eq!(Embedding::mean(std::slice::from_ref(&a)), Some(a.clone()));
+        assert_eq!(Embedding::mean(&[]), None);
+        assert_eq!(Embedding::mean(&[a, Embedding::new(vec![1.0])]), None);
+    }
+
+    #[tokio::test]
+    async fn hashing_embeddings_are_deterministic() {
+        let embedder = HashingEmbedder::new(64);
+        let text = format!("The quick brown fox. {}", words(300));
+        let first = embed_text(&embedder, &Chunking::default(), &text)
+            .await
+            .unwrap();
+        let again = embed_text(&HashingEmbedder::new(64), &Chunking::default(), &text)
+            .await
+            .unwrap();
+        assert_eq!(first, again);
+        assert_eq!(first.dimension(), 64);
+        assert!((first.dot(&first) - 1.0).abs() < 1e-5);
+        // Case and punctuation do not matter.
+        let texts = [
+            "Quick, brown FOX!".to_string(),
+            "quick brown fox".to_string(),
+        ];
+        let pair = embedder.embed(&texts).await.unwrap();
+        assert_eq!(pair[0], pair[1]);
+        assert!(matches!(
+            embed_text(&embedder, &Chunking::default(), " ").await,
+            Err(EmbeddingError::Empty)
+        ));
+    }
+
+    #[tokio::test]
+    async fn embedder_output_is_checked() {
+        let text = words(300);
+        let chunking = Chunking::default();
+        assert!(matches!(
+            embed_text(&Broken { dimension: 4 }, &chunking, &text).await,
+            Err(EmbeddingError::Count {
+                expected: 3,
+                actual: 2
+            })
+        ));
+        assert!(matches!(
+            embed_text(&Broken { dimension: 3 }, &chunking, &text).await,
+            Err(EmbeddingError::Dimension {
+                expected: 4,
+                actual: 3
+            })
+        ));
+    }
+}
@@ -0,0 +1,211 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::{
+    embedding::{Embedder, Embedding, EmbeddingError},
+    retry::Backoff,
+};
+
+/// Embedder talking to any server implementing the OpenAI `POST /embeddings` protocol.
+#[derive(Debug, Clone)]
+pub struct OpenAiEmbedder {
+    client: Client,
+    endpoint: Url,
+    model: String,
+    api_key: Option<String>,
+    dimension: usize,
+    batch_size: usize,
+    timeout: Duration,
+    backoff: Backoff,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl OpenAiEmbedder {
+    pub const DEFAULT_BATCH_SIZE: usize = 32;
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// `base_url` is the API root, _e.g._ `http://localhost:8080/v1`; requests go to
+    /// `{base_url}/embeddings`. The dimension is unknown until [`Self::probe`] is called.
+    pub fn new(client: Client, base_url: &Url, model: impl Into<String>) -> Self {
+        let mut endpoint = base_url.clone();
+        endpoint
+            .path_segments_mut()
+            .map(|mut segments| {
+                segments.pop_if_empty().push("embeddings");
+            })
+            .ok();
+        Self {
+            client,
+            endpoint,
+            model: model.into(),
+            api_key: None,
+            dimension: 0,
+            batch_size: Self::DEFAULT_BATCH_SIZE,
+            timeout: Self::DEFAULT_TIMEOUT,
+            backoff: Backoff::default(),
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    /// Maximum number of texts sent per request.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Timeout of each individual request.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Retry requests failing transiently according to `backoff` rather than the default.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Embed a short text to find out the dimension of the model's vectors, which also checks
+    /// that the server is reachable and serves the model.
+    pub async fn probe(mut self) -> Result<Self, EmbeddingError> {
+        let probe = self.request(&["dimension probe".to_string()]).await?;
+        self.dimension = probe.first().map(Embedding::dimension).unwrap_or(0);
+        if self.dimension == 0 {
+            return Err(EmbeddingError::Empty);
+        }
+        debug!(model = %self.model, dimension = self.dimension, "embedding server probed");
+        Ok(self)
+    }
+
+    async fn request(&self, texts: &[String]) -> Result<Vec<Embedding>, EmbeddingError> {
+        let mut retry = 0;
+        loop {
+            let e = match self.request_once(texts).await {
+                Ok(embeddings) => return Ok(embeddings),
+                Err((true, e)) => e,
+                Err((false, e)) => return Err(EmbeddingError::Backend(e)),
+            };
+            let Some(delay) = self.backoff.delay(retry, &mut rand::rng()) else {
+                return Err(EmbeddingError::Backend(e));
+            };
+            retry += 1;
+            warn!(
+                retry,
+                delay_ms = delay.as_millis() as u64,
+                error = %e,
+                "embedding request failed; retrying"
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// A single request. Errors are paired with whether retrying could help.
+    async fn request_once(
+        &self,
+        texts: &[String],
+    ) -> Result<Vec<Embedding>, (bool, anyhow::Error)> {
+        let mut request = self
+            .client
+            .post(self.endpoint.clone())
+            .timeout(self.timeout)
+            .json(&EmbeddingRequest {
+                model: &self.model,
+                input: texts,
+            });
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request.send().await.map_err(|e| (true, e.into()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let transient = status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+            let body = response.text().await.unwrap_or_default();
+            return Err((
+                transient,
+                anyhow::anyhow!("{} returned {status}: {body}", self.endpoint),
+            ));
+        }
+
+        let body: EmbeddingResponse = response.json().await.map_err(|e| (false, e.into()))?;
+        if body.data.len() != texts.len() {
+            return Err((
+                false,
+                anyhow::anyhow!(
+                    "asked for {} embeddings, got {}",
+                    texts.len(),
+                    body.data.len()
+                ),
+            ));
+        }
+        // Every input must come back exactly once; the order of the data is not guaranteed.
+        let mut embeddings = vec![None; texts.len()];
+        for data in body.data {
+            match embeddings.get_mut(data.index) {
+                Some(slot @ None) => *slot = Some(Embedding::new(data.embedding)),
+                Some(Some(_)) => {
+                    return Err((
+                        false,
+                        anyhow::anyhow!("embedding {} returned twice", data.index),
+                    ));
+                }
+                None => {
+                    return Err((
+                        false,
+                        anyhow::anyhow!(
+                            "embedding index {} out of range for {} inputs",
+                            data.index,
+                            texts.len()
+                        ),
+                    ));
+                }
+            }
+        }
+        // As many embeddings as inputs, none of them twice, so every slot is filled.
+        Ok(embeddings.into_iter().flatten().collect())
+    }
+}
+
+#[async_trait]
+impl Embedder for OpenAiEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Embedding>, EmbeddingError> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            embeddings.extend(self.request(batch).await?);
+        }
+        Ok(embeddings)
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use serde::{Deserialize, Serialize};
 use url::Url;
 
@@ -9,11 +11,31 @@ pub struct Entry {
     origin: String,
     author: String,
     url: Url,
+    /// The main content, as CommonMark.
     content: String,
+    /// The main content as plain text, for embedding and fingerprinting.
+    #[serde(default)]
+    text: String,
     metadata: Metadata,
+
+    /// The URL the page declares as canonical, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    canonical_url: Option<Url>,
+
+    /// Other URLs this entry has been observed under, _e.g._ before redirects or tracking
+    /// parameters were stripped.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<Url>,
+
+    /// The next page of a multi-page article, until it has been appended.
+    #[serde(skip)]
+    next_page: Option<Url>,
 }
 
 impl Entry {
+    /// Longest content, and text, kept in bytes; anything beyond is cut off.
+    pub const MAX_CONTENT_BYTES: usize = 400_000;
+
     pub fn new(
         title: String,
         origin: String,
@@ -28,8 +50,212 @@ impl Entry {
             origin,
             author,
             url,
+            text: content.clone(),
             content,
             metadata,
+            canonical_url: None,
+            aliases: Vec::new(),
+            next_page: None,
         }
     }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn canonical_url(&self) -> Option<&Url> {
+        self.canonical_url.as_ref()
+    }
+
+    pub fn aliases(&self) -> &[Url] {
+        &self.aliases
+    }
+
+    pub fn next_page(&self) -> Option<&Url> {
+        self.next_page.as_ref()
+    }
+
+    /// Set the plain text rendering of the content, which is the content itself by default.
+    pub fn with_text(mut self, text: String) -> Self {
+        self.text = text;
+        self
+    }
+
+    pub fn with_canonical_url(mut self, canonical_url: Option<Url>) -> Self {
+        self.canonical_url = canonical_url;
+        self
+    }
+
+    /// Record further URLs this entry is known under, skipping ones already known.
+    pub fn with_aliases(mut self, aliases: impl IntoIterator<Item = Url>) -> Self {
+        for alias in aliases {
+            if alias != self.url && !self.aliases.contains(&alias) {
+                self.aliases.push(alias);
+            }
+        }
+        self
+    }
+
+    pub fn with_next_page(mut self, next_page: Option<Url>) -> Self {
+        self.next_page = next_page;
+        self
+    }
+
+    /// Append `page`, the next page of this multi-page article, leaving out the blocks it starts
+    /// or ends with that this entry already has, such as a header repeated on every page. The
+    /// next page of `page` becomes the next page of this entry.
+    pub fn append_page(&mut self, page: Entry) {
+        self.content = cap_len(
+            append_blocks(&self.content, &page.content),
+            Self::MAX_CONTENT_BYTES,
+        );
+        self.text = cap_len(
+            append_blocks(&self.text, &page.text),
+            Self::MAX_CONTENT_BYTES,
+        );
+        self.next_page = page.next_page;
+    }
+
+    /// Fill metadata the parser could not find with metadata known before fetching, _e.g._ from
+    /// the feed the entry came from.
+    pub fn with_fallback_metadata(mut self, fallback: Metadata) -> Self {
+        self.metadata = std::mem::take(&mut self.metadata).or(fallback);
+        self
+    }
+}
+
+/// `page` appended to `content`, both made of blocks separated by blank lines, without the
+/// leading and trailing blocks of `page` that `content` already has.
+fn append_blocks(content: &str, page: &str) -> String {
+    let seen = content.split("\n\n").map(str::trim).collect::<HashSet<_>>();
+    let blocks = page
+        .split("\n\n")
+        .filter(|b| !b.trim().is_empty())
+        .collect::<Vec<_>>();
+    let start = blocks
+        .iter()
+        .position(|b| !seen.contains(b.trim()))
+        .unwrap_or(blocks.len());
+    let end = blocks
+        .iter()
+        .rposition(|b| !seen.contains(b.trim()))
+        .map_or(start, |i| i + 1);
+    let new = &blocks[start..end.max(start)];
+    if new.is_empty() {
+        return content.to_string();
+    }
+    if content.trim().is_empty() {
+        return new.join("\n\n");
+    }
+    format!("{}\n\n{}", content.trim_end(), new.join("\n\n"))
+}
+
+/// `s` cut off, on a character boundary, after at most `max` bytes.
+pub(crate) fn cap_len(s: String, max: usize) -> String {
+    if s.len() <= max {
+        s
+    } else {
+        let mut end = max;
+        while end > 0 && !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!("{}…", &s[..end])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(content: &str) -> Entry {
+        let url = Url::parse("https://example.com/story").unwrap();
+        Entry::new(
+            String::new(),
+            String::new(),
+            String::new(),
+            url,
+            content.to_string(),
+            None,
+        )
+    }
+
+    #[test]
+    fn pages_are_appended_without_the_blocks_they_repeat() {
+        let cases = [
+            ("a\n\nb", "c\n\nd", "a\n\nb\n\nc\n\nd"),
+            // Headers and footers repeated on every page.
+            (
+                "head\n\na\n\nfoot",
+                "head\n\nb\n\nfoot",
+                "head\n\na\n\nfoot\n\nb",
+            ),
+            ("head\n\na", " head \n\n\n\nb", "head\n\na\n\nb"),
+            // Repeats in the middle of a page are kept.
+            ("a\n\nb", "c\n\na\n\nd", "a\n\nb\n\nc\n\na\n\nd"),
+            ("a\n\nb", "b\n\na", "a\n\nb"),
+            ("a", "", "a"),
+            ("", "a\n\nb", "a\n\nb"),
+            ("a\n\n", "b", "a\n\nb"),
+        ];
+        for (content, page, expected) in cases {
+            assert_eq!(
+                append_blocks(content, page),
+                expected,
+                "{content:?} + {page:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn appending_a_page_takes_its_next_page() {
+        let next = Url::parse("https://example.com/story/3").unwrap();
+        let mut first = entry("one");
+        first.append_page(entry("two").with_next_page(Some(next.clone())));
+        assert_eq!(first.content(), "one\n\ntwo");
+        assert_eq!(first.text(), "one\n\ntwo");
+        assert_eq!(first.next_page(), Some(&next));
+    }
+
+    #[test]
+    fn stitched_pages_are_capped() {
+        let page = "é".repeat(Entry::MAX_CONTENT_BYTES / 3);
+        let mut first = entry(&format!("{page}1"));
+        first.append_page(entry(&format!("{page}2")));
+        first.append_page(entry(&format!("{page}3")));
+        for stitched in [first.content(), first.text()] {
+            assert!(stitched.len() <= Entry::MAX_CONTENT_BYTES + '…'.len_utf8());
+            assert!(stitched.ends_with('…'));
+        }
+    }
+
+    #[test]
+    fn capping_keeps_whole_characters() {
+        assert_eq!(cap_len("short".to_string(), 5), "short");
+        assert_eq!(cap_len("longer".to_string(), 4), "long…");
+        assert_eq!(cap_len("naïve".to_string(), 3), "na…");
+    }
 }
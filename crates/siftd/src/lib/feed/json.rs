@@ -0,0 +1,346 @@
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use mime::Mime;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::debug;
+use url::Url;
+
+use crate::{
+    feed::{absolutise, plain_text, truncate_chars, Feed, FeedError, FeedItem, FeedParser},
+    metadata::Metadata,
+};
+
+/// Parser for JSON Feed 1.0 and 1.1 (<https://www.jsonfeed.org/version/1.1/>).
+pub struct JsonFeedParser {
+    url: Url,
+    bytes: Bytes,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawFeed {
+    version: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    home_page_url: Option<String>,
+    /// JSON Feed 1.1 `authors`, used as a fallback for items without their own.
+    #[serde(default)]
+    authors: Vec<RawAuthor>,
+    /// Deprecated JSON Feed 1.0 `author`.
+    #[serde(default)]
+    author: Option<RawAuthor>,
+    #[serde(default)]
+    items: Vec<RawItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawItem {
+    /// Required by the spec to be a string, but numbers are common in the wild.
+    #[serde(default)]
+    id: Option<Value>,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    external_url: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    content_html: Option<String>,
+    #[serde(default)]
+    content_text: Option<String>,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    banner_image: Option<String>,
+    #[serde(default)]
+    date_published: Option<String>,
+    #[serde(default)]
+    date_modified: Option<String>,
+    #[serde(default)]
+    authors: Vec<RawAuthor>,
+    #[serde(default)]
+    author: Option<RawAuthor>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawAuthor {
+    #[serde(default)]
+    name: Option<String>,
+}
+
+impl FeedParser for JsonFeedParser {
+    fn new(bytes: &Bytes, headers: &reqwest::header::HeaderMap, url: &Url) -> Option<Box<Self>>
+    where
+        Self: Sized,
+    {
+        let mime = headers
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|s| s.parse::<Mime>().ok());
+
+        let is_feed_mime = mime.as_ref().is_some_and(|m| {
+            m.essence_str()
+                .eq_ignore_ascii_case("application/feed+json")
+        });
+
+        // Plenty of servers still send `application/json` (or worse), so also accept anything
+        // that declares a JSON Feed version.
+        if is_feed_mime || looks_like_json_feed(bytes) {
+            Some(Box::new(Self {
+                url: url.clone(),
+                bytes: bytes.clone(),
+            }))
+        } else {
+            debug!("Body does not look like a JSON feed; skipping JsonFeedParser.");
+            None
+        }
+    }
+
+    fn parse(&self) -> Result<Feed, FeedError> {
+        let (url_host, url_path) = crate::url_host_and_path(&self.url);
+        debug!(parser = "json_feed", %url_host, %url_path, "parse");
+
+        let raw: RawFeed = serde_json::from_slice(&self.bytes)
+            .map_err(|e| FeedError::Parse(anyhow::Error::new(e)))?;
+        if !raw.version.starts_with("https://jsonfeed.org/version/") {
+            return Err(FeedError::Parse(anyhow::anyhow!(
+                "unsupported JSON Feed version {}",
+                raw.version
+            )));
+        }
+        debug!(version = %raw.version, items = raw.items.len());
+
+        let title = raw
+            .title
+            .as_deref()
+            .and_then(plain_text)
+            .unwrap_or_default();
+        let site_link = raw
+            .home_page_url
+            .as_deref()
+            .and_then(|u| absolutise(u, &self.url));
+        let feed_author = author_name(&raw.authors, raw.author.as_ref());
+
+        let items = raw
+            .items
+            .into_iter()
+            .filter_map(|item| item_from_raw(item, &self.url, feed_author.as_deref()))
+            .collect::<Vec<_>>();
+        debug!(items = items.len(), "feed items extracted");
+
+        Ok(Feed::new(self.url.clone(), title, site_link, items))
+    }
+}
+
+fn item_from_raw(item: RawItem, feed_url: &Url, feed_author: Option<&str>) -> Option<FeedItem> {
+    let guid = item.id.as_ref().and_then(|id| match id {
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    });
+
+    let Some(link) = item
+        .url
+        .as_deref()
+        .or(item.external_url.as_deref())
+        .and_then(|u| absolutise(u, feed_url))
+    else {
+        debug!(?guid, "Feed item has no usable link; skipping.");
+        return None;
+    };
+
+    let title = item.title.as_deref().and_then(plain_text);
+    let author =
+        author_name(&item.authors, item.author.as_ref()).or_else(|| feed_author.map(str::to_owned));
+
+    let summary = item.summary.as_deref().and_then(plain_text).or_else(|| {
+        item.content_text
+            .as_deref()
+            .or(item.content_html.as_deref())
+            .and_then(plain_text)
+            .map(|s| truncate_chars(&s, 500))
+    });
+
+    let published_time = item.date_published.as_deref().and_then(parse_time);
+    let updated_time = item.date_modified.as_deref().and_then(parse_time);
+    let thumbnail_url = item
+        .image
+        .as_deref()
+        .or(item.banner_image.as_deref())
+        .and_then(|u| absolutise(u, feed_url));
+
+    let metadata = Metadata::new(summary, published_time, updated_time, thumbnail_url);
+
+    Some(FeedItem::new(link, guid, title, author, Some(metadata)))
+}
+
+fn author_name(authors: &[RawAuthor], author: Option<&RawAuthor>) -> Option<String> {
+    authors
+        .iter()
+        .chain(author)
+        .filter_map(|a| a.name.as_deref())
+        .map(str::trim)
+        .find(|n| !n.is_empty())
+        .map(str::to_owned)
+}
+
+fn parse_time(s: &str) -> Option<DateTime<Utc>> {
+    match DateTime::parse_from_rfc3339(s.trim()) {
+        Ok(dt) => Some(dt.with_timezone(&Utc)),
+        Err(e) => {
+            debug!(value = %s, error = %e, "Ignoring malformed JSON Feed date.");
+            None
+        }
+    }
+}
+
+fn looks_like_json_feed(bytes: &Bytes) -> bool {
+    let starts_with_object = bytes
+        .iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_some_and(|b| *b == b'{');
+    starts_with_object
+        && bytes
+            .windows(22)
+            .any(|w| w == b"jsonfeed.org/version/1".as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderMap;
+
+    use super::*;
+
+    fn parse(body: &str) -> Result<Feed, FeedError> {
+        let url = Url::parse("https://example.com/feed.json").unwrap();
+        let bytes = Bytes::from(body.to_string());
+        JsonFeedParser::new(&bytes, &HeaderMap::new(), &url)
+            .expect("recognised as a JSON feed")
+            .parse()
+    }
+
+    fn time(s: &str) -> Option<DateTime<Utc>> {
+        Some(DateTime::parse_from_rfc3339(s).unwrap().to_utc())
+    }
+
+    #[test]
+    fn items_are_mapped() {
+        let feed = parse(
+            r#"{
+  "version": "https://jsonfeed.org/version/1.1",
+  "title": "Example <b>JSON</b>",
+  "home_page_url": "https://example.com/",
+  "authors": [{ "name": "  " }, { "name": "Feed Author" }],
+  "items": [
+    {
+      "id": " item-1 ",
+      "url": "https://example.com/posts/1",
+      "title": "First",
+      "summary": "<p>The   summary.</p>",
+      "content_html": "<p>Ignored for the summary.</p>",
+      "image": "/images/1.jpg",
+      "banner_image": "/images/banner.jpg",
+      "date_published": "2025-10-14T08:30:00+02:00",
+      "date_modified": "2025-10-15T00:00:00Z",
+      "authors": [{ "name": "Jane Doe" }]
+    },
+    {
+      "id": 2,
+      "external_url": "posts/2",
+      "content_text": "Only text.",
+      "banner_image": "https://cdn.example.com/banner.png",
+      "date_published": "last Tuesday"
+    },
+    { "id": "no-link", "title": "Skipped" }
+  ]
+}"#,
+        )
+        .unwrap();
+        assert_eq!(feed.title(), "Example JSON");
+        assert_eq!(feed.site_link().unwrap().as_str(), "https://example.com/");
+        let [first, second] = feed.items() else {
+            panic!("expected two items, got {:?}", feed.items());
+        };
+
+        assert_eq!(first.link().as_str(), "https://example.com/posts/1");
+        assert_eq!(first.guid(), Some("item-1"));
+        assert_eq!(first.title(), Some("First"));
+        assert_eq!(first.author(), Some("Jane Doe"));
+        let metadata = first.metadata();
+        assert_eq!(metadata.summary(), Some("The summary."));
+        assert_eq!(metadata.published_time(), time("2025-10-14T06:30:00Z"));
+        assert_eq!(metadata.updated_time(), time("2025-10-15T00:00:00Z"));
+        // `image` is preferred over `banner_image`.
+        assert_eq!(
+            metadata.thumbnail_url().unwrap().as_str(),
+            "https://example.com/images/1.jpg"
+        );
+
+        // Numeric ids are kept, relative links resolve against the feed, and the feed's author
+        // stands in for the item's.
+        assert_eq!(second.link().as_str(), "https://example.com/posts/2");
+        assert_eq!(second.guid(), Some("2"));
+        assert_eq!(second.title(), None);
+        assert_eq!(second.author(), Some("Feed Author"));
+        let metadata = second.metadata();
+        assert_eq!(metadata.summary(), Some("Only text."));
+        assert_eq!(metadata.published_time(), None);
+        assert_eq!(
+            metadata.thumbnail_url().unwrap().as_str(),
+            "https://cdn.example.com/banner.png"
+        );
+    }
+
+    #[test]
+    fn version_1_0_authors_are_read() {
+        let feed = parse(
+            r#"{
+  "version": "https://jsonfeed.org/version/1",
+  "title": "Old",
+  "author": { "name": "Old Author" },
+  "items": [
+    { "id": "a", "url": "https://example.com/a", "author": { "name": "Item Author" } },
+    { "id": "b", "url": "https://example.com/b" }
+  ]
+}"#,
+        )
+        .unwrap();
+        let authors = feed
+            .items()
+            .iter()
+            .map(FeedItem::author)
+            .collect::<Vec<_>>();
+        assert_eq!(authors, [Some("Item Author"), Some("Old Author")]);
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let error =
+            parse(r#"{"version": "https://example.com/jsonfeed.org/version/1", "items": []}"#);
+        assert!(matches!(error, Err(FeedError::Parse(_))));
+    }
+
+    #[test]
+    fn only_feeds_are_recognised() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let headers = HeaderMap::new();
+        let recognised = |body: &str| {
+            JsonFeedParser::new(&Bytes::from(body.to_string()), &headers, &url).is_some()
+        };
+        assert!(recognised(
+            "\n  {\"version\": \"https://jsonfeed.org/version/1.1\"}"
+        ));
+        assert!(!recognised("{\"version\": \"2.0\"}"));
+        assert!(!recognised("[\"https://jsonfeed.org/version/1\"]"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            "application/feed+json".parse().unwrap(),
+        );
+        assert!(JsonFeedParser::new(&Bytes::from_static(b"?"), &headers, &url).is_some());
+    }
+}
@@ -0,0 +1,209 @@
+use bytes::Bytes;
+use chrono::TimeDelta;
+use serde::Serialize;
+use thiserror::Error;
+use url::Url;
+
+use crate::{
+    content::{Content, Unfetched},
+    feed::{json::JsonFeedParser, xml::XmlFeedParser},
+    metadata::Metadata,
+};
+
+mod json;
+mod xml;
+
+/// A parsed feed, _i.e._ a producer of URLs to be fetched and parsed into entries.
+#[derive(Debug, Clone, Serialize)]
+pub struct Feed {
+    url: Url,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    site_link: Option<Url>,
+    items: Vec<FeedItem>,
+    #[serde(skip)]
+    refresh_hints: RefreshHints,
+}
+
+impl Feed {
+    pub fn new(url: Url, title: String, site_link: Option<Url>, items: Vec<FeedItem>) -> Self {
+        Self {
+            url,
+            title,
+            site_link,
+            items,
+            refresh_hints: RefreshHints::default(),
+        }
+    }
+
+    pub fn with_refresh_hints(mut self, refresh_hints: RefreshHints) -> Self {
+        self.refresh_hints = refresh_hints;
+        self
+    }
+
+    pub fn refresh_hints(&self) -> &RefreshHints {
+        &self.refresh_hints
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn site_link(&self) -> Option<&Url> {
+        self.site_link.as_ref()
+    }
+
+    pub fn items(&self) -> &[FeedItem] {
+        &self.items
+    }
+
+    pub fn into_items(self) -> Vec<FeedItem> {
+        self.items
+    }
+}
+
+/// What a feed, or the response it came in, says about how often to poll it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefreshHints {
+    /// RSS `<ttl>`.
+    pub ttl: Option<TimeDelta>,
+    /// `sy:updatePeriod` divided by `sy:updateFrequency`.
+    pub update_period: Option<TimeDelta>,
+    /// `max-age` of the `Cache-Control` header.
+    pub max_age: Option<TimeDelta>,
+}
+
+impl RefreshHints {
+    /// The longest of the hinted intervals; polling more often than this is pointless.
+    pub fn min_interval(&self) -> Option<TimeDelta> {
+        [self.ttl, self.update_period, self.max_age]
+            .into_iter()
+            .flatten()
+            .max()
+    }
+}
+
+/// A single item of a feed. Only the link is required; everything else is used to seed the
+/// metadata of the entry once it is fetched.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedItem {
+    link: Url,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    guid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    metadata: Metadata,
+}
+
+impl FeedItem {
+    pub fn new(
+        link: Url,
+        guid: Option<String>,
+        title: Option<String>,
+        author: Option<String>,
+        metadata: Option<Metadata>,
+    ) -> Self {
+        let metadata = metadata.unwrap_or_default();
+        Self {
+            link,
+            guid,
+            title,
+            author,
+            metadata,
+        }
+    }
+
+    pub fn link(&self) -> &Url {
+        &self.link
+    }
+
+    pub fn guid(&self) -> Option<&str> {
+        self.guid.as_deref()
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Turn this item into unfetched content, seeded with the metadata found in the feed.
+    pub fn into_content(self) -> Content<Unfetched> {
+        Content::<Unfetched>::new(self.link, Some(self.metadata))
+    }
+}
+
+pub trait FeedParser: Send {
+    fn new(bytes: &Bytes, headers: &reqwest::header::HeaderMap, url: &Url) -> Option<Box<Self>>
+    where
+        Self: Sized;
+    fn parse(&self) -> Result<Feed, FeedError>;
+}
+
+type FeedParserFn = fn(&Bytes, &reqwest::header::HeaderMap, &Url) -> Option<Box<dyn FeedParser>>;
+
+/// List of feed parser constructors to iterate over.
+///
+/// NOTE: As with `parser::PARSERS`, ordering signifies priority.
+static FEED_PARSERS: &[FeedParserFn] = &[construct::<XmlFeedParser>, construct::<JsonFeedParser>];
+
+fn construct<P>(
+    bytes: &Bytes,
+    headers: &reqwest::header::HeaderMap,
+    url: &Url,
+) -> Option<Box<dyn FeedParser>>
+where
+    P: FeedParser + 'static,
+{
+    P::new(bytes, headers, url).map(|x| x as Box<dyn FeedParser>)
+}
+
+pub fn identify(
+    bytes: &Bytes,
+    headers: &reqwest::header::HeaderMap,
+    url: &Url,
+) -> Option<Box<dyn FeedParser>> {
+    FEED_PARSERS.iter().find_map(|f| f(bytes, headers, url))
+}
+
+#[derive(Debug, Error)]
+pub enum FeedError {
+    #[error("Parsing feed failed")]
+    Parse(#[source] anyhow::Error),
+}
+
+/// Reduce a possibly HTML-formatted feed field to its plain text.
+fn plain_text(s: &str) -> Option<String> {
+    let fragment = scraper::Html::parse_fragment(s);
+    let text = fragment.root_element().text().collect::<Vec<_>>().join(" ");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+fn absolutise(raw: &str, base: &Url) -> Option<Url> {
+    let s = raw.trim();
+    if s.is_empty() {
+        return None;
+    }
+    Url::parse(s).or_else(|_| base.join(s)).ok()
+}
+
+/// Truncate to at most `max` characters, marking the cut with an ellipsis.
+fn truncate_chars(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((end, _)) => format!("{}…", &s[..end]),
+        None => s.to_string(),
+    }
+}
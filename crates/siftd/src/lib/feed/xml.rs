@@ -0,0 +1,434 @@
+use bytes::Bytes;
+use chrono::TimeDelta;
+use mime::Mime;
+use tracing::debug;
+use url::Url;
+
+use crate::{
+    feed::{
+        absolutise, plain_text, truncate_chars, Feed, FeedError, FeedItem, FeedParser, RefreshHints,
+    },
+    metadata::Metadata,
+};
+
+/// Parser for the XML feed formats: RSS 0.9x/2.0, RSS 1.0 (RDF) and Atom.
+pub struct XmlFeedParser {
+    url: Url,
+    bytes: Bytes,
+}
+
+impl FeedParser for XmlFeedParser {
+    fn new(bytes: &Bytes, headers: &reqwest::header::HeaderMap, url: &Url) -> Option<Box<Self>>
+    where
+        Self: Sized,
+    {
+        let mime = headers
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|s| s.parse::<Mime>().ok());
+
+        let is_feed_mime = mime.as_ref().is_some_and(|m| {
+            let essence = m.essence_str();
+            [
+                "application/rss+xml",
+                "application/atom+xml",
+                "application/rdf+xml",
+            ]
+            .iter()
+            .any(|e| essence.eq_ignore_ascii_case(e))
+        });
+
+        // Feeds are just as often served as `text/xml`, `application/xml` or even `text/plain`,
+        // so fall back to peeking at the root element.
+        if is_feed_mime || looks_like_xml_feed(bytes) {
+            Some(Box::new(Self {
+                url: url.clone(),
+                bytes: bytes.clone(),
+            }))
+        } else {
+            debug!("Body does not look like an XML feed; skipping XmlFeedParser.");
+            None
+        }
+    }
+
+    fn parse(&self) -> Result<Feed, FeedError> {
+        let (url_host, url_path) = crate::url_host_and_path(&self.url);
+        debug!(parser = "xml_feed", %url_host, %url_path, "parse");
+
+        // Leave missing IDs empty rather than letting feed-rs synthesise one, so that we can
+        // tell an actual guid apart from a generated hash.
+        let raw = feed_rs::parser::Builder::new()
+            .base_uri(Some(self.url.as_str()))
+            .id_generator(|_, _, _| String::new())
+            .build()
+            .parse(self.bytes.as_ref())
+            .map_err(|e| FeedError::Parse(anyhow::Error::new(e)))?;
+        debug!(feed_type = ?raw.feed_type, entries = raw.entries.len());
+
+        let title = raw
+            .title
+            .as_ref()
+            .and_then(|t| plain_text(&t.content))
+            .unwrap_or_default();
+
+        let site_link = pick_link(&raw.links).and_then(|l| absolutise(&l.href, &self.url));
+        let refresh_hints = RefreshHints {
+            ttl: raw.ttl.map(|minutes| TimeDelta::minutes(minutes.into())),
+            update_period: update_period(&self.bytes),
+            max_age: None,
+        };
+
+        let items = raw
+            .entries
+            .into_iter()
+            .filter_map(|entry| item_from_entry(entry, &self.url))
+            .collect::<Vec<_>>();
+        debug!(items = items.len(), "feed items extracted");
+
+        Ok(Feed::new(self.url.clone(), title, site_link, items).with_refresh_hints(refresh_hints))
+    }
+}
+
+fn item_from_entry(entry: feed_rs::model::Entry, feed_url: &Url) -> Option<FeedItem> {
+    let Some(link) = pick_link(&entry.links).and_then(|l| absolutise(&l.href, feed_url)) else {
+        debug!(guid = %entry.id, "Feed item has no usable link; skipping.");
+        return None;
+    };
+
+    let guid = (!entry.id.trim().is_empty()).then(|| entry.id.trim().to_string());
+    let title = entry.title.as_ref().and_then(|t| plain_text(&t.content));
+    let author = entry.authors.iter().find_map(person_name);
+
+    // Prefer the summary; RSS feeds that only ship `content:encoded` get its text truncated to
+    // 500 characters.
+    let summary = entry
+        .summary
+        .as_ref()
+        .and_then(|t| plain_text(&t.content))
+        .or_else(|| {
+            entry
+                .content
+                .as_ref()
+                .and_then(|c| c.body.as_deref())
+                .and_then(plain_text)
+                .map(|s| truncate_chars(&s, 500))
+        });
+
+    let thumbnail_url = pick_thumbnail(&entry, feed_url);
+
+    let metadata = Metadata::new(summary, entry.published, entry.updated, thumbnail_url);
+
+    Some(FeedItem::new(link, guid, title, author, Some(metadata)))
+}
+
+/// RSS 2.0 `<author>` is an email address, optionally followed by the name in parentheses, which
+/// feed-rs stores as the email of a person literally named "author".
+fn person_name(person: &feed_rs::model::Person) -> Option<String> {
+    let name = person.name.trim();
+    if !name.is_empty() && name != "author" {
+        return Some(name.to_string());
+    }
+    let email = person.email.as_deref()?.trim();
+    let name = email
+        .split_once('(')
+        .and_then(|(_, rest)| rest.strip_suffix(')'))
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .unwrap_or(email);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Pick the link pointing at the HTML representation: `rel="alternate"` or no `rel` at all.
+fn pick_link(links: &[feed_rs::model::Link]) -> Option<&feed_rs::model::Link> {
+    links
+        .iter()
+        .find(|l| matches!(l.rel.as_deref(), None | Some("alternate")))
+        .or_else(|| links.iter().find(|l| l.rel.as_deref() != Some("self")))
+}
+
+fn pick_thumbnail(entry: &feed_rs::model::Entry, feed_url: &Url) -> Option<Url> {
+    let from_thumbnails = entry
+        .media
+        .iter()
+        .flat_map(|m| m.thumbnails.iter())
+        .find_map(|t| absolutise(&t.image.uri, feed_url));
+    if from_thumbnails.is_some() {
+        return from_thumbnails;
+    }
+
+    entry
+        .media
+        .iter()
+        .flat_map(|m| m.content.iter())
+        .filter(|c| {
+            c.content_type
+                .as_ref()
+                .is_some_and(|t| t.ty().as_str().eq_ignore_ascii_case("image"))
+        })
+        .find_map(|c| c.url.clone())
+}
+
+/// Interval of the RSS syndication module's `sy:updatePeriod` and `sy:updateFrequency`, which
+/// feed-rs does not parse.
+fn update_period(bytes: &Bytes) -> Option<TimeDelta> {
+    // The cut may split a character, and the feed need not be UTF-8 at all.
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(16 * 1024)]);
+    let element = |name: &str| {
+        let start = text.find(&format!(":{name}>"))? + name.len() + 2;
+        let len = text[start..].find('<')?;
+        Some(text[start..start + len].trim())
+    };
+    let period = match element("updatePeriod")? {
+        "hourly" => TimeDelta::hours(1),
+        "daily" => TimeDelta::days(1),
+        "weekly" => TimeDelta::weeks(1),
+        "monthly" => TimeDelta::days(30),
+        "yearly" => TimeDelta::days(365),
+        _ => return None,
+    };
+    let frequency = element("updateFrequency")
+        .and_then(|f| f.parse::<i32>().ok())
+        .filter(|f| *f > 0)
+        .unwrap_or(1);
+    Some(period / frequency)
+}
+
+fn looks_like_xml_feed(bytes: &Bytes) -> bool {
+    let probe = &bytes[..bytes.len().min(2048)];
+    let lower = probe
+        .iter()
+        .map(|b| b.to_ascii_lowercase())
+        .collect::<Vec<_>>();
+    let hay = lower.as_slice();
+    hay.windows(4).any(|w| w == b"<rss".as_ref())
+        || hay.windows(8).any(|w| w == b"<rdf:rdf".as_ref())
+        || hay
+            .windows(6)
+            .any(|w| w.starts_with(b"<feed") && (w[5] == b'>' || w[5].is_ascii_whitespace()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use reqwest::header::HeaderMap;
+
+    use super::*;
+
+    fn parse(body: &str) -> Feed {
+        let url = Url::parse("https://example.com/feeds/main.xml").unwrap();
+        let bytes = Bytes::from(body.to_string());
+        XmlFeedParser::new(&bytes, &HeaderMap::new(), &url)
+            .expect("recognised as an XML feed")
+            .parse()
+            .unwrap()
+    }
+
+    fn time(s: &str) -> Option<DateTime<Utc>> {
+        Some(DateTime::parse_from_rfc3339(s).unwrap().to_utc())
+    }
+
+    #[test]
+    fn rss_items_are_mapped() {
+        let feed = parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:media="http://search.yahoo.com/mrss/"
+     xmlns:content="http://purl.org/rss/1.0/modules/content/">
+  <channel>
+    <title>Example &amp; Co</title>
+    <link>https://example.com/</link>
+    <ttl>90</ttl>
+    <item>
+      <title>First <![CDATA[<b>post</b>]]></title>
+      <link>https://example.com/posts/1</link>
+      <guid isPermaLink="false">  post-1  </guid>
+      <author>jane@example.com (Jane Doe)</author>
+      <description>&lt;p&gt;A short   summary.&lt;/p&gt;</description>
+      <pubDate>Tue, 14 Oct 2025 08:30:00 +0200</pubDate>
+      <media:thumbnail url="/images/1.jpg"/>
+    </item>
+    <item>
+      <title>Second</title>
+      <link>/posts/2</link>
+      <author>john@example.com</author>
+      <content:encoded><![CDATA[<p>Only content.</p>]]></content:encoded>
+      <media:content url="https://cdn.example.com/2.png" type="image/png" medium="image"/>
+    </item>
+    <item>
+      <title>No link, skipped</title>
+      <guid>orphan</guid>
+    </item>
+  </channel>
+</rss>"#,
+        );
+        assert_eq!(feed.title(), "Example & Co");
+        assert_eq!(feed.site_link().unwrap().as_str(), "https://example.com/");
+        assert_eq!(feed.refresh_hints().ttl, Some(TimeDelta::minutes(90)));
+        let [first, second] = feed.items() else {
+            panic!("expected two items, got {:?}", feed.items());
+        };
+
+        assert_eq!(first.link().as_str(), "https://example.com/posts/1");
+        assert_eq!(first.guid(), Some("post-1"));
+        assert_eq!(first.title(), Some("First post"));
+        assert_eq!(first.author(), Some("Jane Doe"));
+        let metadata = first.metadata();
+        assert_eq!(metadata.summary(), Some("A short summary."));
+        assert_eq!(metadata.published_time(), time("2025-10-14T06:30:00Z"));
+        assert_eq!(
+            metadata.thumbnail_url().unwrap().as_str(),
+            "https://example.com/images/1.jpg"
+        );
+
+        // Relative links resolve against the feed.
+        assert_eq!(second.link().as_str(), "https://example.com/posts/2");
+        assert_eq!(second.guid(), None);
+        assert_eq!(second.author(), Some("john@example.com"));
+        let metadata = second.metadata();
+        assert_eq!(metadata.summary(), Some("Only content."));
+        assert_eq!(metadata.published_time(), None);
+        assert_eq!(
+            metadata.thumbnail_url().unwrap().as_str(),
+            "https://cdn.example.com/2.png"
+        );
+    }
+
+    #[test]
+    fn rdf_items_are_mapped() {
+        let feed = parse(
+            r#"<?xml version="1.0"?>
+<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"
+         xmlns="http://purl.org/rss/1.0/"
+         xmlns:dc="http://purl.org/dc/elements/1.1/"
+         xmlns:sy="http://purl.org/rss/1.0/modules/syndication/">
+  <channel rdf:about="https://example.com/">
+    <title>Example RDF</title>
+    <link>https://example.com/</link>
+    <sy:updatePeriod>daily</sy:updatePeriod>
+    <sy:updateFrequency>4</sy:updateFrequency>
+  </channel>
+  <item rdf:about="https://example.com/rdf/1">
+    <title>RDF item</title>
+    <link>https://example.com/rdf/1</link>
+    <description>About RDF.</description>
+    <dc:creator>Ada</dc:creator>
+    <dc:date>2025-10-01T12:00:00Z</dc:date>
+  </item>
+</rdf:RDF>"#,
+        );
+        assert_eq!(feed.title(), "Example RDF");
+        assert_eq!(
+            feed.refresh_hints().update_period,
+            Some(TimeDelta::hours(6))
+        );
+        let [item] = feed.items() else {
+            panic!("expected one item, got {:?}", feed.items());
+        };
+        assert_eq!(item.link().as_str(), "https://example.com/rdf/1");
+        assert_eq!(item.title(), Some("RDF item"));
+        assert_eq!(item.author(), Some("Ada"));
+        assert_eq!(item.metadata().summary(), Some("About RDF."));
+        assert_eq!(
+            item.metadata().published_time(),
+            time("2025-10-01T12:00:00Z")
+        );
+    }
+
+    #[test]
+    fn atom_entries_are_mapped() {
+        let feed = parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom"
+      xmlns:media="http://search.yahoo.com/mrss/">
+  <title type="html">Example &lt;em&gt;Atom&lt;/em&gt;</title>
+  <link rel="self" href="https://example.com/feeds/main.xml"/>
+  <link href="https://example.com/"/>
+  <id>urn:example:feed</id>
+  <updated>2025-10-02T00:00:00Z</updated>
+  <entry>
+    <title>Atom entry</title>
+    <link rel="self" href="https://example.com/api/entries/1"/>
+    <link rel="alternate" type="text/html" href="entries/1"/>
+    <id>tag:example.com,2025:1</id>
+    <author><name>Grace</name><email>grace@example.com</email></author>
+    <published>2025-10-01T09:00:00+01:00</published>
+    <updated>2025-10-02T10:00:00Z</updated>
+    <summary type="html">&lt;p&gt;Summary&lt;/p&gt; of the entry</summary>
+    <media:thumbnail url="https://example.com/thumbs/1.jpg"/>
+  </entry>
+</feed>"#,
+        );
+        assert_eq!(feed.title(), "Example Atom");
+        assert_eq!(feed.site_link().unwrap().as_str(), "https://example.com/");
+        let [entry] = feed.items() else {
+            panic!("expected one entry, got {:?}", feed.items());
+        };
+        assert_eq!(entry.link().as_str(), "https://example.com/feeds/entries/1");
+        assert_eq!(entry.guid(), Some("tag:example.com,2025:1"));
+        assert_eq!(entry.title(), Some("Atom entry"));
+        assert_eq!(entry.author(), Some("Grace"));
+        let metadata = entry.metadata();
+        assert_eq!(metadata.summary(), Some("Summary of the entry"));
+        assert_eq!(metadata.published_time(), time("2025-10-01T08:00:00Z"));
+        assert_eq!(metadata.updated_time(), time("2025-10-02T10:00:00Z"));
+        assert_eq!(
+            metadata.thumbnail_url().unwrap().as_str(),
+            "https://example.com/thumbs/1.jpg"
+        );
+    }
+
+    #[test]
+    fn update_periods_are_read_from_any_encoding() {
+        let channel = |period: &str, frequency: &str| {
+            format!(
+                "<rss><channel><sy:updatePeriod>{period}</sy:updatePeriod>\
+                 <sy:updateFrequency>{frequency}</sy:updateFrequency>"
+            )
+        };
+        let bytes = |s: String| Bytes::from(s.into_bytes());
+        assert_eq!(
+            update_period(&bytes(channel("hourly", "2"))),
+            Some(TimeDelta::minutes(30))
+        );
+        assert_eq!(
+            update_period(&bytes(channel(" weekly ", "0"))),
+            Some(TimeDelta::weeks(1))
+        );
+        assert_eq!(update_period(&bytes(channel("often", "1"))), None);
+
+        // A multibyte character split by the 16 KiB cut, and Latin-1 text.
+        let split = channel("daily", "2") + "." + &"é".repeat(16 * 1024);
+        assert!(!split.is_char_boundary(16 * 1024));
+        assert_eq!(update_period(&bytes(split)), Some(TimeDelta::hours(12)));
+        let mut latin1 = b"<title>Caf\xe9</title>".to_vec();
+        latin1.extend_from_slice(channel("daily", "1").as_bytes());
+        assert_eq!(
+            update_period(&Bytes::from(latin1)),
+            Some(TimeDelta::days(1))
+        );
+    }
+
+    #[test]
+    fn only_feeds_are_recognised() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let headers = HeaderMap::new();
+        let recognised = |body: &str| {
+            XmlFeedParser::new(&Bytes::from(body.to_string()), &headers, &url).is_some()
+        };
+        assert!(recognised("<?xml version=\"1.0\"?>\n<rss version=\"2.0\">"));
+        assert!(recognised(
+            "<feed\n  xmlns=\"http://www.w3.org/2005/Atom\">"
+        ));
+        assert!(recognised("<RDF:RDF>"));
+        assert!(!recognised("<html><body><feedback/></body></html>"));
+        assert!(!recognised(
+            "{\"version\": \"https://jsonfeed.org/version/1.1\"}"
+        ));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            "application/atom+xml; charset=utf-8".parse().unwrap(),
+        );
+        assert!(XmlFeedParser::new(&Bytes::from_static(b"?"), &headers, &url).is_some());
+    }
+}
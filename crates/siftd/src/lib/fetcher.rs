@@ -0,0 +1,515 @@
+//! Background fetching of anything with a URL, be it a feed or a single entry.
+//!
+//! Producers push [`Fetchable`]s onto a [`FetchQueue`] with a priority; a [`Fetcher`] pulls them
+//! off, highest priority first, while keeping the number of fetches in flight under a global and
+//! a per-host limit.
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use thiserror::Error;
+use tokio::sync::{Notify, Semaphore};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::{
+    content::{Content, ContentError, FetchOutcome, Unfetched},
+    feed::RefreshHints,
+    handler::AppState,
+    storage::StorageError,
+};
+
+/// Something the fetcher can fetch and store.
+#[async_trait]
+pub trait Fetchable: Send + 'static {
+    /// URL fetched, which decides the host the fetch counts against.
+    fn url(&self) -> &Url;
+
+    /// Fetch, parse and store, returning anything that should be fetched as a consequence,
+    /// _e.g._ the new items of a feed.
+    async fn ingest(
+        self: Box<Self>,
+        state: &AppState,
+    ) -> Result<Vec<Box<dyn Fetchable>>, FetcherError>;
+}
+
+#[async_trait]
+impl Fetchable for Content<Unfetched> {
+    fn url(&self) -> &Url {
+        Content::url(self)
+    }
+
+    async fn ingest(
+        self: Box<Self>,
+        state: &AppState,
+    ) -> Result<Vec<Box<dyn Fetchable>>, FetcherError> {
+        let feed_id = self.feed_id();
+        let url = self.url().clone();
+        let fetched = self.fetch(state.http()).await;
+        state.track_fetch(&url, &fetched).await;
+        let entry = fetched?
+            .parse_pages(state.http(), state.site_rules(), state.max_article_pages())
+            .await?;
+        let stored = state.storage().entries().upsert(&entry, feed_id).await?;
+        debug!(entry_id = stored.id(), "entry stored");
+        state.spawn_embedding(&stored);
+        Ok(Vec::new())
+    }
+}
+
+/// Refresh of a feed: fetch it, update it, and queue up the items not stored yet.
+#[derive(Debug, Clone)]
+pub struct FeedRefresh {
+    url: Url,
+}
+
+impl FeedRefresh {
+    pub fn new(url: Url) -> Self {
+        Self { url }
+    }
+}
+
+#[async_trait]
+impl Fetchable for FeedRefresh {
+    fn url(&self) -> &Url {
+        &self.url
+    }
+
+    async fn ingest(
+        self: Box<Self>,
+        state: &AppState,
+    ) -> Result<Vec<Box<dyn Fetchable>>, FetcherError> {
+        let feeds = state.storage().feeds();
+        let content = Content::<Unfetched>::new(self.url, None);
+        let known = feeds.find_by_url(content.url()).await?;
+        let ignore_robots = known.as_ref().is_some_and(|f| f.ignore_robots);
+        let content = content.with_ignore_robots(ignore_robots);
+        let url = content.url().clone();
+        let fetched = match &known {
+            Some(known) => {
+                content
+                    .fetch_if_modified(state.http(), &known.validators)
+                    .await
+            }
+            None => content
+                .fetch(state.http())
+                .await
+                .map(|fetched| FetchOutcome::Modified(Box::new(fetched))),
+        };
+        state.track_fetch(&url, &fetched).await;
+        let fetched = match fetched? {
+            FetchOutcome::Modified(fetched) => *fetched,
+            FetchOutcome::NotModified { max_age } => {
+                // Only conditional fetches come back unmodified, so the feed is known. What the
+                // feed itself hinted at is unchanged too.
+                if let Some(known) = known {
+                    debug!(feed_id = known.id, "feed not modified");
+                    let hints = RefreshHints {
+                        max_age: max_age.or(known.refresh_hints.max_age),
+                        ..known.refresh_hints
+                    };
+                    state
+                        .scheduler()
+                        .observe(state.storage(), known.id, &hints, &[])
+                        .await?;
+                }
+                return Ok(Vec::new());
+            }
+        };
+        let validators = fetched.validators();
+        let feed = fetched.parse_feed()?;
+        let feed_id = feeds.upsert(&feed).await?;
+        feeds.set_validators(feed_id, &validators).await?;
+        let hints = *feed.refresh_hints();
+        feeds.set_refresh_hints(feed_id, &hints).await?;
+
+        let entries = state.storage().entries();
+        let mut new = Vec::<Box<dyn Fetchable>>::new();
+        let post_times = feed
+            .items()
+            .iter()
+            .filter_map(|item| item.metadata().published_time())
+            .collect::<Vec<_>>();
+        for item in feed.into_items() {
+            if entries.find_by_url(item.link()).await?.is_none() {
+                let content = item
+                    .into_content()
+                    .with_feed_id(Some(feed_id))
+                    .with_ignore_robots(ignore_robots);
+                new.push(Box::new(content));
+            }
+        }
+        debug!(feed_id, new_items = new.len(), "feed refreshed");
+        state
+            .scheduler()
+            .observe(state.storage(), feed_id, &hints, &post_times)
+            .await?;
+        Ok(new)
+    }
+}
+
+/// Order in which queued fetches are made; higher first, and first come first served among
+/// equals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Priority(pub u8);
+
+impl Priority {
+    pub const BACKGROUND: Priority = Priority(0);
+    pub const NORMAL: Priority = Priority(64);
+    /// Fetches a user is waiting on.
+    pub const INTERACTIVE: Priority = Priority(128);
+}
+
+/// How many fetches may be in flight at once.
+#[derive(Debug, Clone, Copy)]
+pub struct FetchLimits {
+    pub global: usize,
+    pub per_host: usize,
+}
+
+impl FetchLimits {
+    pub const DEFAULT_GLOBAL: usize = 16;
+    pub const DEFAULT_PER_HOST: usize = 2;
+}
+
+impl Default for FetchLimits {
+    fn default() -> Self {
+        Self {
+            global: Self::DEFAULT_GLOBAL,
+            per_host: Self::DEFAULT_PER_HOST,
+        }
+    }
+}
+
+struct Queued {
+    priority: Priority,
+    sequence: u64,
+    item: Box<dyn Fetchable>,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    heap: BinaryHeap<Queued>,
+    sequence: u64,
+    /// URLs queued or being fetched, so that nothing is fetched twice at once.
+    pending: HashSet<Url>,
+    in_flight: HashMap<String, usize>,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<QueueState>,
+    /// Signalled whenever something is queued or a fetch finishes.
+    changed: Notify,
+}
+
+/// Handle for queueing fetches. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct FetchQueue {
+    shared: Arc<Shared>,
+}
+
+impl FetchQueue {
+    /// Queue `item`, unless its URL is already queued or being fetched. Returns whether it was
+    /// queued.
+    pub fn push(&self, item: Box<dyn Fetchable>, priority: Priority) -> bool {
+        let mut state = self.shared.state.lock().unwrap();
+        if !state.pending.insert(item.url().clone()) {
+            debug!(url = %item.url(), "already queued");
+            return false;
+        }
+        state.sequence += 1;
+        let sequence = state.sequence;
+        state.heap.push(Queued {
+            priority,
+            sequence,
+            item,
+        });
+        drop(state);
+        self.shared.changed.notify_one();
+        true
+    }
+
+    /// Number of fetches queued but not started.
+    pub fn len(&self) -> usize {
+        self.shared.state.lock().unwrap().heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Take the highest priority item whose host has room for another fetch, and count it as
+    /// in flight until the returned guard is dropped.
+    fn pop(&self, per_host: usize) -> Option<(Queued, InFlight)> {
+        let mut state = self.shared.state.lock().unwrap();
+        let mut blocked = Vec::new();
+        let mut found = None;
+        while let Some(queued) = state.heap.pop() {
+            let host = host(queued.item.url());
+            if state.in_flight.get(&host).copied().unwrap_or(0) < per_host {
+                *state.in_flight.entry(host.clone()).or_default() += 1;
+                let in_flight = InFlight {
+                    queue: self.clone(),
+                    url: queued.item.url().clone(),
+                    host,
+                };
+                found = Some((queued, in_flight));
+                break;
+            }
+            blocked.push(queued);
+        }
+        state.heap.extend(blocked);
+        found
+    }
+
+    fn finish(&self, url: &Url, host: &str) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.pending.remove(url);
+        if let Some(n) = state.in_flight.get_mut(host) {
+            *n -= 1;
+            if *n == 0 {
+                state.in_flight.remove(host);
+            }
+        }
+        drop(state);
+        self.shared.changed.notify_one();
+    }
+}
+
+/// A fetch taken off a [`FetchQueue`], which counts as in flight until this is dropped, however
+/// the fetch ends.
+struct InFlight {
+    queue: FetchQueue,
+    url: Url,
+    host: String,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.queue.finish(&self.url, &self.host);
+    }
+}
+
+impl fmt::Debug for FetchQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FetchQueue")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Drains a [`FetchQueue`], see the module documentation.
+#[derive(Debug)]
+pub struct Fetcher {
+    queue: FetchQueue,
+    limits: FetchLimits,
+}
+
+impl Fetcher {
+    pub fn new(queue: FetchQueue, limits: FetchLimits) -> Self {
+        Self { queue, limits }
+    }
+
+    /// Fetch queued items forever, storing the results through `state`. Whatever a fetch
+    /// produces is queued with the priority of the fetch that produced it.
+    pub async fn run(self, state: AppState) {
+        info!(
+            global = self.limits.global,
+            per_host = self.limits.per_host,
+            "fetcher started"
+        );
+        let global = Arc::new(Semaphore::new(self.limits.global.max(1)));
+        let per_host = self.limits.per_host.max(1);
+        loop {
+            let permit = global
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("the semaphore is never closed");
+            let (queued, in_flight) = loop {
+                if let Some(next) = self.queue.pop(per_host) {
+                    break next;
+                }
+                // A push or finish since the pop left a permit behind, so nothing is missed.
+                self.queue.shared.changed.notified().await;
+            };
+
+            let queue = self.queue.clone();
+            let state = state.clone();
+            tokio::spawn(async move {
+                let Queued { priority, item, .. } = queued;
+                let url = item.url().clone();
+                debug!(%url, ?priority, "fetch dequeued");
+                match item.ingest(&state).await {
+                    Ok(next) => {
+                        for item in next {
+                            queue.push(item, priority);
+                        }
+                    }
+                    Err(e) => warn!(%url, error = %e, "queued fetch failed"),
+                }
+                drop(in_flight);
+                drop(permit);
+            });
+        }
+    }
+}
+
+fn host(url: &Url) -> String {
+    url.host_str().unwrap_or_default().to_string()
+}
+
+#[derive(Debug, Error)]
+pub enum FetcherError {
+    #[error(transparent)]
+    Content(#[from] ContentError),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Stub(Url);
+
+    #[async_trait]
+    impl Fetchable for Stub {
+        fn url(&self) -> &Url {
+            &self.0
+        }
+
+        async fn ingest(
+            self: Box<Self>,
+            _state: &AppState,
+        ) -> Result<Vec<Box<dyn Fetchable>>, FetcherError> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn stub(url: &str) -> Box<dyn Fetchable> {
+        Box::new(Stub(Url::parse(url).unwrap()))
+    }
+
+    /// URLs of everything `queue` hands out, in order, without finishing any.
+    fn drain(queue: &FetchQueue, per_host: usize) -> (Vec<String>, Vec<InFlight>) {
+        let mut urls = Vec::new();
+        let mut in_flight = Vec::new();
+        while let Some((queued, guard)) = queue.pop(per_host) {
+            urls.push(queued.item.url().to_string());
+            in_flight.push(guard);
+        }
+        (urls, in_flight)
+    }
+
+    #[test]
+    fn higher_priorities_go_first_then_first_come() {
+        let queue = FetchQueue::default();
+        queue.push(stub("https://a.example/1"), Priority::BACKGROUND);
+        queue.push(stub("https://b.example/1"), Priority::NORMAL);
+        queue.push(stub("https://c.example/1"), Priority::INTERACTIVE);
+        queue.push(stub("https://d.example/1"), Priority::NORMAL);
+        queue.push(stub("https://e.example/1"), Priority::BACKGROUND);
+
+        let (urls, _in_flight) = drain(&queue, 1);
+        assert_eq!(
+            urls,
+            [
+                "https://c.example/1",
+                "https://b.example/1",
+                "https://d.example/1",
+                "https://a.example/1",
+                "https://e.example/1",
+            ]
+        );
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn urls_are_queued_once_until_finished() {
+        let queue = FetchQueue::default();
+        assert!(queue.push(stub("https://a.example/1"), Priority::NORMAL));
+        assert!(!queue.push(stub("https://a.example/1"), Priority::INTERACTIVE));
+        assert_eq!(queue.len(), 1);
+
+        // Still pending while it is fetched.
+        let (_, in_flight) = queue.pop(1).unwrap();
+        assert!(!queue.push(stub("https://a.example/1"), Priority::NORMAL));
+        drop(in_flight);
+        assert!(queue.push(stub("https://a.example/1"), Priority::NORMAL));
+    }
+
+    #[test]
+    fn busy_hosts_are_skipped_until_a_fetch_finishes() {
+        let queue = FetchQueue::default();
+        for url in [
+            "https://a.example/1",
+            "https://a.example/2",
+            "https://a.example/3",
+            "https://b.example/1",
+        ] {
+            queue.push(stub(url), Priority::NORMAL);
+        }
+
+        let (urls, mut in_flight) = drain(&queue, 2);
+        assert_eq!(
+            urls,
+            [
+                "https://a.example/1",
+                "https://a.example/2",
+                "https://b.example/1"
+            ]
+        );
+        assert_eq!(queue.len(), 1);
+
+        in_flight.remove(0);
+        let (urls, _) = drain(&queue, 2);
+        assert_eq!(urls, ["https://a.example/3"]);
+    }
+
+    #[test]
+    fn a_panicking_fetch_still_finishes() {
+        let queue = FetchQueue::default();
+        queue.push(stub("https://a.example/1"), Priority::NORMAL);
+        queue.push(stub("https://a.example/2"), Priority::NORMAL);
+
+        let (_, in_flight) = queue.pop(1).unwrap();
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+            let _in_flight = in_flight;
+            panic!("ingest failed");
+        }));
+        assert!(panicked.is_err());
+
+        let (queued, _) = queue.pop(1).expect("the host is free again");
+        assert_eq!(queued.item.url().as_str(), "https://a.example/2");
+        assert!(queue.push(stub("https://a.example/1"), Priority::NORMAL));
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde::Serialize;
+
+use crate::embedding::Embedding;
+
+/// A vertex adjacent to another, and the weight of the edge between them.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Neighbour {
+    pub id: i64,
+    /// Cosine similarity of the two entries.
+    pub similarity: f32,
+}
+
+/// Mutual k-nearest-neighbour graph: entries are vertices, and v and w are adjacent,
+/// weighted by their cosine similarity, if and only if each is among the other's k most similar
+/// entries.
+///
+/// Each vertex keeps its directed k-nearest-neighbour list, from which the mutual edges follow.
+/// Inserting a vertex compares it once against every other vertex: its own list is the best k
+/// of those, and it enters the list of every vertex it is closer to than that vertex's current
+/// k-th neighbour. Only removals, which are rare, have to recompute the lists they touch.
+#[derive(Debug, Clone)]
+pub struct KnnGraph {
+    k: usize,
+    min_similarity: f32,
+    /// Normalised embedding of every vertex.
+    vectors: HashMap<i64, Embedding>,
+    /// Most similar vertices first, at most `k` of them.
+    knn: HashMap<i64, Vec<Neighbour>>,
+}
+
+impl KnnGraph {
+    pub const DEFAULT_K: usize = 10;
+
+    /// Empty graph. Pairs less similar than `min_similarity` are never adjacent, which keeps
+    /// edge weights positive when it is at least 0.
+    pub fn new(k: usize, min_similarity: f32) -> Self {
+        Self {
+            k: k.max(1),
+            min_similarity,
+            vectors: HashMap::new(),
+            knn: HashMap::new(),
+        }
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    pub fn contains(&self, id: i64) -> bool {
+        self.vectors.contains_key(&id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = i64> + '_ {
+        self.vectors.keys().copied()
+    }
+
+    /// Add vertex `id`, replacing it if present. Returns the vertices whose nearest-neighbour
+    /// lists changed, which includes `id`.
+    pub fn insert(&mut self, id: i64, embedding: Embedding) -> BTreeSet<i64> {
+        let mut changed = if self.contains(id) {
+            self.remove(id)
+        } else {
+            BTreeSet::new()
+        };
+        let embedding = embedding.normalised();
+
+        let mut own = Vec::new();
+        for (&other, vector) in &self.vectors {
+            let similarity = embedding.dot(vector);
+            if similarity < self.min_similarity {
+                continue;
+            }
+            own.push(Neighbour {
+                id: other,
+                similarity,
+            });
+            let list = self.knn.entry(other).or_default();
+            if offer(list, self.k, Neighbour { id, similarity }) {
+                changed.insert(other);
+            }
+        }
+        sort(&mut own);
+        own.truncate(self.k);
+
+        self.vectors.insert(id, embedding);
+        self.knn.insert(id, own);
+        changed.insert(id);
+        changed
+    }
+
+    /// Remove vertex `id`. Returns the vertices whose nearest-neighbour lists changed; those
+    /// that listed `id` get their list recomputed.
+    pub fn remove(&mut self, id: i64) -> BTreeSet<i64> {
+        let mut changed = BTreeSet::new();
+        if self.vectors.remove(&id).is_none() {
+            return changed;
+        }
+        self.knn.remove(&id);
+        let affected = self
+            .knn
+            .iter()
+            .filter(|(_, list)| list.iter().any(|n| n.id == id))
+            .map(|(&v, _)| v)
+            .collect::<Vec<_>>();
+        for v in affected {
+            let list = self.nearest(v);
+            self.knn.insert(v, list);
+            changed.insert(v);
+        }
+        changed
+    }
+
+    /// Vertices adjacent to `id` in the mutual graph, most similar first.
+    pub fn neighbours(&self, id: i64) -> Vec<Neighbour> {
+        self.knn
+            .get(&id)
+            .map(|list| {
+                list.iter()
+                    .filter(|n| self.is_listed(n.id, id))
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Directed nearest-neighbour list of `id`, most similar first.
+    pub fn nearest_neighbours(&self, id: i64) -> &[Neighbour] {
+        self.knn.get(&id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Every edge of the mutual graph once, as `(v, w, similarity)` with `v < w`.
+    pub fn edges(&self) -> impl Iterator<Item = (i64, i64, f32)> + '_ {
+        self.knn.iter().flat_map(move |(&v, list)| {
+            list.iter()
+                .filter(move |n| v < n.id && self.is_listed(n.id, v))
+                .map(move |n| (v, n.id, n.similarity))
+        })
+    }
+
+    /// Build from previously computed nearest-neighbour lists, trusting them to be up to date.
+    /// Lists of unknown vertices are dropped, and vertices without a list are inserted afresh.
+    /// Returns the vertices whose lists changed in the process.
+    pub fn restore(
+        k: usize,
+        min_similarity: f32,
+        vectors: impl IntoIterator<Item = (i64, Embedding)>,
+        mut lists: HashMap<i64, Vec<Neighbour>>,
+    ) -> (Self, BTreeSet<i64>) {
+        let mut graph = Self::new(k, min_similarity);
+        let mut pending = Vec::new();
+        for (id, vector) in vectors {
+            match lists.remove(&id) {
+                Some(list) => {
+                    graph.vectors.insert(id, vector.normalised());
+                    graph.knn.insert(id, list);
+                }
+                None => pending.push((id, vector)),
+            }
+        }
+        for list in graph.knn.values_mut() {
+            list.retain(|n| graph.vectors.contains_key(&n.id));
+            sort(list);
+            list.truncate(graph.k);
+        }
+        let mut changed = BTreeSet::new();
+        for (id, vector) in pending {
+            changed.extend(graph.insert(id, vector));
+        }
+        (graph, changed)
+    }
+
+    /// Whether `w` is in the nearest-neighbour list of `v`.
+    fn is_listed(&self, v: i64, w: i64) -> bool {
+        self.knn
+            .get(&v)
+            .is_some_and(|list| list.iter().any(|n| n.id == w))
+    }
+
+    /// Nearest-neighbour list of `id` computed from scratch.
+    fn nearest(&self, id: i64) -> Vec<Neighbour> {
+        let Some(embedding) = self.vectors.get(&id) else {
+            return Vec::new();
+        };
+        let mut list = self
+            .vectors
+            .iter()
+            .filter(|&(&other, _)| other != id)
+            .map(|(&other, vector)| Neighbour {
+                id: other,
+                similarity: embedding.dot(vector),
+            })
+            .filter(|n| n.similarity >= self.min_similarity)
+            .collect::<Vec<_>>();
+        sort(&mut list);
+        list.truncate(self.k);
+        list
+    }
+}
+
+/// Put `candidate` into `list` if it is among the `k` best, returning whether it was.
+fn offer(list: &mut Vec<Neighbour>, k: usize, candidate: Neighbour) -> bool {
+    if list.len() >= k && list.last().is_some_and(|worst| !better(&candidate, worst)) {
+        return false;
+    }
+    let at = list.partition_point(|n| better(n, &candidate));
+    list.insert(at, candidate);
+    list.truncate(k);
+    true
+}
+
+/// Order by similarity, breaking ties by id so that lists do not depend on insertion order.
+fn better(a: &Neighbour, b: &Neighbour) -> bool {
+    a.similarity > b.similarity || (a.similarity == b.similarity && a.id < b.id)
+}
+
+fn sort(list: &mut [Neighbour]) {
+    list.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    const K: usize = 4;
+
+    fn random_vector(rng: &mut StdRng) -> Embedding {
+        Embedding::new((0..8).map(|_| rng.random_range(-1.0..1.0)).collect())
+    }
+
+    /// Nearest-neighbour lists of every vertex, computed from scratch.
+    fn brute_force(vectors: &HashMap<i64, Embedding>) -> HashMap<i64, Vec<Neighbour>> {
+        vectors
+            .iter()
+            .map(|(&v, vector)| {
+                let vector = vector.clone().normalised();
+                let mut list = vectors
+                    .iter()
+                    .filter(|&(&w, _)| w != v)
+                    .map(|(&w, other)| Neighbour {
+                        id: w,
+                        similarity: vector.dot(&other.clone().normalised()),
+                    })
+                    .filter(|n| n.similarity >= 0.0)
+                    .collect::<Vec<_>>();
+                sort(&mut list);
+                list.truncate(K);
+                (v, list)
+            })
+            .collect()
+    }
+
+    fn edges(graph: &KnnGraph) -> BTreeSet<(i64, i64)> {
+        graph.edges().map(|(v, w, _)| (v, w)).collect()
+    }
+
+    fn assert_matches(graph: &KnnGraph, vectors: &HashMap<i64, Embedding>) {
+        let expected = brute_force(vectors);
+        assert_eq!(graph.len(), expected.len());
+        for (v, list) in &expected {
+            assert_eq!(graph.nearest_neighbours(*v), list.as_slice(), "list of {v}");
+        }
+        let mutual = expected
+            .iter()
+            .flat_map(|(&v, list)| list.iter().map(move |n| (v, n.id)))
+            .filter(|&(v, w)| v < w && expected[&w].iter().any(|n| n.id == v))
+            .collect::<BTreeSet<_>>();
+        assert_eq!(edges(graph), mutual);
+        for (v, w) in mutual {
+            assert!(graph.neighbours(v).iter().any(|n| n.id == w));
+            assert!(graph.neighbours(w).iter().any(|n| n.id == v));
+        }
+    }
+
+    #[test]
+    fn inserting_one_at_a_time_matches_a_rebuild() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut graph = KnnGraph::new(K, 0.0);
+        let mut vectors = HashMap::new();
+        for id in 0..80 {
+            let vector = random_vector(&mut rng);
+            vectors.insert(id, vector.clone());
+            let changed = graph.insert(id, vector);
+            assert!(changed.contains(&id));
+            if id % 10 == 0 {
+                assert_matches(&graph, &vectors);
+            }
+        }
+        assert_matches(&graph, &vectors);
+
+        // Replacing a vertex moves it.
+        for id in [3, 40, 79] {
+            let vector = random_vector(&mut rng);
+            vectors.insert(id, vector.clone());
+            graph.insert(id, vector);
+        }
+        assert_matches(&graph, &vectors);
+    }
+
+    #[test]
+    fn removing_matches_a_rebuild() {
+        let mut rng = StdRng::seed_from_u64(12);
+        let mut graph = KnnGraph::new(K, 0.0);
+        let mut vectors = HashMap::new();
+        for id in 0..60 {
+            let vector = random_vector(&mut rng);
+            vectors.insert(id, vector.clone());
+            graph.insert(id, vector);
+        }
+        for id in [0, 7, 31, 59, 12, 44] {
+            let listed_by = graph
+                .ids()
+                .filter(|&v| graph.is_listed(v, id))
+                .collect::<BTreeSet<_>>();
+            vectors.remove(&id);
+            assert_eq!(graph.remove(id), listed_by);
+            assert!(!graph.contains(id));
+            assert_matches(&graph, &vectors);
+        }
+        assert!(graph.remove(1000).is_empty());
+    }
+
+    #[test]
+    fn restoring_stored_lists_round_trips() {
+        let mut rng = StdRng::seed_from_u64(13);
+        let mut graph = KnnGraph::new(K, 0.0);
+        let mut vectors = HashMap::new();
+        for id in 0..40 {
+            let vector = random_vector(&mut rng);
+            vectors.insert(id, vector.clone());
+            graph.insert(id, vector);
+        }
+        let lists = graph
+            .ids()
+            .map(|id| (id, graph.nearest_neighbours(id).to_vec()))
+            .collect::<HashMap<_, _>>();
+
+        let (restored, changed) = KnnGraph::restore(K, 0.0, vectors.clone(), lists.clone());
+        assert!(changed.is_empty());
+        assert_matches(&restored, &vectors);
+        assert_eq!(edges(&restored), edges(&graph));
+
+        // Vertices stored since the lists were are inserted afresh, and lists of vertices gone
+        // since lose them.
+        let mut lists = lists;
+        lists.remove(&5);
+        lists.insert(1000, Vec::new());
+        for id in 40..45 {
+            vectors.insert(id, random_vector(&mut rng));
+        }
+        let (restored, changed) = KnnGraph::restore(K, 0.0, vectors.clone(), lists);
+        assert!((40..45).chain([5]).all(|id| changed.contains(&id)));
+        assert!(!restored.contains(1000));
+        assert_matches(&restored, &vectors);
+    }
+}
@@ -0,0 +1,192 @@
+//! Similarity graph over entry embeddings.
+
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
+use tracing::info;
+
+use crate::{
+    embedding::Embedding,
+    storage::{GraphStore, Storage, StorageError},
+};
+
+pub use crate::graph::{
+    knn::{KnnGraph, Neighbour},
+    ppr::{power_iteration, FastPpr, TransitionGraph},
+};
+
+mod knn;
+mod ppr;
+
+/// The mutual kNN graph of one embedding model, kept in memory and mirrored to storage as it
+/// changes. Cheap to clone.
+#[derive(Clone, Debug)]
+pub struct SimilarityGraph {
+    model_id: String,
+    graph: Arc<RwLock<KnnGraph>>,
+    store: GraphStore,
+}
+
+impl SimilarityGraph {
+    /// Load the graph of `model_id`, adding any stored embedding it is missing. A graph stored
+    /// with a different k is rebuilt from scratch.
+    pub async fn load(storage: &Storage, model_id: &str, k: usize) -> Result<Self, StorageError> {
+        let store = storage.graph();
+        let lists = if store.k(model_id).await? == Some(k) {
+            store.all(model_id).await?
+        } else {
+            store.reset(model_id, k).await?;
+            Default::default()
+        };
+        let vectors = storage.embeddings().all(model_id).await?;
+        let (graph, changed) = KnnGraph::restore(k, 0.0, vectors, lists);
+
+        let this = Self {
+            model_id: model_id.to_string(),
+            graph: Arc::new(RwLock::new(graph)),
+            store,
+        };
+        {
+            let graph = this.graph.read().await;
+            this.persist(&graph, changed).await?;
+            info!(
+                model = model_id,
+                k,
+                vertices = graph.len(),
+                "similarity graph loaded"
+            );
+        }
+        Ok(this)
+    }
+
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    /// Add or replace entry `id`, storing the lists that changed.
+    pub async fn insert(&self, id: i64, embedding: Embedding) -> Result<(), StorageError> {
+        // Holding the write lock while storing keeps concurrent inserts from storing stale lists.
+        let mut graph = self.graph.write().await;
+        let changed = graph.insert(id, embedding);
+        self.persist(&graph, changed).await
+    }
+
+    /// Entries adjacent to `id`, most similar first.
+    pub async fn neighbours(&self, id: i64) -> Vec<Neighbour> {
+        self.graph.read().await.neighbours(id)
+    }
+
+    /// Read access to the whole graph.
+    pub async fn read(&self) -> tokio::sync::RwLockReadGuard<'_, KnnGraph> {
+        self.graph.read().await
+    }
+
+    async fn persist(
+        &self,
+        graph: &KnnGraph,
+        changed: impl IntoIterator<Item = i64>,
+    ) -> Result<(), StorageError> {
+        let lists = changed
+            .into_iter()
+            .map(|id| (id, graph.nearest_neighbours(id).to_vec()))
+            .collect::<Vec<_>>();
+        if lists.is_empty() {
+            return Ok(());
+        }
+        self.store.put(&self.model_id, &lists).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use url::Url;
+
+    use super::*;
+    use crate::entry::Entry;
+
+    const MODEL: &str = "test-model";
+
+    async fn edges(graph: &SimilarityGraph) -> BTreeSet<(i64, i64)> {
+        graph.read().await.edges().map(|(v, w, _)| (v, w)).collect()
+    }
+
+    /// Store `count` entries, returning their ids.
+    async fn entries(storage: &Storage, count: usize) -> Vec<i64> {
+        let mut ids = Vec::new();
+        for i in 0..count {
+            let url = Url::parse(&format!("https://example.com/{i}")).unwrap();
+            let entry = Entry::new(
+                format!("Entry {i}"),
+                "example.com".to_string(),
+                String::new(),
+                url,
+                String::new(),
+                None,
+            );
+            ids.push(storage.entries().upsert(&entry, None).await.unwrap().id());
+        }
+        ids
+    }
+
+    #[tokio::test]
+    async fn loading_restores_the_stored_graph() {
+        let storage = Storage::connect("sqlite::memory:").await.unwrap();
+        let ids = entries(&storage, 30).await;
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut vector = || Embedding::new((0..8).map(|_| rng.random_range(-1.0..1.0)).collect());
+
+        let graph = SimilarityGraph::load(&storage, MODEL, 4).await.unwrap();
+        for &id in &ids[..25] {
+            let embedding = vector();
+            storage
+                .embeddings()
+                .put(id, MODEL, &embedding)
+                .await
+                .unwrap();
+            graph.insert(id, embedding).await.unwrap();
+        }
+        let loaded = SimilarityGraph::load(&storage, MODEL, 4).await.unwrap();
+        assert_eq!(loaded.read().await.len(), 25);
+        assert_eq!(edges(&loaded).await, edges(&graph).await);
+        for &id in &ids[..25] {
+            assert_eq!(loaded.neighbours(id).await, graph.neighbours(id).await);
+        }
+
+        // Embeddings stored while the graph was not running are added on load.
+        let mut expected = KnnGraph::new(4, 0.0);
+        for (id, embedding) in storage.embeddings().all(MODEL).await.unwrap() {
+            expected.insert(id, embedding);
+        }
+        for &id in &ids[25..] {
+            let embedding = vector();
+            storage
+                .embeddings()
+                .put(id, MODEL, &embedding)
+                .await
+                .unwrap();
+            expected.insert(id, embedding);
+        }
+        let loaded = SimilarityGraph::load(&storage, MODEL, 4).await.unwrap();
+        let expected_edges = expected
+            .edges()
+            .map(|(v, w, _)| (v, w))
+            .collect::<BTreeSet<_>>();
+        assert_eq!(loaded.read().await.len(), 30);
+        assert_eq!(edges(&loaded).await, expected_edges);
+        assert_eq!(
+            edges(&SimilarityGraph::load(&storage, MODEL, 4).await.unwrap()).await,
+            expected_edges
+        );
+
+        // A different k rebuilds it.
+        let rebuilt = SimilarityGraph::load(&storage, MODEL, 2).await.unwrap();
+        let rebuilt = rebuilt.read().await;
+        assert_eq!(rebuilt.len(), 30);
+        assert!(ids
+            .iter()
+            .all(|&id| rebuilt.nearest_neighbours(id).len() <= 2));
+    }
+}
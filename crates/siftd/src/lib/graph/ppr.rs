@@ -0,0 +1,457 @@
+use std::collections::{HashMap, VecDeque};
+
+use rand::Rng;
+
+use crate::graph::KnnGraph;
+
+/// Steps after which a random walk is stopped wherever it is. Walks are that long with
+/// probability `(1 - teleport)^MAX_WALK_LEN`, negligible unless the teleport is tiny.
+const MAX_WALK_LEN: usize = 10_000;
+
+/// Pushes after which a reverse push stops, leaving more residual for the walks to sample.
+const MAX_PUSHES: usize = 1_000_000;
+
+/// Rounds after which power iteration stops, converged or not.
+const MAX_ROUNDS: usize = 10_000;
+
+/// Snapshot of a weighted graph as the transition probabilities of a random walk on it, so that
+/// PageRank can be computed without holding on to the graph. Vertices without edges loop onto
+/// themselves, so that every walk has somewhere to go.
+#[derive(Debug, Clone)]
+pub struct TransitionGraph {
+    ids: Vec<i64>,
+    index: HashMap<i64, usize>,
+    /// Successors of each vertex and the probability of moving to them.
+    out: Vec<Vec<(usize, f64)>>,
+    /// Predecessors of each vertex and the probability of moving from them to it.
+    inc: Vec<Vec<(usize, f64)>>,
+}
+
+impl TransitionGraph {
+    /// Graph of undirected, weighted `edges` between `vertices`. Edges without a positive weight
+    /// are ignored; vertices only named by edges are added.
+    pub fn from_edges(
+        vertices: impl IntoIterator<Item = i64>,
+        edges: impl IntoIterator<Item = (i64, i64, f32)>,
+    ) -> Self {
+        let mut ids = Vec::new();
+        let mut index = HashMap::new();
+        let mut intern = |id: i64, ids: &mut Vec<i64>| {
+            *index.entry(id).or_insert_with(|| {
+                ids.push(id);
+                ids.len() - 1
+            })
+        };
+        for v in vertices {
+            intern(v, &mut ids);
+        }
+        let mut weights = Vec::new();
+        for (v, w, weight) in edges {
+            let (v, w) = (intern(v, &mut ids), intern(w, &mut ids));
+            if weight > 0.0 && v != w {
+                weights.push((v, w, weight as f64));
+            }
+        }
+
+        let mut out = vec![Vec::new(); ids.len()];
+        for (v, w, weight) in weights {
+            out[v].push((w, weight));
+            out[w].push((v, weight));
+        }
+        for (v, successors) in out.iter_mut().enumerate() {
+            let degree = successors.iter().map(|(_, w)| w).sum::<f64>();
+            if successors.is_empty() {
+                successors.push((v, 1.0));
+            } else {
+                successors.iter_mut().for_each(|(_, w)| *w /= degree);
+            }
+        }
+        let mut inc = vec![Vec::new(); ids.len()];
+        for (v, successors) in out.iter().enumerate() {
+            for &(w, p) in successors {
+                inc[w].push((v, p));
+            }
+        }
+
+        Self {
+            ids,
+            index,
+            out,
+            inc,
+        }
+    }
+
+    /// The mutual kNN graph, weighted by similarity.
+    pub fn from_knn(graph: &KnnGraph) -> Self {
+        Self::from_edges(graph.ids(), graph.edges())
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn contains(&self, id: i64) -> bool {
+        self.index.contains_key(&id)
+    }
+
+    /// Average number of successors per vertex.
+    pub fn average_degree(&self) -> f64 {
+        if self.ids.is_empty() {
+            return 0.0;
+        }
+        self.out.iter().map(Vec::len).sum::<usize>() as f64 / self.ids.len() as f64
+    }
+
+    /// Known vertices of `sources` with their weights scaled to sum to 1. Vertices without a
+    /// positive weight are dropped.
+    fn distribution(&self, sources: &[(i64, f64)]) -> Vec<(usize, f64)> {
+        let known = sources
+            .iter()
+            .filter(|(_, w)| *w > 0.0)
+            .filter_map(|(id, w)| Some((*self.index.get(id)?, *w)))
+            .collect::<Vec<_>>();
+        let total = known.iter().map(|(_, w)| w).sum::<f64>();
+        known.into_iter().map(|(v, w)| (v, w / total)).collect()
+    }
+}
+
+/// Personalised PageRank estimation after Fast-PPR (Lofgren et al., KDD 2014), in its
+/// bidirectional form: a reverse local push from the target leaves estimates and residuals such
+/// that the PageRank of the target equals the estimate at the source plus the residuals averaged
+/// over where random walks from the source end, which forward walks then sample.
+///
+/// The PageRank of `t` personalised to `s` is the probability that a walk from `s`, stopping
+/// with probability `teleport` before each step, ends at `t`.
+#[derive(Debug, Clone, Copy)]
+pub struct FastPpr {
+    /// Probability of a walk stopping before each step, in (0, 1].
+    pub teleport: f64,
+    /// Smallest PageRank worth estimating accurately; anything smaller may come out as noise.
+    pub delta: f64,
+    /// Relative error allowed on PageRanks of at least `delta`.
+    pub relative_error: f64,
+    /// Probability of an estimate exceeding `relative_error`.
+    pub failure_probability: f64,
+}
+
+impl FastPpr {
+    pub const DEFAULT_TELEPORT: f64 = 0.15;
+    pub const DEFAULT_DELTA: f64 = 1e-3;
+    pub const DEFAULT_RELATIVE_ERROR: f64 = 0.1;
+    pub const DEFAULT_FAILURE_PROBABILITY: f64 = 0.01;
+
+    /// Estimated PageRank of `target` personalised to `sources`, a distribution over vertices
+    /// given as weights.
+    pub fn estimate(
+        &self,
+        graph: &TransitionGraph,
+        sources: &[(i64, f64)],
+        target: i64,
+        rng: &mut impl Rng,
+    ) -> f64 {
+        self.estimate_many(graph, sources, &[target], rng)
+            .get(&target)
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Estimated PageRank of each of `targets` personalised to `sources`. The forward walks are
+    /// shared between targets, so this is cheaper than estimating them one by one. Targets not in
+    /// the graph are left out.
+    ///
+    /// # Panics
+    ///
+    /// If the teleport probability is not in (0, 1].
+    pub fn estimate_many(
+        &self,
+        graph: &TransitionGraph,
+        sources: &[(i64, f64)],
+        targets: &[i64],
+        rng: &mut impl Rng,
+    ) -> HashMap<i64, f64> {
+        assert_teleport(self.teleport);
+        let sources = graph.distribution(sources);
+        if sources.is_empty() {
+            return HashMap::new();
+        }
+        let r_max = self.residual_threshold(graph);
+        let walks = self.walks(r_max);
+        let endpoints = (0..walks)
+            .map(|_| self.walk(graph, sample(&sources, rng), rng))
+            .collect::<Vec<_>>();
+
+        targets
+            .iter()
+            .filter_map(|&target| {
+                let t = *graph.index.get(&target)?;
+                let (estimates, residuals) = self.reverse_push(graph, t, r_max);
+                let exact = sources
+                    .iter()
+                    .map(|(s, w)| w * estimates.get(s).copied().unwrap_or(0.0))
+                    .sum::<f64>();
+                let sampled = endpoints
+                    .iter()
+                    .map(|v| residuals.get(v).copied().unwrap_or(0.0))
+                    .sum::<f64>()
+                    / walks as f64;
+                Some((target, exact + sampled))
+            })
+            .collect()
+    }
+
+    /// Residual left at each vertex by the reverse push, balancing its cost against the walks':
+    /// the lower it is, the more the push does and the fewer walks are needed.
+    pub fn residual_threshold(&self, graph: &TransitionGraph) -> f64 {
+        (self.delta * graph.average_degree().max(1.0) / self.walk_constant())
+            .sqrt()
+            .min(1.0)
+    }
+
+    /// Number of walks for the sampled residuals, each at most `r_max`, to be within the
+    /// relative error with the configured probability.
+    pub fn walks(&self, r_max: f64) -> usize {
+        (self.walk_constant() * r_max / self.delta).ceil().max(1.0) as usize
+    }
+
+    fn walk_constant(&self) -> f64 {
+        3.0 * (2.0 / self.failure_probability).ln() / self.relative_error.powi(2)
+    }
+
+    /// Push residual mass backwards from `target` until no vertex holds more than `r_max`, or
+    /// for at most [`MAX_PUSHES`] pushes. Afterwards, for every source `s`, the PageRank of
+    /// `target` personalised to `s` is the estimate at `s` plus the residual at each vertex
+    /// weighted by its PageRank personalised to `s`.
+    fn reverse_push(
+        &self,
+        graph: &TransitionGraph,
+        target: usize,
+        r_max: f64,
+    ) -> (HashMap<usize, f64>, HashMap<usize, f64>) {
+        let mut estimates = HashMap::<usize, f64>::new();
+        let mut residuals = HashMap::from([(target, 1.0)]);
+        let mut queue = VecDeque::from([target]);
+        let mut pushes = 0;
+        while let Some(v) = queue.pop_front() {
+            let residual = residuals.get(&v).copied().unwrap_or(0.0);
+            if residual <= r_max {
+                continue;
+            }
+            if pushes == MAX_PUSHES {
+                break;
+            }
+            pushes += 1;
+            residuals.insert(v, 0.0);
+            *estimates.entry(v).or_default() += self.teleport * residual;
+            for &(u, p) in &graph.inc[v] {
+                let r = residuals.entry(u).or_default();
+                let before = *r;
+                *r += (1.0 - self.teleport) * p * residual;
+                if before <= r_max && *r > r_max {
+                    queue.push_back(u);
+                }
+            }
+        }
+        (estimates, residuals)
+    }
+
+    /// Where a random walk from `source` ends, cut short after [`MAX_WALK_LEN`] steps.
+    fn walk(&self, graph: &TransitionGraph, source: usize, rng: &mut impl Rng) -> usize {
+        let mut v = source;
+        for _ in 0..MAX_WALK_LEN {
+            if rng.random_bool(self.teleport) {
+                break;
+            }
+            v = sample(&graph.out[v], rng);
+        }
+        v
+    }
+}
+
+impl Default for FastPpr {
+    fn default() -> Self {
+        Self {
+            teleport: Self::DEFAULT_TELEPORT,
+            delta: Self::DEFAULT_DELTA,
+            relative_error: Self::DEFAULT_RELATIVE_ERROR,
+            failure_probability: Self::DEFAULT_FAILURE_PROBABILITY,
+        }
+    }
+}
+
+/// Exact PageRank of every vertex personalised to `sources`, by power iteration until the
+/// vector moves by less than `tolerance` in L1 distance, or for at most [`MAX_ROUNDS`] rounds.
+/// Quadratic at best, this is the reference the estimates are checked against.
+///
+/// # Panics
+///
+/// If `teleport` is not in (0, 1].
+pub fn power_iteration(
+    graph: &TransitionGraph,
+    sources: &[(i64, f64)],
+    teleport: f64,
+    tolerance: f64,
+) -> HashMap<i64, f64> {
+    assert_teleport(teleport);
+    let sources = graph.distribution(sources);
+    let mut restart = vec![0.0; graph.len()];
+    for &(s, w) in &sources {
+        restart[s] += teleport * w;
+    }
+    let mut rank = restart.clone();
+    for _ in 0..MAX_ROUNDS {
+        let mut next = restart.clone();
+        for (v, successors) in graph.out.iter().enumerate() {
+            for &(w, p) in successors {
+                next[w] += (1.0 - teleport) * p * rank[v];
+            }
+        }
+        let moved = next
+            .iter()
+            .zip(&rank)
+            .map(|(a, b)| (a - b).abs())
+            .sum::<f64>();
+        rank = next;
+        if moved < tolerance {
+            break;
+        }
+    }
+    graph.ids.iter().copied().zip(rank).collect()
+}
+
+fn assert_teleport(teleport: f64) {
+    assert!(
+        teleport > 0.0 && teleport <= 1.0,
+        "teleport probability {teleport} is not in (0, 1]"
+    );
+}
+
+/// Index drawn from `(index, probability)` pairs whose probabilities sum to 1.
+fn sample(choices: &[(usize, f64)], rng: &mut impl Rng) -> usize {
+    let mut x = rng.random::<f64>();
+    for &(v, p) in choices {
+        if x < p {
+            return v;
+        }
+        x -= p;
+    }
+    choices.last().map(|(v, _)| *v).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    /// Two weighted triangles joined by a weak bridge, a pendant vertex and an isolated one.
+    fn graph() -> TransitionGraph {
+        TransitionGraph::from_edges(
+            [7],
+            [
+                (1, 2, 0.9),
+                (2, 3, 0.8),
+                (1, 3, 0.7),
+                (3, 4, 0.1),
+                (4, 5, 0.9),
+                (5, 6, 0.6),
+                (4, 6, 0.8),
+                (6, 8, 0.5),
+            ],
+        )
+    }
+
+    #[test]
+    fn power_iteration_is_a_distribution() {
+        let graph = graph();
+        let rank = power_iteration(&graph, &[(1, 1.0)], 0.15, 1e-12);
+        assert!((rank.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert_eq!(rank[&7], 0.0);
+
+        let isolated = power_iteration(&graph, &[(7, 1.0)], 0.15, 1e-12);
+        assert!((isolated[&7] - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reverse_push_keeps_its_invariant() {
+        let graph = graph();
+        let ppr = FastPpr::default();
+        for target in 0..graph.len() {
+            let (estimates, residuals) = ppr.reverse_push(&graph, target, 0.05);
+            let target = graph.ids[target];
+            for &source in &graph.ids {
+                let rank = power_iteration(&graph, &[(source, 1.0)], ppr.teleport, 1e-12);
+                let s = graph.index[&source];
+                let reconstructed = estimates.get(&s).copied().unwrap_or(0.0)
+                    + residuals
+                        .iter()
+                        .map(|(v, r)| rank[&graph.ids[*v]] * r)
+                        .sum::<f64>();
+                assert!(
+                    (reconstructed - rank[&target]).abs() < 1e-9,
+                    "source {source}, target {target}: {reconstructed} != {}",
+                    rank[&target]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn estimates_match_power_iteration() {
+        let graph = graph();
+        let ppr = FastPpr {
+            delta: 0.01,
+            ..FastPpr::default()
+        };
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let sources = [(1, 2.0), (5, 1.0)];
+        let exact = power_iteration(&graph, &sources, ppr.teleport, 1e-12);
+        let targets = graph.ids.clone();
+        let estimates = ppr.estimate_many(&graph, &sources, &targets, &mut rng);
+        for target in targets {
+            let (estimate, exact) = (estimates[&target], exact[&target]);
+            let allowed = ppr.relative_error * exact.max(ppr.delta);
+            assert!(
+                (estimate - exact).abs() <= allowed,
+                "target {target}: estimated {estimate}, exact {exact}"
+            );
+        }
+    }
+
+    #[test]
+    fn tiny_teleports_terminate() {
+        let graph = graph();
+        let ppr = FastPpr {
+            teleport: f64::MIN_POSITIVE,
+            delta: 0.1,
+            ..FastPpr::default()
+        };
+        let mut rng = StdRng::seed_from_u64(2);
+        ppr.estimate(&graph, &[(1, 1.0)], 2, &mut rng);
+        power_iteration(&graph, &[(1, 1.0)], f64::MIN_POSITIVE, 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "not in (0, 1]")]
+    fn zero_teleport_is_rejected() {
+        let ppr = FastPpr {
+            teleport: 0.0,
+            ..FastPpr::default()
+        };
+        ppr.estimate(&graph(), &[(1, 1.0)], 2, &mut StdRng::seed_from_u64(3));
+    }
+
+    #[test]
+    fn unknown_vertices_are_ignored() {
+        let graph = graph();
+        let ppr = FastPpr::default();
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(ppr.estimate(&graph, &[(42, 1.0)], 1, &mut rng), 0.0);
+        assert!(ppr
+            .estimate_many(&graph, &[(1, 1.0)], &[42], &mut rng)
+            .is_empty());
+    }
+}
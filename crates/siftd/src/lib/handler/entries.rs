@@ -0,0 +1,89 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    graph::Neighbour,
+    handler::{ApiError, AppState},
+    storage::{EntryFilter, Page, StoredEntry},
+};
+
+pub async fn get_entry(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<StoredEntry>, ApiError> {
+    debug!(entry_id = id, "get entry");
+    let entry = state
+        .storage()
+        .entries()
+        .get(id)
+        .await?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("no entry with id {id}")))?;
+    Ok(Json(entry))
+}
+
+/// `GET /entries/{id}/neighbours`: entries adjacent to this one in the similarity graph, most
+/// similar first.
+pub async fn get_neighbours(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<Neighbour>>, ApiError> {
+    debug!(entry_id = id, "get neighbours");
+    let graph = state.graph().read().await;
+    if !graph.contains(id) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("entry {id} is not in the similarity graph"),
+        )
+            .into());
+    }
+    Ok(Json(graph.neighbours(id)))
+}
+
+pub async fn list_entries(
+    State(state): State<AppState>,
+    Query(query): Query<ListEntries>,
+) -> Result<Json<Vec<StoredEntry>>, ApiError> {
+    debug!(?query, "list entries");
+    let page = Page::new(query.limit.unwrap_or(Page::DEFAULT_LIMIT), query.offset);
+    let filter = EntryFilter {
+        include_duplicates: query.include_duplicates,
+        duplicate_of: query.duplicate_of,
+        origin: query.origin,
+        author: query.author,
+        published_after: query.published_after,
+        published_before: query.published_before,
+        unread: query.unread,
+        bookmarked: query.bookmarked,
+        liked_since: query.liked_since,
+        disliked_since: query.disliked_since,
+    };
+    let entries = state.storage().entries().list(&filter, page).await?;
+    Ok(Json(entries))
+}
+
+/// Query string of `GET /entries`.
+#[derive(Deserialize, Debug)]
+pub struct ListEntries {
+    limit: Option<u32>,
+    #[serde(default)]
+    offset: u32,
+    #[serde(default)]
+    include_duplicates: bool,
+    duplicate_of: Option<i64>,
+    origin: Option<String>,
+    author: Option<String>,
+    published_after: Option<DateTime<Utc>>,
+    published_before: Option<DateTime<Utc>>,
+    #[serde(default)]
+    unread: bool,
+    #[serde(default)]
+    bookmarked: bool,
+    liked_since: Option<DateTime<Utc>>,
+    disliked_since: Option<DateTime<Utc>>,
+}
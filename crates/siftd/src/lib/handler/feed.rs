@@ -0,0 +1,280 @@
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::{
+    content::{Content, ErrorClass, Unfetched},
+    fetcher::{FeedRefresh, Fetchable, FetcherError, Priority},
+    handler::{ApiError, AppState},
+    storage::StoredEntry,
+};
+
+/// `POST /feed`: fetch a feed, store it, and fetch and store its items. Items are fetched by the
+/// background fetcher, ahead of anything it has queued, within its concurrency limits.
+pub async fn handle_feed(
+    State(state): State<AppState>,
+    Json(payload): Json<HandleFeed>,
+) -> Result<(StatusCode, Json<FeedResponse>), ApiError> {
+    let url = payload.url;
+    let (url_host, url_path) = crate::url_host_and_path(&url);
+    info!(%url_host, %url_path, "process feed");
+    let content = Content::<Unfetched>::new(url, None);
+    let ignore_robots = match payload.ignore_robots {
+        Some(ignore_robots) => ignore_robots,
+        None => state
+            .storage()
+            .feeds()
+            .find_by_url(content.url())
+            .await?
+            .is_some_and(|f| f.ignore_robots),
+    };
+    let url = content.url().clone();
+    let fetched = content
+        .with_ignore_robots(ignore_robots)
+        .fetch(state.http())
+        .await;
+    state.track_fetch(&url, &fetched).await;
+    let fetched = fetched?;
+    let validators = fetched.validators();
+    let feed = fetched.parse_feed()?;
+
+    let feed_id = state.storage().feeds().upsert(&feed).await?;
+    if let Err(e) = state
+        .storage()
+        .feeds()
+        .set_validators(feed_id, &validators)
+        .await
+    {
+        warn!(feed_id, error = %e, "storing feed validators failed");
+    }
+    if let Err(e) = state
+        .storage()
+        .feeds()
+        .set_refresh_hints(feed_id, feed.refresh_hints())
+        .await
+    {
+        warn!(feed_id, error = %e, "storing feed refresh hints failed");
+    }
+    if let Some(ignore_robots) = payload.ignore_robots {
+        state
+            .storage()
+            .feeds()
+            .set_ignore_robots(feed_id, ignore_robots)
+            .await?;
+    }
+
+    let title = feed.title().to_string();
+    let url = feed.url().clone();
+    let site_link = feed.site_link().cloned();
+    let hints = *feed.refresh_hints();
+    let items = feed.into_items();
+    info!(items = items.len(), "feed parsed");
+
+    let post_times = items
+        .iter()
+        .filter_map(|item| item.metadata().published_time())
+        .collect::<Vec<_>>();
+
+    let pending = items
+        .into_iter()
+        .map(|item| {
+            let url = item.link().clone();
+            let (done, result) = oneshot::channel();
+            let queued = QueuedItem {
+                link: url.clone(),
+                content: item.into_content().with_ignore_robots(ignore_robots),
+                feed_id,
+                done,
+            };
+            let queued = state
+                .fetch_queue()
+                .push(Box::new(queued), Priority::INTERACTIVE);
+            (url, queued.then_some(result))
+        })
+        .collect::<Vec<_>>();
+    let mut items = Vec::with_capacity(pending.len());
+    for (url, result) in pending {
+        items.push(match result {
+            Some(result) => result.await.unwrap_or_else(|_| ItemResult::FetchError {
+                url,
+                class: ErrorClass::Other,
+                error: "fetch abandoned".to_string(),
+            }),
+            None => ItemResult::Queued { url },
+        });
+    }
+
+    let failed = items
+        .iter()
+        .filter(|i| !matches!(i, ItemResult::Entry { .. } | ItemResult::Queued { .. }))
+        .count();
+    info!(items = items.len(), failed, "feed ingested");
+
+    if let Err(e) = state
+        .scheduler()
+        .observe(state.storage(), feed_id, &hints, &post_times)
+        .await
+    {
+        warn!(feed_id, error = %e, "scheduling feed failed");
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(FeedResponse {
+            url,
+            title,
+            site_link,
+            items,
+        }),
+    ))
+}
+
+/// `POST /feeds/{id}/refresh`: queue a refresh of a stored feed for the background fetcher.
+pub async fn refresh_feed(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    debug!(feed_id = id, "refresh feed");
+    let feed = state
+        .storage()
+        .feeds()
+        .get(id)
+        .await?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("no feed with id {id}")))?;
+    state.fetch_queue().push(
+        Box::new(FeedRefresh::new(feed.url().clone())),
+        Priority::INTERACTIVE,
+    );
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// A feed item fetched through the queue for `POST /feed`, which waits on its result.
+struct QueuedItem {
+    /// Link of the item in the feed, which results are reported under.
+    link: Url,
+    content: Content<Unfetched>,
+    feed_id: i64,
+    done: oneshot::Sender<ItemResult>,
+}
+
+#[async_trait]
+impl Fetchable for QueuedItem {
+    fn url(&self) -> &Url {
+        self.content.url()
+    }
+
+    async fn ingest(
+        self: Box<Self>,
+        state: &AppState,
+    ) -> Result<Vec<Box<dyn Fetchable>>, FetcherError> {
+        let result = ingest_item(state, self.feed_id, self.link, self.content).await;
+        // The request may have gone away, in which case nobody is waiting for the result.
+        let _ = self.done.send(result);
+        Ok(Vec::new())
+    }
+}
+
+async fn ingest_item(
+    state: &AppState,
+    feed_id: i64,
+    url: Url,
+    content: Content<Unfetched>,
+) -> ItemResult {
+    let fetch_url = content.url().clone();
+    let fetched = content.fetch(state.http()).await;
+    state.track_fetch(&fetch_url, &fetched).await;
+    let fetched = match fetched {
+        Ok(fetched) => fetched,
+        Err(e) => {
+            warn!(%url, error = %e, "feed item fetch failed");
+            return ItemResult::FetchError {
+                url,
+                class: e.class(),
+                error: e.to_string(),
+            };
+        }
+    };
+    let entry = match fetched
+        .parse_pages(state.http(), state.site_rules(), state.max_article_pages())
+        .await
+    {
+        Ok(entry) => entry,
+        Err(e) => {
+            warn!(%url, error = %e, "feed item parse failed");
+            return ItemResult::ParseError {
+                url,
+                error: e.to_string(),
+            };
+        }
+    };
+    match state
+        .storage()
+        .entries()
+        .upsert(&entry, Some(feed_id))
+        .await
+    {
+        Ok(stored) => {
+            state.spawn_embedding(&stored);
+            ItemResult::Entry {
+                entry: Box::new(stored),
+            }
+        }
+        Err(e) => {
+            warn!(%url, error = %e, "feed item store failed");
+            ItemResult::StorageError {
+                url,
+                error: e.to_string(),
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct HandleFeed {
+    url: Url,
+    /// Fetch the feed and its items regardless of `robots.txt`. Left as stored when absent.
+    #[serde(default)]
+    ignore_robots: Option<bool>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FeedResponse {
+    url: Url,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    site_link: Option<Url>,
+    items: Vec<ItemResult>,
+}
+
+/// Outcome of ingesting a single feed item. One bad item does not fail the whole feed.
+#[derive(Serialize, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ItemResult {
+    Entry {
+        entry: Box<StoredEntry>,
+    },
+    FetchError {
+        url: Url,
+        class: ErrorClass,
+        error: String,
+    },
+    ParseError {
+        url: Url,
+        error: String,
+    },
+    StorageError {
+        url: Url,
+        error: String,
+    },
+    /// The item was already queued or being fetched, and is stored once that fetch is done.
+    Queued {
+        url: Url,
+    },
+}
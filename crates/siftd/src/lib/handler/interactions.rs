@@ -0,0 +1,59 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use tracing::debug;
+
+use crate::{
+    handler::{ApiError, AppState},
+    interaction::{Interaction, InteractionEvent, InteractionState},
+};
+
+/// `POST /entries/{id}/{read,bookmark,like,dislike}`.
+pub async fn record_interaction(
+    State(state): State<AppState>,
+    Path((id, interaction)): Path<(i64, Interaction)>,
+) -> Result<Json<InteractionState>, ApiError> {
+    record(&state, id, interaction, true).await
+}
+
+/// `DELETE /entries/{id}/{read,bookmark,like,dislike}`, undoing the corresponding `POST`.
+pub async fn undo_interaction(
+    State(state): State<AppState>,
+    Path((id, interaction)): Path<(i64, Interaction)>,
+) -> Result<Json<InteractionState>, ApiError> {
+    record(&state, id, interaction, false).await
+}
+
+/// `GET /entries/{id}/interactions`: every interaction with the entry, oldest first.
+pub async fn list_interactions(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<InteractionEvent>>, ApiError> {
+    debug!(entry_id = id, "list interactions");
+    let history = state.storage().interactions().history(id).await?;
+    Ok(Json(history))
+}
+
+async fn record(
+    state: &AppState,
+    id: i64,
+    interaction: Interaction,
+    active: bool,
+) -> Result<Json<InteractionState>, ApiError> {
+    debug!(
+        entry_id = id,
+        interaction = interaction.as_str(),
+        active,
+        "record interaction"
+    );
+    let recorded = state
+        .storage()
+        .interactions()
+        .record(id, interaction, active, Utc::now())
+        .await?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("no entry with id {id}")))?;
+    Ok(Json(recorded))
+}
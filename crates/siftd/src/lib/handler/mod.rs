@@ -1 +1,248 @@
+use std::sync::Arc;
+
+use ::url::Url;
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{
+    content::{ContentError, ErrorClass},
+    embedding::{self, Embedder},
+    fetcher::FetchQueue,
+    graph::SimilarityGraph,
+    http::HttpClient,
+    parser::rules::SiteRules,
+    recommender::Recommender,
+    scheduler::Scheduler,
+    storage::{Storage, StorageError, StoredEntry},
+};
+
+pub mod entries;
+pub mod feed;
+pub mod interactions;
+pub mod recommendations;
 pub mod url;
+
+/// State shared by all handlers.
+#[derive(Clone, Debug)]
+pub struct AppState {
+    storage: Storage,
+    embedder: Arc<dyn Embedder>,
+    graph: SimilarityGraph,
+    recommender: Recommender,
+    fetch_queue: FetchQueue,
+    scheduler: Scheduler,
+    http: HttpClient,
+    site_rules: Arc<SiteRules>,
+    max_article_pages: usize,
+}
+
+impl AppState {
+    /// Pages of a multi-page article fetched and stitched together, the first one included.
+    pub const DEFAULT_MAX_ARTICLE_PAGES: usize = 10;
+
+    pub fn new(storage: Storage, embedder: Arc<dyn Embedder>, graph: SimilarityGraph) -> Self {
+        Self {
+            storage,
+            embedder,
+            graph,
+            recommender: Recommender::default(),
+            fetch_queue: FetchQueue::default(),
+            scheduler: Scheduler::default(),
+            http: HttpClient::default(),
+            site_rules: Arc::default(),
+            max_article_pages: Self::DEFAULT_MAX_ARTICLE_PAGES,
+        }
+    }
+
+    /// Fetch content with `http` rather than a client with the default configuration.
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Parse pages following `site_rules` where they apply, rather than by heuristics alone.
+    pub fn with_site_rules(mut self, site_rules: SiteRules) -> Self {
+        self.site_rules = Arc::new(site_rules);
+        self
+    }
+
+    /// Follow multi-page articles for up to `max_article_pages` pages; 1 follows none.
+    pub fn with_max_article_pages(mut self, max_article_pages: usize) -> Self {
+        self.max_article_pages = max_article_pages;
+        self
+    }
+
+    pub fn with_scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    /// Queue background fetches on `fetch_queue`, which a `Fetcher` should be draining.
+    pub fn with_fetch_queue(mut self, fetch_queue: FetchQueue) -> Self {
+        self.fetch_queue = fetch_queue;
+        self
+    }
+
+    pub fn with_recommender(mut self, recommender: Recommender) -> Self {
+        self.recommender = recommender;
+        self
+    }
+
+    pub fn storage(&self) -> &Storage {
+        &self.storage
+    }
+
+    pub fn embedder(&self) -> &Arc<dyn Embedder> {
+        &self.embedder
+    }
+
+    pub fn graph(&self) -> &SimilarityGraph {
+        &self.graph
+    }
+
+    pub fn recommender(&self) -> &Recommender {
+        &self.recommender
+    }
+
+    pub fn fetch_queue(&self) -> &FetchQueue {
+        &self.fetch_queue
+    }
+
+    pub fn scheduler(&self) -> &Scheduler {
+        &self.scheduler
+    }
+
+    pub fn http(&self) -> &HttpClient {
+        &self.http
+    }
+
+    pub fn site_rules(&self) -> &SiteRules {
+        &self.site_rules
+    }
+
+    pub fn max_article_pages(&self) -> usize {
+        self.max_article_pages
+    }
+
+    /// Keep the record of URLs that failed for good up to date with a fetch of `url`. Transient
+    /// failures are not recorded.
+    pub(crate) async fn track_fetch<T>(&self, url: &Url, result: &Result<T, ContentError>) {
+        let failures = self.storage.failures();
+        let tracked = match result {
+            Ok(_) => failures.clear(url).await,
+            Err(e) if !e.is_transient() => failures.record(e).await,
+            Err(_) => Ok(()),
+        };
+        if let Err(e) = tracked {
+            warn!(%url, error = %e, "tracking fetch failure failed");
+        }
+    }
+
+    /// Embed a freshly stored entry and add it to the similarity graph in the background, so
+    /// that requests do not wait on it.
+    pub(crate) fn spawn_embedding(&self, stored: &StoredEntry) {
+        let embedder = self.embedder.clone();
+        let store = self.storage.embeddings();
+        let graph = self.graph.clone();
+        let id = stored.id();
+        let text = stored.entry().text().to_string();
+        tokio::spawn(async move {
+            match embedding::embed_entry(embedder.as_ref(), &store, id, &text).await {
+                Ok(embedding) => {
+                    if let Err(e) = graph.insert(id, embedding).await {
+                        warn!(entry_id = id, error = %e, "adding entry to similarity graph failed");
+                    }
+                }
+                Err(e) => warn!(entry_id = id, error = %e, "embedding entry failed"),
+            }
+        });
+    }
+}
+
+/// Error response of the HTTP API, sent as a JSON object.
+#[derive(Debug, Serialize)]
+pub struct ApiError {
+    #[serde(skip)]
+    status: StatusCode,
+    error: String,
+    /// Kind of failure, for content that could not be fetched or parsed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    class: Option<ErrorClass>,
+    /// Status the remote server answered with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upstream_status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_after_secs: Option<u64>,
+}
+
+impl From<(StatusCode, String)> for ApiError {
+    fn from((status, error): (StatusCode, String)) -> Self {
+        Self {
+            status,
+            error,
+            class: None,
+            upstream_status: None,
+            url: None,
+            retry_after_secs: None,
+        }
+    }
+}
+
+/// A site refusing us or sending garbage is not a gateway failure.
+impl From<ContentError> for ApiError {
+    fn from(e: ContentError) -> Self {
+        let class = e.class();
+        let status = match class {
+            ErrorClass::Disallowed => StatusCode::FORBIDDEN,
+            ErrorClass::RateLimited => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorClass::Parse => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorClass::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::BAD_GATEWAY,
+        };
+        let retry_after_secs = match &e {
+            ContentError::RateLimited { retry_after, .. } => Some(retry_after.as_secs()),
+            _ => None,
+        };
+        Self {
+            status,
+            error: e.to_string(),
+            class: Some(class),
+            upstream_status: e.status().map(|s| s.as_u16()),
+            url: Some(e.url().to_string()),
+            retry_after_secs,
+        }
+    }
+}
+
+impl From<StorageError> for ApiError {
+    fn from(e: StorageError) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            error: format!("storage error: {e}"),
+            class: Some(ErrorClass::Storage),
+            upstream_status: None,
+            url: None,
+            retry_after_secs: None,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let retry_after = self.retry_after_secs;
+        let mut response = (self.status, Json(self)).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, secs.into());
+        }
+        response
+    }
+}
@@ -0,0 +1,38 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    handler::{ApiError, AppState},
+    recommender::Recommendation,
+    storage::Page,
+};
+
+pub async fn get_recommendations(
+    State(state): State<AppState>,
+    Query(query): Query<GetRecommendations>,
+) -> Result<Json<Vec<Recommendation>>, ApiError> {
+    debug!(?query, "get recommendations");
+    let limit = query
+        .limit
+        .unwrap_or(GetRecommendations::DEFAULT_LIMIT)
+        .min(Page::MAX_LIMIT);
+    let recommendations = state
+        .recommender()
+        .recommend(state.storage(), state.graph(), limit as usize)
+        .await?;
+    Ok(Json(recommendations))
+}
+
+/// Query string of `GET /recommendations`.
+#[derive(Deserialize, Debug)]
+pub struct GetRecommendations {
+    limit: Option<u32>,
+}
+
+impl GetRecommendations {
+    const DEFAULT_LIMIT: u32 = 20;
+}
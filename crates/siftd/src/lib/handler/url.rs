@@ -1,25 +1,41 @@
-use axum::{http::StatusCode, Json};
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    Json,
+};
 use serde::Deserialize;
 use tracing::info;
 use url::Url;
 
 use crate::{
     content::{Content, Unfetched},
-    entry::Entry,
+    handler::{ApiError, AppState},
+    storage::StoredEntry,
 };
 
-pub async fn handle_url(Json(payload): Json<HandleUrl>) -> Result<(StatusCode, Json<Entry>), (StatusCode, String)> {
+pub async fn handle_url(
+    State(state): State<AppState>,
+    Json(payload): Json<HandleUrl>,
+) -> Result<(StatusCode, HeaderMap, Json<StoredEntry>), ApiError> {
     let url = payload.url;
     let (url_host, url_path) = crate::url_host_and_path(&url);
     info!(%url_host, %url_path, "process url");
     let content = Content::<Unfetched>::new(url, None);
-    let entry = content
-        .fetch()
-        .await
-        .map_err(|e| (StatusCode::BAD_GATEWAY, format!("fetch error: {e}")))?
-        .parse()
-        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, format!("parse error: {e}")))?;
-    Ok((StatusCode::CREATED, Json(entry)))
+    let url = content.url().clone();
+    let fetched = content.fetch(state.http()).await;
+    state.track_fetch(&url, &fetched).await;
+    let entry = fetched?
+        .parse_pages(state.http(), state.site_rules(), state.max_article_pages())
+        .await?;
+    let stored = state.storage().entries().upsert(&entry, None).await?;
+    info!(entry_id = stored.id(), "entry stored");
+    state.spawn_embedding(&stored);
+
+    let mut headers = HeaderMap::new();
+    if let Ok(location) = HeaderValue::from_str(&format!("/entries/{}", stored.id())) {
+        headers.insert(header::LOCATION, location);
+    }
+    Ok((StatusCode::CREATED, headers, Json(stored)))
 }
 
 #[derive(Deserialize, Debug)]
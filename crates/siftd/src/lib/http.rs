@@ -0,0 +1,156 @@
+//! The HTTP client content is fetched with.
+
+use std::{sync::Arc, time::Duration};
+
+use bytes::{Bytes, BytesMut};
+use reqwest::{header::HeaderMap, redirect, Client, Proxy, Response};
+use url::Url;
+
+use crate::{ratelimit::HostLimiter, robots::RobotsCache};
+
+/// How to make requests. Built into an [`HttpClient`] once, at startup.
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    pub connect_timeout: Duration,
+    /// Longest wait for the next bytes of a response.
+    pub read_timeout: Duration,
+    /// Longest a whole request, body included, may take.
+    pub timeout: Duration,
+    /// Redirects followed before giving up; 0 follows none.
+    pub max_redirects: usize,
+    /// HTTP, HTTPS or SOCKS5 proxy for every request.
+    pub proxy: Option<Url>,
+    pub user_agent: String,
+    /// Sent with every request.
+    pub headers: HeaderMap,
+    /// Responses longer than this are cut off and refused.
+    pub max_body_bytes: usize,
+}
+
+impl HttpConfig {
+    pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+    pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(30);
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+    pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+    pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.10 Safari/605.1.1";
+    pub const DEFAULT_MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+    pub fn build(&self) -> Result<HttpClient, reqwest::Error> {
+        let redirect = match self.max_redirects {
+            0 => redirect::Policy::none(),
+            n => redirect::Policy::limited(n),
+        };
+        let mut builder = Client::builder()
+            .user_agent(&self.user_agent)
+            .default_headers(self.headers.clone())
+            .connect_timeout(self.connect_timeout)
+            .read_timeout(self.read_timeout)
+            .timeout(self.timeout)
+            .redirect(redirect);
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy.as_str())?);
+        }
+        Ok(HttpClient {
+            client: builder.build()?,
+            max_body_bytes: self.max_body_bytes,
+            robots: Arc::default(),
+            limiter: Arc::default(),
+        })
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Self::DEFAULT_CONNECT_TIMEOUT,
+            read_timeout: Self::DEFAULT_READ_TIMEOUT,
+            timeout: Self::DEFAULT_TIMEOUT,
+            max_redirects: Self::DEFAULT_MAX_REDIRECTS,
+            proxy: None,
+            user_agent: Self::DEFAULT_USER_AGENT.to_string(),
+            headers: HeaderMap::new(),
+            max_body_bytes: Self::DEFAULT_MAX_BODY_BYTES,
+        }
+    }
+}
+
+/// Client built from an [`HttpConfig`], with the `robots.txt` cache and per-host limits that
+/// fetches through it obey. Cheap to clone; clones share both.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: Client,
+    max_body_bytes: usize,
+    robots: Arc<RobotsCache>,
+    limiter: Arc<HostLimiter>,
+}
+
+impl HttpClient {
+    /// Consult `robots` rather than a default cache before fetching.
+    pub fn with_robots(mut self, robots: RobotsCache) -> Self {
+        self.robots = Arc::new(robots);
+        self
+    }
+
+    /// Limit requests to each host by `limiter` rather than the default limits.
+    pub fn with_limiter(mut self, limiter: HostLimiter) -> Self {
+        self.limiter = Arc::new(limiter);
+        self
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn max_body_bytes(&self) -> usize {
+        self.max_body_bytes
+    }
+
+    pub fn robots(&self) -> &RobotsCache {
+        &self.robots
+    }
+
+    pub fn limiter(&self) -> &HostLimiter {
+        &self.limiter
+    }
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        HttpConfig::default()
+            .build()
+            .expect("the default HTTP configuration is valid")
+    }
+}
+
+/// A response body, read up to a limit.
+pub(crate) struct Body {
+    pub bytes: Bytes,
+    /// Whether the body went on beyond the limit.
+    pub truncated: bool,
+}
+
+/// Read the body of `response`, stopping once it exceeds `limit` bytes: only the first `limit`
+/// are kept, and the rest is not read. Content-Length may be missing or lie, so it only sizes the
+/// buffer.
+pub(crate) async fn read_body(
+    response: &mut Response,
+    limit: usize,
+) -> Result<Body, reqwest::Error> {
+    let length = response.content_length().unwrap_or(0).min(limit as u64);
+    let mut bytes = BytesMut::with_capacity(length as usize);
+    while let Some(chunk) = response.chunk().await? {
+        let room = limit - bytes.len();
+        if chunk.len() > room {
+            bytes.extend_from_slice(&chunk[..room]);
+            return Ok(Body {
+                bytes: bytes.freeze(),
+                truncated: true,
+            });
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Body {
+        bytes: bytes.freeze(),
+        truncated: false,
+    })
+}
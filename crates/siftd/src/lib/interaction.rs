@@ -0,0 +1,102 @@
+//! Bookkeeping of what the user did with an entry: reading, bookmarking, liking and disliking.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Something the user can do to an entry, and undo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interaction {
+    Read,
+    Bookmark,
+    Like,
+    Dislike,
+}
+
+impl Interaction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Interaction::Read => "read",
+            Interaction::Bookmark => "bookmark",
+            Interaction::Like => "like",
+            Interaction::Dislike => "dislike",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "read" => Some(Interaction::Read),
+            "bookmark" => Some(Interaction::Bookmark),
+            "like" => Some(Interaction::Like),
+            "dislike" => Some(Interaction::Dislike),
+            _ => None,
+        }
+    }
+
+    /// The interaction that doing this one cancels, _i.e._ liking an entry un-dislikes it.
+    pub fn opposite(&self) -> Option<Self> {
+        match self {
+            Interaction::Like => Some(Interaction::Dislike),
+            Interaction::Dislike => Some(Interaction::Like),
+            Interaction::Read | Interaction::Bookmark => None,
+        }
+    }
+}
+
+/// When an entry was read, bookmarked, liked or disliked, if it currently is.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct InteractionState {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    read_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bookmarked_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    liked_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disliked_at: Option<DateTime<Utc>>,
+}
+
+impl InteractionState {
+    pub fn new(
+        read_at: Option<DateTime<Utc>>,
+        bookmarked_at: Option<DateTime<Utc>>,
+        liked_at: Option<DateTime<Utc>>,
+        disliked_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            read_at,
+            bookmarked_at,
+            liked_at,
+            disliked_at,
+        }
+    }
+
+    pub fn read_at(&self) -> Option<DateTime<Utc>> {
+        self.read_at
+    }
+
+    pub fn bookmarked_at(&self) -> Option<DateTime<Utc>> {
+        self.bookmarked_at
+    }
+
+    pub fn liked_at(&self) -> Option<DateTime<Utc>> {
+        self.liked_at
+    }
+
+    pub fn disliked_at(&self) -> Option<DateTime<Utc>> {
+        self.disliked_at
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// One entry of the interaction log.
+#[derive(Debug, Clone, Serialize)]
+pub struct InteractionEvent {
+    pub interaction: Interaction,
+    /// `false` if this event undid the interaction.
+    pub active: bool,
+    pub occurred_at: DateTime<Utc>,
+}
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use url::Url;
 
 type Time = DateTime<Utc>;
-#[derive(Default, Debug, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Metadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     thumbnail_url: Option<Url>,
@@ -32,4 +32,30 @@ impl Metadata {
             thumbnail_url,
         }
     }
+
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_deref()
+    }
+
+    pub fn published_time(&self) -> Option<Time> {
+        self.published_time
+    }
+
+    pub fn updated_time(&self) -> Option<Time> {
+        self.updated_time
+    }
+
+    pub fn thumbnail_url(&self) -> Option<&Url> {
+        self.thumbnail_url.as_ref()
+    }
+
+    /// Fill any missing field from `fallback`, keeping the fields already present.
+    pub fn or(self, fallback: Metadata) -> Self {
+        Self {
+            summary: self.summary.or(fallback.summary),
+            published_time: self.published_time.or(fallback.published_time),
+            updated_time: self.updated_time.or(fallback.updated_time),
+            thumbnail_url: self.thumbnail_url.or(fallback.thumbnail_url),
+        }
+    }
 }
@@ -1,15 +1,22 @@
-use once_cell::sync::Lazy;
-use reqwest::Client;
-
+pub mod canonical;
 pub mod content;
+pub mod dedup;
+pub mod embedding;
 pub mod entry;
+pub mod feed;
+pub mod fetcher;
+pub mod graph;
 pub mod handler;
+pub mod http;
+pub mod interaction;
 pub mod metadata;
 pub mod parser;
-
-const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.10 Safari/605.1.1";
-pub static HTTP_CLIENT: Lazy<Client> =
-    Lazy::new(|| Client::builder().user_agent(USER_AGENT).build().unwrap());
+pub mod ratelimit;
+pub mod recommender;
+pub mod retry;
+pub mod robots;
+pub mod scheduler;
+pub mod storage;
 
 /// Return host and path of a URL, with query/fragment stripped.
 pub fn url_host_and_path(u: &url::Url) -> (String, String) {
@@ -0,0 +1,284 @@
+//! Working out the character encoding of a document and decoding it.
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252, X_USER_DEFINED};
+use mime::Mime;
+use url::Url;
+
+/// Where the encoding of a document came from, in order of precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodingSource {
+    /// `charset` parameter of the `Content-Type` header.
+    ContentType,
+    /// Byte order mark.
+    Bom,
+    /// `<meta charset>` or `<meta http-equiv="Content-Type">`.
+    Meta,
+    /// Guessed from the bytes.
+    Detected,
+}
+
+/// A document decoded to UTF-8.
+#[derive(Debug, Clone)]
+pub struct Decoded {
+    pub text: String,
+    pub encoding: &'static Encoding,
+    pub source: EncodingSource,
+    /// Whether malformed sequences were replaced with U+FFFD.
+    pub had_errors: bool,
+}
+
+/// How far into a document `<meta>` declarations are looked for.
+const META_PRESCAN_BYTES: usize = 4096;
+
+/// Decode `bytes`, served with `content_type` from `url`, whose top-level domain hints at the
+/// likely encoding if it has to be guessed.
+pub fn decode(bytes: &[u8], content_type: Option<&Mime>, url: &Url) -> Decoded {
+    let (sniffed, source) = sniff(bytes, content_type, url);
+    // Decoding strips a BOM matching the encoding, and lets one that does not match win, as
+    // browsers do.
+    let (text, encoding, had_errors) = sniffed.decode(bytes);
+    let source = if encoding == sniffed {
+        source
+    } else {
+        EncodingSource::Bom
+    };
+    Decoded {
+        text: text.into_owned(),
+        encoding,
+        source,
+        had_errors,
+    }
+}
+
+fn sniff(
+    bytes: &[u8],
+    content_type: Option<&Mime>,
+    url: &Url,
+) -> (&'static Encoding, EncodingSource) {
+    let declared = content_type
+        .and_then(|m| m.get_param(mime::CHARSET))
+        .and_then(|cs| Encoding::for_label(cs.as_str().trim().as_bytes()));
+    if let Some(encoding) = declared {
+        return (encoding, EncodingSource::ContentType);
+    }
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return (encoding, EncodingSource::Bom);
+    }
+    if let Some(encoding) = meta_charset(&bytes[..bytes.len().min(META_PRESCAN_BYTES)]) {
+        return (encoding, EncodingSource::Meta);
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    let tld = url
+        .host_str()
+        .and_then(|h| h.rsplit('.').next())
+        .map(str::as_bytes);
+    (detector.guess(tld, true), EncodingSource::Detected)
+}
+
+/// Encoding declared by the first `<meta>` tag in `head` that declares one.
+fn meta_charset(head: &[u8]) -> Option<&'static Encoding> {
+    let lower = head.to_ascii_lowercase();
+    let mut rest = lower.as_slice();
+    while let Some(start) = find(rest, b"<meta") {
+        let tag = &rest[start + 5..];
+        let end = tag.iter().position(|b| *b == b'>').unwrap_or(tag.len());
+        let tag = &tag[..end];
+        rest = &rest[start + 5 + end..];
+
+        // Covers both `charset="x"` and `content="text/html; charset=x"`.
+        let Some(at) = find(tag, b"charset") else {
+            continue;
+        };
+        let value = tag[at + 7..].trim_ascii_start();
+        let Some(value) = value.strip_prefix(b"=") else {
+            continue;
+        };
+        let value = value.trim_ascii_start();
+        let value = value
+            .strip_prefix(b"\"")
+            .or_else(|| value.strip_prefix(b"'"))
+            .unwrap_or(value);
+        let len = value
+            .iter()
+            .position(|b| matches!(b, b'"' | b'\'' | b';' | b'/') || b.is_ascii_whitespace())
+            .unwrap_or(value.len());
+        let Some(encoding) = Encoding::for_label(&value[..len]) else {
+            continue;
+        };
+        // A document that could be read far enough to find this is not UTF-16.
+        return Some(if encoding == UTF_16LE || encoding == UTF_16BE {
+            UTF_8
+        } else if encoding == X_USER_DEFINED {
+            WINDOWS_1252
+        } else {
+            encoding
+        });
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::{KOI8_R, SHIFT_JIS};
+
+    use super::*;
+
+    fn decoded(bytes: &[u8], content_type: Option<&str>, url: &str) -> Decoded {
+        let content_type = content_type.map(|ct| ct.parse::<Mime>().unwrap());
+        decode(bytes, content_type.as_ref(), &Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn declarations_take_precedence_in_order() {
+        const BOM: &[u8] = b"\xEF\xBB\xBF";
+        const META_1252: &[u8] = b"<meta charset=\"windows-1252\">";
+        const CAFE_UTF_8: &[u8] = b"caf\xC3\xA9";
+        let url = "https://example.com/";
+        let cases = [
+            (
+                [META_1252, CAFE_UTF_8].concat(),
+                Some("text/html; charset=utf-8"),
+                UTF_8,
+                EncodingSource::ContentType,
+                "café",
+            ),
+            // A BOM overrides even the header, as in browsers.
+            (
+                [BOM, CAFE_UTF_8].concat(),
+                Some("text/html; charset=windows-1252"),
+                UTF_8,
+                EncodingSource::Bom,
+                "café",
+            ),
+            (
+                [BOM, META_1252, CAFE_UTF_8].concat(),
+                None,
+                UTF_8,
+                EncodingSource::Bom,
+                "café",
+            ),
+            (
+                [META_1252, CAFE_UTF_8].concat(),
+                Some("text/html"),
+                WINDOWS_1252,
+                EncodingSource::Meta,
+                "cafÃ©",
+            ),
+            // An unknown label is ignored.
+            (
+                [META_1252, b"caf\xE9"].concat(),
+                Some("text/html; charset=no-such-encoding"),
+                WINDOWS_1252,
+                EncodingSource::Meta,
+                "café",
+            ),
+            (
+                [
+                    b"<p>",
+                    CAFE_UTF_8,
+                    b" au lait, cr\xC3\xA8me br\xC3\xBBl\xC3\xA9e</p>",
+                ]
+                .concat(),
+                None,
+                UTF_8,
+                EncodingSource::Detected,
+                "café",
+            ),
+        ];
+        for (bytes, content_type, encoding, source, text) in cases {
+            let decoded = decoded(&bytes, content_type, url);
+            assert_eq!(
+                (decoded.encoding, decoded.source),
+                (encoding, source),
+                "{content_type:?} {bytes:x?}"
+            );
+            assert!(decoded.text.contains(text), "{:?}", decoded.text);
+            assert!(!decoded.text.starts_with('\u{feff}'));
+        }
+    }
+
+    #[test]
+    fn meta_declarations_are_found_in_either_form() {
+        let cases: [(&[u8], &Encoding); 5] = [
+            (b"<META CHARSET='KOI8-R'>", KOI8_R),
+            (
+                b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=shift_jis\">",
+                SHIFT_JIS,
+            ),
+            (
+                b"<meta name=\"x\"><meta charset = windows-1252 />",
+                WINDOWS_1252,
+            ),
+            // Neither could have been read this far if they were right.
+            (b"<meta charset=\"utf-16le\">", UTF_8),
+            (b"<meta charset=\"x-user-defined\">", WINDOWS_1252),
+        ];
+        for (head, encoding) in cases {
+            assert_eq!(
+                meta_charset(head),
+                Some(encoding),
+                "{}",
+                String::from_utf8_lossy(head)
+            );
+        }
+        assert_eq!(
+            meta_charset(b"<meta name=\"charset\" content=\"none\">"),
+            None
+        );
+    }
+
+    #[test]
+    fn legacy_encodings_decode() {
+        let cases: [(&[u8], &str, &str); 3] = [
+            (b"\x93caf\xE9\x94 \x805", "windows-1252", "“café” €5"),
+            (b"\x93\xFA\x96{\x8C\xEA, \xB1", "shift_jis", "日本語, ｱ"),
+            (
+                b"\xF0\xD2\xC9\xD7\xC5\xD4, \xED\xC9\xD2",
+                "koi8-r",
+                "Привет, Мир",
+            ),
+        ];
+        for (bytes, charset, text) in cases {
+            let content_type = format!("text/html; charset={charset}");
+            let decoded = decoded(bytes, Some(&content_type), "https://example.com/");
+            assert_eq!(decoded.text, text, "{charset}");
+            assert!(!decoded.had_errors, "{charset}");
+        }
+    }
+
+    #[test]
+    fn undeclared_legacy_encodings_are_detected() {
+        let cases = [
+            (
+                SHIFT_JIS,
+                "https://example.jp/",
+                "日本語の文章です。今日はいい天気ですね。",
+            ),
+            (
+                KOI8_R,
+                "https://example.ru/",
+                "Привет, мир! Это простой текст на русском.",
+            ),
+            (
+                WINDOWS_1252,
+                "https://example.fr/",
+                "Le café était très bon, et la crème brûlée aussi.",
+            ),
+        ];
+        for (encoding, url, text) in cases {
+            let (bytes, _, unmappable) = encoding.encode(text);
+            assert!(!unmappable);
+            let decoded = decoded(&bytes, None, url);
+            // Detection may settle on a superset, such as KOI8-U for KOI8-R, which decodes the
+            // same text.
+            assert_eq!(decoded.source, EncodingSource::Detected, "{url}");
+            assert_eq!(decoded.text, text, "{url} as {}", decoded.encoding.name());
+        }
+    }
+}
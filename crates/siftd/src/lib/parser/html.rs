@@ -1,14 +1,18 @@
 use bytes::Bytes;
 use mime::Mime;
 use serde_json::Value;
-use tracing::debug;
+use tracing::{debug, info};
 use url::Url;
 use webpage::HTML;
 
 use crate::{
-    entry::Entry,
+    canonical,
+    entry::{cap_len, Entry},
     metadata::Metadata,
-    parser::{Parser, ParserError, ParserFamily},
+    parser::{
+        charset, markdown, pagination, readability, rules::SiteRule, Parser, ParserError,
+        ParserFamily,
+    },
 };
 
 pub struct HtmlParser {
@@ -45,25 +49,34 @@ impl<'a> Parser<'a> for HtmlParser {
         }
     }
 
-    fn parse(&self) -> Result<crate::entry::Entry, ParserError> {
+    fn parse(&self, rule: Option<&SiteRule>) -> Result<crate::entry::Entry, ParserError> {
         let (url_host, url_path) = crate::url_host_and_path(&self.url);
         debug!(parser = "html", %url_host, %url_path, "parse");
 
-        // Decode to UTF-8 String. Prefer header charset if present; otherwise fall back to lossy UTF-8.
-        let decoded = match charset_from_mime(self.content_type.as_ref()) {
-            Some(cs) => decode_with_charset(&self.bytes, &cs),
-            None => String::from_utf8_lossy(&self.bytes).into_owned(),
-        };
+        let decoded = charset::decode(&self.bytes, self.content_type.as_ref(), &self.url);
+        info!(
+            encoding = decoded.encoding.name(),
+            source = ?decoded.source,
+            had_errors = decoded.had_errors,
+            "document decoded"
+        );
+        let decoded = decoded.text;
 
         debug!(decoded_len = decoded.len());
 
         let html = HTML::from_string(decoded.clone(), Some(self.url.to_string()))
             .map_err(|e| ParserError::WebpageParse(anyhow::Error::new(e)))?;
 
-        // Build a `scraper` DOM for heuristics.
-        let document = scraper::Html::parse_document(&decoded);
+        // Build a `scraper` DOM for heuristics, minus anything the site's rule strips.
+        let mut document = scraper::Html::parse_document(&decoded);
+        if let Some(rule) = rule {
+            debug!("site rule applies");
+            rule.strip(&mut document);
+        }
 
-        let title = pick_title(&html, &document);
+        let title = rule
+            .and_then(|r| r.title(&document))
+            .unwrap_or_else(|| pick_title(&html, &document));
         debug!(title_len = title.len(), preview = %truncate_for_log(&title), "title chosen");
 
         let mut summary = html
@@ -75,26 +88,50 @@ impl<'a> Parser<'a> for HtmlParser {
             .or(html.description.clone())
             .filter(|s| !s.trim().is_empty());
 
-        // Main content extraction with heuristics; fallback to webpage text_content
-        let content = extract_main_content(&document)
-            .filter(|s| !s.trim().is_empty())
-            .unwrap_or_else(|| html.text_content.clone());
+        // Main content from the site's rule, or by scoring, as Markdown and as plain text;
+        // fallback to webpage text_content
+        let main = rule
+            .and_then(|r| r.content(&document))
+            .unwrap_or_else(|| readability::extract(&document));
+        let text = readability::text(&main);
+        let (content, text) = if text.trim().is_empty() {
+            (html.text_content.clone(), html.text_content.clone())
+        } else {
+            let base = markdown::base_url(&document, &self.url);
+            (markdown::render(&main, &base), text)
+        };
 
         if summary.is_none() {
-            summary = content
+            summary = text
                 .split('\n')
                 .find(|p| !p.trim().is_empty())
                 .map(|s| s.trim().to_string());
         }
 
-        let author = pick_author(&html).unwrap_or_default();
+        let author = rule
+            .and_then(|r| r.author(&document))
+            .or_else(|| pick_author(&html))
+            .unwrap_or_default();
         let origin = pick_origin(&html, &self.url);
-        let (published_time, updated_time) = extract_times(&html);
+        let (mut published_time, updated_time) = extract_times(&html);
+        if let Some(published) = rule
+            .and_then(|r| r.date(&document))
+            .and_then(|d| parse_time(&d))
+        {
+            published_time = Some(published);
+        }
 
         let url = self.url.clone();
-        let content_capped = cap_len(content, 400_000);
+        let content_capped = cap_len(content, Entry::MAX_CONTENT_BYTES);
+        let text_capped = cap_len(text, Entry::MAX_CONTENT_BYTES);
+
+        let next_page = pagination::next_page(&document, &self.url, rule);
+        if let Some(next) = &next_page {
+            debug!(%next, "article continues on another page");
+        }
 
         let thumbnail_url = pick_thumbnail(&html, &document, &self.url);
+        let (canonical_url, aliases) = pick_canonical(&html, &document, &self.url);
 
         let metadata = Some(Metadata::new(
             summary,
@@ -103,14 +140,13 @@ impl<'a> Parser<'a> for HtmlParser {
             thumbnail_url,
         ));
 
-        Ok(Entry::new(
-            title,
-            origin,
-            author,
-            url,
-            content_capped,
-            metadata,
-        ))
+        Ok(
+            Entry::new(title, origin, author, url, content_capped, metadata)
+                .with_text(text_capped)
+                .with_next_page(next_page)
+                .with_canonical_url(canonical_url)
+                .with_aliases(aliases),
+        )
     }
 }
 
@@ -153,23 +189,6 @@ fn looks_like_html(bytes: &Bytes) -> bool {
         || hay.windows(5).any(|w| w == b"<html".as_ref())
 }
 
-fn charset_from_mime(mime: Option<&Mime>) -> Option<String> {
-    mime.and_then(|m| m.get_param(mime::CHARSET).map(|v| v.to_string()))
-}
-
-fn decode_with_charset(bytes: &Bytes, charset: &str) -> String {
-    // Basic decoding: prefer utf-8; otherwise fall back to lossily mapping bytes.
-    if charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("utf8") {
-        match std::str::from_utf8(bytes) {
-            Ok(s) => s.to_string(),
-            Err(_) => String::from_utf8_lossy(bytes).into_owned(),
-        }
-    } else {
-        // If not UTF-8, defer to lossy conversion to ensure robustness without panicking.
-        String::from_utf8_lossy(bytes).into_owned()
-    }
-}
-
 fn pick_title(html: &HTML, doc: &scraper::Html) -> String {
     html.opengraph
         .properties
@@ -189,45 +208,6 @@ fn first_text_selector(doc: &scraper::Html, selector: &str) -> Option<String> {
     Some(node.text().collect::<Vec<_>>().join(" ").trim().to_string())
 }
 
-fn extract_main_content(doc: &scraper::Html) -> Option<String> {
-    // Candidate containers
-    let candidates = [
-        "article",
-        "main",
-        "[role=main]",
-        "#content",
-        "#main",
-        ".post-content",
-        ".article-content",
-        ".article-body",
-        ".entry-content",
-        "[itemprop=articleBody]",
-    ];
-
-    for sel in candidates
-        .iter()
-        .filter_map(|s| scraper::Selector::parse(s).ok())
-    {
-        if let Some(container) = doc.select(&sel).next() {
-            let blocks =
-                scraper::Selector::parse("p, h1, h2, h3, h4, h5, h6, li, blockquote, pre").ok()?;
-            let mut parts = Vec::new();
-            for node in container.select(&blocks) {
-                let t = node.text().collect::<Vec<_>>().join(" ");
-                let t = t.trim();
-                if !t.is_empty() {
-                    parts.push(t.to_string());
-                }
-            }
-            let joined = parts.join("\n\n");
-            if !joined.trim().is_empty() {
-                return Some(joined);
-            }
-        }
-    }
-    None
-}
-
 fn pick_author(html: &HTML) -> Option<String> {
     let meta_candidate = html
         .meta
@@ -267,6 +247,54 @@ fn pick_author(html: &HTML) -> Option<String> {
     None
 }
 
+/// Pick the canonical URL from `<link rel="canonical">`, falling back to `og:url`. Whichever of
+/// the two is not picked is returned as an alias. Either is only trusted on the page's own site.
+fn pick_canonical(html: &HTML, doc: &scraper::Html, page_url: &Url) -> (Option<Url>, Vec<Url>) {
+    // `html.url` is the page URL unless overridden by a canonical link, so we cannot tell the two
+    // apart there and have to look at the DOM ourselves.
+    let link = scraper::Selector::parse(r#"link[rel~="canonical"]"#)
+        .ok()
+        .and_then(|sel| doc.select(&sel).find_map(|el| el.value().attr("href")))
+        .and_then(|href| absolutise(href, page_url));
+    let og = html
+        .opengraph
+        .properties
+        .get("url")
+        .and_then(|u| absolutise(u, page_url));
+
+    // Some sites point every page's canonical link at their home page; trusting that would merge
+    // every article into one.
+    // Nor can a page speak for another site: entries are identified by their canonical URL and
+    // aliases, so that would let it overwrite whatever that site's entry is.
+    let plausible = |u: &Url| {
+        matches!(u.scheme(), "http" | "https")
+            && (u.path() != "/" || page_url.path() == "/")
+            && same_site(u, page_url)
+    };
+    let mut candidates = [link, og].into_iter().flatten().filter(plausible);
+    let canonical = candidates.next();
+    let aliases = candidates
+        .filter(|u| Some(u) != canonical.as_ref())
+        .collect();
+    if canonical.is_none() {
+        debug!("No plausible canonical URL declared.");
+    }
+    (canonical, aliases)
+}
+
+/// Whether `a` and `b` are on the same host, give or take the `www.`, `m.` and `amp.` prefixes
+/// that [`canonical::canonicalise`] strips. Sibling subdomains are told apart: without the public
+/// suffix list, `blog.example.com` and `news.example.com` look no different from `a.github.io`
+/// and `b.github.io`.
+fn same_site(a: &Url, b: &Url) -> bool {
+    let host = |u: &Url| {
+        canonical::canonicalise(u)
+            .host_str()
+            .map(str::to_ascii_lowercase)
+    };
+    host(a).is_some() && host(a) == host(b)
+}
+
 fn pick_origin(html: &HTML, url: &Url) -> String {
     html.opengraph
         .properties
@@ -356,18 +384,6 @@ fn parse_time(s: &str) -> Option<chrono::DateTime<chrono::Utc>> {
     None
 }
 
-fn cap_len(s: String, max: usize) -> String {
-    if s.len() <= max {
-        s
-    } else {
-        let mut end = max;
-        while end > 0 && !s.is_char_boundary(end) {
-            end -= 1;
-        }
-        format!("{}…", &s[..end])
-    }
-}
-
 /// Best-effort thumbnail URL selection. Returns an absolute URL if any viable candidate is found.
 fn pick_thumbnail(html: &HTML, doc: &scraper::Html, page_url: &Url) -> Option<Url> {
     let mut og_candidates: Vec<(String, u64)> = html
@@ -510,3 +526,59 @@ fn image_from_schema(v: &serde_json::Value) -> Option<String> {
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(head: &str, page: &str) -> (Option<String>, Vec<String>) {
+        let page = Url::parse(page).unwrap();
+        let source = format!("<html><head>{head}</head><body><p>Text.</p></body></html>");
+        let html = HTML::from_string(source.clone(), Some(page.to_string())).unwrap();
+        let doc = scraper::Html::parse_document(&source);
+        let (canonical, aliases) = pick_canonical(&html, &doc, &page);
+        (
+            canonical.map(String::from),
+            aliases.into_iter().map(String::from).collect(),
+        )
+    }
+
+    #[test]
+    fn canonical_urls_are_only_trusted_on_the_same_site() {
+        let page = "https://www.example.com/news/story?utm_source=x";
+        let both = r#"<link rel="canonical" href="/news/story">
+            <meta property="og:url" content="https://m.example.com/story">"#;
+        assert_eq!(
+            canonical(both, page),
+            (
+                Some("https://www.example.com/news/story".to_string()),
+                vec!["https://m.example.com/story".to_string()]
+            )
+        );
+
+        // Neither a canonical link nor og:url on another site is kept, even as an alias.
+        let elsewhere = r#"<link rel="canonical" href="https://victim.example.net/news/story">
+            <meta property="og:url" content="https://www.example.com/news/story">"#;
+        assert_eq!(
+            canonical(elsewhere, page),
+            (
+                Some("https://www.example.com/news/story".to_string()),
+                vec![]
+            )
+        );
+        let og_elsewhere = r#"<meta property="og:url" content="https://evil.example.org/a">"#;
+        assert_eq!(canonical(og_elsewhere, page), (None, vec![]));
+        let sibling = r#"<link rel="canonical" href="https://blog.example.com/news/story">"#;
+        assert_eq!(canonical(sibling, page), (None, vec![]));
+    }
+
+    #[test]
+    fn home_page_canonicals_are_ignored_on_articles() {
+        let home = r#"<link rel="canonical" href="https://example.com/">"#;
+        assert_eq!(canonical(home, "https://example.com/a"), (None, vec![]));
+        assert_eq!(
+            canonical(home, "https://example.com/?page=1"),
+            (Some("https://example.com/".to_string()), vec![])
+        );
+    }
+}
@@ -0,0 +1,369 @@
+//! Rendering extracted content as CommonMark.
+//!
+//! Headings, paragraphs, emphasis, links, images, lists, block quotes and code are kept; links and
+//! image sources are made absolute, so that the Markdown stands on its own. Anything
+//! [`readability`] considers boilerplate is left out, as in the plain text rendering.
+
+use scraper::{node::Node, ElementRef, Html, Selector};
+use url::Url;
+
+use crate::parser::readability;
+
+/// CommonMark for `nodes`, with relative URLs resolved against `base`.
+pub fn render(nodes: &[ElementRef<'_>], base: &Url) -> String {
+    let mut blocks = Vec::new();
+    for node in nodes {
+        block(*node, base, &mut blocks);
+    }
+    blocks.join("\n\n")
+}
+
+/// URL relative links in `doc`, served from `url`, are resolved against: its `<base href>`, if
+/// it has one, or `url` itself.
+pub fn base_url(doc: &Html, url: &Url) -> Url {
+    let selector = Selector::parse("base[href]").expect("valid selector");
+    doc.select(&selector)
+        .next()
+        .and_then(|base| url.join(base.value().attr("href")?.trim()).ok())
+        .unwrap_or_else(|| url.clone())
+}
+
+fn block(element: ElementRef<'_>, base: &Url, out: &mut Vec<String>) {
+    if readability::is_boilerplate(element) || readability::is_link_list(element) {
+        return;
+    }
+    match element.value().name() {
+        name @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+            let mut text = String::new();
+            inline_children(element, base, &mut text);
+            let text = paragraph(&text.replace('\n', " "));
+            if !text.is_empty() {
+                let level = usize::from(name.as_bytes()[1] - b'0');
+                out.push(format!("{} {text}", "#".repeat(level)));
+            }
+        }
+        "pre" => {
+            let code = element.text().collect::<String>();
+            let code = code.trim_start_matches('\n').trim_end();
+            if !code.is_empty() {
+                out.push(fenced(code, language(element).as_deref()));
+            }
+        }
+        "hr" => out.push("---".to_string()),
+        "ul" | "ol" => {
+            if let Some(list) = list(element, base) {
+                out.push(list);
+            }
+        }
+        "blockquote" => {
+            let mut inner = Vec::new();
+            container(element, base, &mut inner);
+            if !inner.is_empty() {
+                out.push(prefix_lines(&inner.join("\n\n"), "> ", ">"));
+            }
+        }
+        _ => container(element, base, out),
+    }
+}
+
+/// Blocks of an element holding a mix of blocks and inline content, where every run of inline
+/// content between blocks is a paragraph.
+fn container(element: ElementRef<'_>, base: &Url, out: &mut Vec<String>) {
+    let mut inline = String::new();
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => push_text(&mut inline, text),
+            Node::Element(_) => {
+                let child = ElementRef::wrap(child).expect("element nodes wrap");
+                if readability::is_block(child) {
+                    flush(&mut inline, out);
+                    block(child, base, out);
+                } else {
+                    inline_element(child, base, &mut inline);
+                }
+            }
+            _ => {}
+        }
+    }
+    flush(&mut inline, out);
+}
+
+fn list(element: ElementRef<'_>, base: &Url) -> Option<String> {
+    let ordered = element.value().name() == "ol";
+    let mut number = element
+        .value()
+        .attr("start")
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .unwrap_or(1);
+
+    let mut items = Vec::new();
+    let mut loose = false;
+    for item in element.children().filter_map(ElementRef::wrap) {
+        if item.value().name() != "li"
+            || readability::is_boilerplate(item)
+            || readability::is_link_list(item)
+        {
+            continue;
+        }
+        let mut blocks = Vec::new();
+        container(item, base, &mut blocks);
+        if blocks.is_empty() {
+            continue;
+        }
+        // Items made of paragraphs need blank lines between them to stay apart.
+        loose |= item
+            .children()
+            .filter_map(ElementRef::wrap)
+            .any(|c| c.value().name() == "p");
+        let marker = if ordered {
+            number += 1;
+            format!("{}. ", number - 1)
+        } else {
+            "- ".to_string()
+        };
+        items.push((marker, blocks));
+    }
+    if items.is_empty() {
+        return None;
+    }
+    let separator = if loose { "\n\n" } else { "\n" };
+    let items = items
+        .into_iter()
+        .map(|(marker, blocks)| {
+            let body = prefix_lines(&blocks.join(separator), &" ".repeat(marker.len()), "");
+            format!("{marker}{}", &body[marker.len()..])
+        })
+        .collect::<Vec<_>>();
+    Some(items.join(separator))
+}
+
+fn inline_element(element: ElementRef<'_>, base: &Url, out: &mut String) {
+    if readability::is_boilerplate(element) {
+        return;
+    }
+    match element.value().name() {
+        "br" => out.push('\n'),
+        "img" => {
+            if let Some(image) = image(element, base) {
+                out.push_str(&image);
+            }
+        }
+        "a" => {
+            let mut text = String::new();
+            inline_children(element, base, &mut text);
+            match element.value().attr("href").and_then(|h| link(h, base)) {
+                Some(href) if !text.trim().is_empty() => {
+                    wrap(out, &text, "[", &format!("]({})", destination(&href)));
+                }
+                _ => out.push_str(&text),
+            }
+        }
+        "strong" | "b" => {
+            let mut text = String::new();
+            inline_children(element, base, &mut text);
+            wrap(out, &text, "**", "**");
+        }
+        "em" | "i" => {
+            let mut text = String::new();
+            inline_children(element, base, &mut text);
+            wrap(out, &text, "*", "*");
+        }
+        "code" | "kbd" | "samp" | "tt" => {
+            let code = element.text().collect::<String>();
+            let code = code.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !code.is_empty() {
+                out.push_str(&code_span(&code));
+            }
+        }
+        _ => inline_children(element, base, out),
+    }
+}
+
+fn inline_children(element: ElementRef<'_>, base: &Url, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => push_text(out, text),
+            Node::Element(_) => {
+                let child = ElementRef::wrap(child).expect("element nodes wrap");
+                inline_element(child, base, out);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Surround `text` with `open` and `close`, keeping surrounding whitespace outside, where
+/// emphasis and links need it to be.
+fn wrap(out: &mut String, text: &str, open: &str, close: &str) {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        out.push_str(text);
+        return;
+    }
+    if text.starts_with(char::is_whitespace) {
+        out.push(' ');
+    }
+    out.push_str(open);
+    out.push_str(trimmed);
+    out.push_str(close);
+    if text.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+}
+
+fn image(element: ElementRef<'_>, base: &Url) -> Option<String> {
+    let value = element.value();
+    // Lazy-loaded images keep a placeholder in `src` and the real source elsewhere.
+    let src = ["src", "data-src", "data-original"]
+        .iter()
+        .filter_map(|attr| value.attr(attr))
+        .find(|src| !src.trim().is_empty() && !src.trim_start().starts_with("data:"))?;
+    let src = link(src, base)?;
+    let alt = value
+        .attr("alt")
+        .map(|alt| escape(&alt.split_whitespace().collect::<Vec<_>>().join(" ")))
+        .unwrap_or_default();
+    Some(format!("![{alt}]({})", destination(&src)))
+}
+
+/// `raw` resolved against `base`, unless it is a script.
+fn link(raw: &str, base: &Url) -> Option<Url> {
+    let url = base.join(raw.trim()).ok()?;
+    (!matches!(url.scheme(), "javascript" | "vbscript" | "data")).then_some(url)
+}
+
+fn destination(url: &Url) -> String {
+    let url = url.as_str();
+    if url.contains(['(', ')']) {
+        format!("<{url}>")
+    } else {
+        url.to_string()
+    }
+}
+
+fn code_span(code: &str) -> String {
+    let ticks = "`".repeat(longest_run(code, '`') + 1);
+    if code.starts_with('`') || code.ends_with('`') {
+        format!("{ticks} {code} {ticks}")
+    } else {
+        format!("{ticks}{code}{ticks}")
+    }
+}
+
+fn fenced(code: &str, language: Option<&str>) -> String {
+    let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+    format!("{fence}{}\n{code}\n{fence}", language.unwrap_or_default())
+}
+
+/// Language of a code block, from a `language-*` or `lang-*` class on it or its `<code>`.
+fn language(pre: ElementRef<'_>) -> Option<String> {
+    let code = pre
+        .children()
+        .filter_map(ElementRef::wrap)
+        .find(|c| c.value().name() == "code");
+    [Some(pre), code]
+        .into_iter()
+        .flatten()
+        .flat_map(|e| e.value().classes())
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+        })
+        .filter(|lang| !lang.is_empty() && !lang.contains('`'))
+        .map(str::to_string)
+}
+
+fn longest_run(s: &str, c: char) -> usize {
+    s.split(|x| x != c).map(str::len).max().unwrap_or(0)
+}
+
+fn prefix_lines(text: &str, prefix: &str, empty_prefix: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                empty_prefix.to_string()
+            } else {
+                format!("{prefix}{line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Append the text of a text node, with its whitespace collapsed and Markdown escaped. Line
+/// breaks in the buffer are `<br>`s.
+fn push_text(out: &mut String, text: &str) {
+    let mut collapsed = String::with_capacity(text.len());
+    for (i, word) in text.split_whitespace().enumerate() {
+        if i > 0 {
+            collapsed.push(' ');
+        }
+        collapsed.push_str(word);
+    }
+    if text.starts_with(char::is_whitespace) {
+        out.push(' ');
+    }
+    out.push_str(&escape(&collapsed));
+    if !collapsed.is_empty() && text.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+}
+
+/// Backslash-escape characters that would otherwise be read as inline Markdown.
+fn escape(text: &str) -> String {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut escaped = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let intraword = |i: usize| {
+            i > 0
+                && chars[i - 1].is_alphanumeric()
+                && chars.get(i + 1).is_some_and(|n| n.is_alphanumeric())
+        };
+        match c {
+            '\\' | '`' | '*' | '[' | ']' | '<' => escaped.push('\\'),
+            '_' if !intraword(i) => escaped.push('\\'),
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Finish the paragraph in `inline`, if it has any text, as hard-broken lines.
+fn flush(inline: &mut String, out: &mut Vec<String>) {
+    let text = paragraph(inline);
+    if !text.is_empty() {
+        out.push(text);
+    }
+    inline.clear();
+}
+
+fn paragraph(inline: &str) -> String {
+    inline
+        .split('\n')
+        .map(|line| {
+            escape_line_start(
+                &line
+                    .split(' ')
+                    .filter(|w| !w.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            )
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\\\n")
+}
+
+/// Escape what would start a heading, quote, list, rule or fence at the start of a line.
+fn escape_line_start(line: &str) -> String {
+    if line.starts_with(['#', '>', '-', '+', '=', '~']) {
+        return format!("\\{line}");
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && line[digits..].starts_with(['.', ')']) {
+        return format!("{}\\{}", &line[..digits], &line[digits..]);
+    }
+    line.to_string()
+}
@@ -2,15 +2,24 @@ use bytes::Bytes;
 use thiserror::Error;
 use url::Url;
 
-use crate::{entry::Entry, parser::html::HtmlParser};
+use crate::{
+    entry::Entry,
+    parser::{html::HtmlParser, rules::SiteRule},
+};
 
+pub mod charset;
 mod html;
+pub mod markdown;
+pub mod pagination;
+pub mod readability;
+pub mod rules;
 
 pub trait Parser<'a>: Send {
     fn new(bytes: &Bytes, headers: &'a reqwest::header::HeaderMap, url: &Url) -> Option<Box<Self>>
     where
         Self: Sized;
-    fn parse(&self) -> Result<Entry, ParserError>;
+    /// Parse the document, following `rule` where it applies to the document's site.
+    fn parse(&self, rule: Option<&SiteRule>) -> Result<Entry, ParserError>;
 }
 
 type ParserFn = for<'a> fn(
@@ -0,0 +1,263 @@
+//! Finding the next page of a multi-page article.
+//!
+//! The next page is taken from the site's rule, if it has one, or else from `rel="next"` or a
+//! "next page" link in pagination. Blogs also use `rel="next"` for the next post, so such links
+//! only count if they lead to the same path with a page number added or incremented, and
+//! [`continues`] checks every page followed against the first.
+
+use scraper::{ElementRef, Html, Selector};
+use url::Url;
+
+use crate::parser::{markdown, rules::SiteRule};
+
+/// Link texts that say a link leads to the next page.
+const NEXT_TEXTS: &[&str] = &["next", "next page", "next »", "next ›", "next →", "next >"];
+/// Link texts that only say so within pagination.
+const NEXT_SYMBOLS: &[&str] = &["»", "›", "→", ">", ">>"];
+/// Class or id fragments of pagination.
+const PAGINATION: &[&str] = &["pagination", "pager", "paging", "page-nav", "page-numbers"];
+/// Query parameters numbering the pages of an article.
+const PAGE_PARAMS: &[&str] = &["page", "pg", "paged", "pagenum", "pn", "start"];
+
+/// The page after `page_url` of the article in `doc`, if it has one.
+pub fn next_page(doc: &Html, page_url: &Url, rule: Option<&SiteRule>) -> Option<Url> {
+    let base = markdown::base_url(doc, page_url);
+    if let Some(next) = rule.and_then(|r| r.next_page(doc, &base)) {
+        return (next != *page_url).then_some(next);
+    }
+    let rel_next = Selector::parse("link[rel~=next][href], a[rel~=next][href]").ok()?;
+    let links = Selector::parse("a[href]").ok()?;
+    doc.select(&rel_next)
+        .chain(doc.select(&links).filter(|a| looks_like_next(*a)))
+        .filter_map(|e| base.join(e.value().attr("href")?.trim()).ok())
+        .find(|next| follows(page_url, next))
+}
+
+/// Whether `next` is a later page of the article starting at `first`: the same path and query
+/// but for a page number.
+pub fn continues(first: &Url, next: &Url) -> bool {
+    let Some((first_path, next_path)) = comparable(first, next) else {
+        return false;
+    };
+    next_path == first_path
+        || matches!(split_page_number(&next_path), (base, Some(_)) if base == first_path)
+}
+
+/// Whether `next` may be the page after `page`, which may itself be numbered.
+fn follows(page: &Url, next: &Url) -> bool {
+    if next == page {
+        return false;
+    }
+    if continues(page, next) {
+        return true;
+    }
+    let Some((page_path, next_path)) = comparable(page, next) else {
+        return false;
+    };
+    match (split_page_number(&page_path), split_page_number(&next_path)) {
+        ((page_base, Some(n)), (next_base, Some(m))) => page_base == next_base && m == n + 1,
+        _ => false,
+    }
+}
+
+/// Paths of `a` and `b`, without a trailing slash or extension, if they are on the same host
+/// and their queries only differ in page numbers.
+fn comparable(a: &Url, b: &Url) -> Option<(String, String)> {
+    if a.host_str() != b.host_str() || query(a) != query(b) {
+        return None;
+    }
+    Some((path(a), path(b)))
+}
+
+fn path(url: &Url) -> String {
+    let path = url.path().trim_end_matches('/');
+    let last = path.rfind('/').map_or(0, |i| i + 1);
+    match path[last..].rfind('.') {
+        Some(dot) if dot > 0 => path[..last + dot].to_string(),
+        _ => path.to_string(),
+    }
+}
+
+fn query(url: &Url) -> Vec<(String, String)> {
+    let mut query = url
+        .query_pairs()
+        .filter(|(k, _)| !PAGE_PARAMS.contains(&k.to_ascii_lowercase().as_str()))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect::<Vec<_>>();
+    query.sort();
+    query
+}
+
+/// `path` without a page number of up to three digits at its end, as in `/story/2`,
+/// `/story/page/2` or `/story-2`, and the number.
+fn split_page_number(path: &str) -> (&str, Option<u32>) {
+    let rest = path.trim_end_matches(|c: char| c.is_ascii_digit());
+    let digits = path.len() - rest.len();
+    if digits == 0 || digits > 3 {
+        return (path, None);
+    }
+    ["/page/", "/page-", "-page-", "_page_", "/", "-", "_", ","]
+        .iter()
+        .find_map(|sep| rest.strip_suffix(sep))
+        .map_or((path, None), |base| (base, path[rest.len()..].parse().ok()))
+}
+
+fn looks_like_next(a: ElementRef<'_>) -> bool {
+    let text = a
+        .text()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    if NEXT_TEXTS.contains(&text.as_str()) || text.starts_with("next page") {
+        return true;
+    }
+    let in_pagination = a
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .any(|e| PAGINATION.iter().any(|p| marks(e).contains(p)));
+    in_pagination && (NEXT_SYMBOLS.contains(&text.as_str()) || marks(a).contains("next"))
+}
+
+fn marks(element: ElementRef<'_>) -> String {
+    format!(
+        "{} {}",
+        element.value().attr("class").unwrap_or_default(),
+        element.value().attr("id").unwrap_or_default()
+    )
+    .to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn page_numbers_are_split_off_paths() {
+        let cases = [
+            ("/story/2", ("/story", Some(2))),
+            ("/story/page/12", ("/story", Some(12))),
+            ("/story/page-3", ("/story", Some(3))),
+            ("/story-page-4", ("/story", Some(4))),
+            ("/story_page_5", ("/story", Some(5))),
+            ("/story-2", ("/story", Some(2))),
+            ("/story_2", ("/story", Some(2))),
+            ("/story,2", ("/story", Some(2))),
+            ("/story", ("/story", None)),
+            // Too long to be a page number, or not set apart.
+            ("/story/2024", ("/story/2024", None)),
+            ("/story2", ("/story2", None)),
+            ("/", ("/", None)),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(split_page_number(path), expected, "{path}");
+        }
+    }
+
+    #[test]
+    fn later_pages_continue_the_first() {
+        let first = url("https://example.com/story.html?id=7");
+        let cases = [
+            ("https://example.com/story.html?id=7&page=2", true),
+            ("https://example.com/story/2?id=7", true),
+            ("https://example.com/story/page/3.html?id=7", true),
+            ("https://example.com/story-4/?id=7", true),
+            ("https://example.com/story.html?id=8&page=2", false),
+            ("https://example.org/story.html?id=7&page=2", false),
+            ("https://example.com/other/2?id=7", false),
+            ("https://example.com/story/comments?id=7", false),
+        ];
+        for (next, expected) in cases {
+            assert_eq!(continues(&first, &url(next)), expected, "{next}");
+        }
+    }
+
+    #[test]
+    fn only_the_next_page_follows() {
+        let cases = [
+            (
+                "https://example.com/story",
+                "https://example.com/story/2",
+                true,
+            ),
+            (
+                "https://example.com/story/2",
+                "https://example.com/story/3",
+                true,
+            ),
+            (
+                "https://example.com/story?page=2",
+                "https://example.com/story?page=3",
+                true,
+            ),
+            (
+                "https://example.com/story/2",
+                "https://example.com/story/2",
+                false,
+            ),
+            (
+                "https://example.com/story/2",
+                "https://example.com/story/4",
+                false,
+            ),
+            (
+                "https://example.com/story",
+                "https://example.com/other",
+                false,
+            ),
+            // The next post of a blog numbering its posts follows the one before, but does not
+            // continue the article.
+            (
+                "https://example.com/posts/12",
+                "https://example.com/posts/13",
+                true,
+            ),
+        ];
+        for (page, next, expected) in cases {
+            assert_eq!(
+                follows(&url(page), &url(next)),
+                expected,
+                "{page} -> {next}"
+            );
+        }
+        assert!(!continues(
+            &url("https://example.com/posts/12"),
+            &url("https://example.com/posts/13")
+        ));
+    }
+
+    #[test]
+    fn next_links_are_found_in_pagination() {
+        let page = url("https://example.com/story");
+        let cases = [
+            (
+                r#"<link rel="next" href="/story/2">"#,
+                Some("https://example.com/story/2"),
+            ),
+            (
+                r#"<a href="/story/2">Next page</a>"#,
+                Some("https://example.com/story/2"),
+            ),
+            (
+                r#"<nav class="pagination"><a href="/story/2">»</a></nav>"#,
+                Some("https://example.com/story/2"),
+            ),
+            // A bare arrow outside pagination could be anything.
+            (r#"<a href="/story/2">»</a>"#, None),
+            (r#"<a rel="next" href="/another-story">Next post</a>"#, None),
+        ];
+        for (html, expected) in cases {
+            let doc = Html::parse_document(html);
+            assert_eq!(
+                next_page(&doc, &page, None).as_ref().map(Url::as_str),
+                expected,
+                "{html}"
+            );
+        }
+    }
+}
@@ -0,0 +1,438 @@
+//! Finding the main content of a page, after Mozilla's Readability.
+//!
+//! Boilerplate (navigation, sidebars, comments, share buttons and the like) is recognised by tag,
+//! ARIA role and class or id, and ignored throughout. Every paragraph of the rest then scores its
+//! parent and grandparent by its length and number of commas; containers are weighted by tag and
+//! class or id, and discounted by how much of their text is links. The best container wins,
+//! together with those of its siblings that look like part of the same article.
+
+use std::collections::HashMap;
+
+use ego_tree::NodeId;
+use scraper::{node::Node, ElementRef, Html};
+
+/// Paragraphs shorter than this do not vote for containers.
+const MIN_PARAGRAPH_LEN: usize = 25;
+
+/// Elements whose text is never content.
+const BOILERPLATE_TAGS: &[&str] = &[
+    "aside", "button", "canvas", "dialog", "embed", "footer", "form", "header", "iframe", "input",
+    "menu", "nav", "noscript", "object", "script", "select", "style", "svg", "template",
+    "textarea",
+];
+
+const BOILERPLATE_ROLES: &[&str] = &[
+    "alertdialog",
+    "banner",
+    "complementary",
+    "contentinfo",
+    "dialog",
+    "menu",
+    "menubar",
+    "navigation",
+    "search",
+];
+
+/// Class or id fragments marking boilerplate, unless one of [`MAYBE_CONTENT`] is present too.
+const UNLIKELY: &[&str] = &[
+    "-ad-",
+    "ad-break",
+    "agegate",
+    "banner",
+    "breadcrumb",
+    "combx",
+    "comment",
+    "community",
+    "cookie",
+    "disqus",
+    "extra",
+    "footer",
+    "gdpr",
+    "header",
+    "legends",
+    "menu",
+    "newsletter",
+    "pager",
+    "pagination",
+    "popup",
+    "related",
+    "remark",
+    "replies",
+    "rss",
+    "share",
+    "shoutbox",
+    "sidebar",
+    "skyscraper",
+    "social",
+    "sponsor",
+    "subscribe",
+    "supplemental",
+    "yom-remote",
+];
+
+const MAYBE_CONTENT: &[&str] = &[
+    "and", "article", "body", "column", "content", "main", "shadow",
+];
+
+const POSITIVE: &[&str] = &[
+    "article", "blog", "body", "content", "entry", "h-entry", "hentry", "main", "page", "post",
+    "story", "text",
+];
+
+const NEGATIVE: &[&str] = &[
+    "-ad",
+    "banner",
+    "byline",
+    "combx",
+    "comment",
+    "com-",
+    "contact",
+    "foot",
+    "footnote",
+    "gdpr",
+    "hidden",
+    "masthead",
+    "media",
+    "meta",
+    "outbrain",
+    "promo",
+    "related",
+    "scroll",
+    "share",
+    "shoutbox",
+    "sidebar",
+    "skyscraper",
+    "sponsor",
+    "shopping",
+    "tags",
+    "tool",
+    "widget",
+];
+
+/// Elements that start a new block of text; anything else is inline.
+const BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "ul",
+];
+
+/// The main content of `doc`: the winning container and the siblings that go with it, in
+/// document order. Falls back to the whole body when no paragraph is long enough to vote.
+pub fn extract(doc: &Html) -> Vec<ElementRef<'_>> {
+    let scores = score(doc);
+    let Some((&top_id, &top_score)) = scores.iter().max_by(|a, b| a.1.total_cmp(b.1)) else {
+        return body(doc).into_iter().collect();
+    };
+    let top = doc
+        .tree
+        .get(top_id)
+        .and_then(ElementRef::wrap)
+        .expect("only elements are scored");
+
+    let Some(parent) = top.parent().and_then(ElementRef::wrap) else {
+        return vec![top];
+    };
+    let threshold = (top_score * 0.2).max(10.0);
+    let top_class = top.value().attr("class").filter(|c| !c.trim().is_empty());
+    parent
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|sibling| {
+            if sibling.id() == top_id {
+                return true;
+            }
+            if is_boilerplate(*sibling) {
+                return false;
+            }
+            let bonus = match top_class {
+                Some(class) if sibling.value().attr("class") == Some(class) => top_score * 0.2,
+                _ => 0.0,
+            };
+            if scores
+                .get(&sibling.id())
+                .is_some_and(|s| s + bonus >= threshold)
+            {
+                return true;
+            }
+            if sibling.value().name() != "p" {
+                return false;
+            }
+            let text = normalise(&sibling.text().collect::<String>());
+            let density = link_density(*sibling);
+            (text.len() > 80 && density < 0.25)
+                || (!text.is_empty() && density == 0.0 && text.ends_with('.'))
+        })
+        .collect()
+}
+
+/// Plain text of `nodes`, one paragraph per block, leaving out boilerplate.
+pub fn text(nodes: &[ElementRef<'_>]) -> String {
+    let mut blocks = Vec::new();
+    for node in nodes {
+        render(*node, &mut blocks);
+    }
+    blocks.join("\n\n")
+}
+
+/// Plain text of the main content of `doc`, if it has any.
+pub fn extract_text(doc: &Html) -> Option<String> {
+    Some(text(&extract(doc))).filter(|t| !t.trim().is_empty())
+}
+
+fn body(doc: &Html) -> Option<ElementRef<'_>> {
+    let selector = scraper::Selector::parse("body").ok()?;
+    doc.select(&selector).next()
+}
+
+/// Readability score of every container some paragraph voted for.
+fn score(doc: &Html) -> HashMap<NodeId, f64> {
+    let mut scores = HashMap::<NodeId, f64>::new();
+    let Some(body) = body(doc) else {
+        return scores;
+    };
+    for element in body.descendants().filter_map(ElementRef::wrap) {
+        if !is_paragraph(element) || in_boilerplate(element) {
+            continue;
+        }
+        let text = normalise(&element.text().collect::<String>());
+        if text.len() < MIN_PARAGRAPH_LEN {
+            continue;
+        }
+        let points =
+            1.0 + text.matches([',', '，']).count() as f64 + (text.len() / 100).min(3) as f64;
+
+        let ancestors = element.ancestors().filter_map(ElementRef::wrap).take(2);
+        for (level, ancestor) in ancestors.enumerate() {
+            if matches!(ancestor.value().name(), "html" | "body") {
+                break;
+            }
+            let share = if level == 0 { points } else { points / 2.0 };
+            *scores
+                .entry(ancestor.id())
+                .or_insert_with(|| initial_score(ancestor)) += share;
+        }
+    }
+    for (id, score) in scores.iter_mut() {
+        if let Some(element) = doc.tree.get(*id).and_then(ElementRef::wrap) {
+            *score *= 1.0 - link_density(element);
+        }
+    }
+    scores
+}
+
+fn initial_score(element: ElementRef<'_>) -> f64 {
+    let by_tag = match element.value().name() {
+        "article" => 10.0,
+        "div" | "section" | "main" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    by_tag + class_weight(element)
+}
+
+/// +25 for each of class and id that suggests content, -25 for each that suggests otherwise.
+fn class_weight(element: ElementRef<'_>) -> f64 {
+    ["class", "id"]
+        .iter()
+        .filter_map(|attr| element.value().attr(attr))
+        .map(|value| {
+            let value = value.to_ascii_lowercase();
+            let mut weight = 0.0;
+            if NEGATIVE.iter().any(|n| value.contains(n)) {
+                weight -= 25.0;
+            }
+            if POSITIVE.iter().any(|p| value.contains(p)) {
+                weight += 25.0;
+            }
+            weight
+        })
+        .sum()
+}
+
+/// Elements whose text reads as one paragraph: those that hold nothing but inline content.
+fn is_paragraph(element: ElementRef<'_>) -> bool {
+    match element.value().name() {
+        "p" | "pre" | "td" | "blockquote" => true,
+        "div" | "section" => !has_block_children(element),
+        _ => false,
+    }
+}
+
+fn has_block_children(element: ElementRef<'_>) -> bool {
+    element
+        .children()
+        .filter_map(ElementRef::wrap)
+        .any(|child| is_block(child))
+}
+
+pub(crate) fn is_block(element: ElementRef<'_>) -> bool {
+    BLOCK_TAGS.contains(&element.value().name())
+}
+
+/// Whether `element`, and everything in it, is boilerplate rather than content.
+pub(crate) fn is_boilerplate(element: ElementRef<'_>) -> bool {
+    let value = element.value();
+    if BOILERPLATE_TAGS.contains(&value.name()) {
+        return true;
+    }
+    if value.attr("hidden").is_some()
+        || value.attr("aria-hidden") == Some("true")
+        || value.attr("style").is_some_and(|s| {
+            let s = s.replace(' ', "").to_ascii_lowercase();
+            s.contains("display:none") || s.contains("visibility:hidden")
+        })
+    {
+        return true;
+    }
+    if value
+        .attr("role")
+        .is_some_and(|r| BOILERPLATE_ROLES.contains(&r.trim().to_ascii_lowercase().as_str()))
+    {
+        return true;
+    }
+    if matches!(value.name(), "html" | "body" | "article" | "main") {
+        return false;
+    }
+    let marks = format!(
+        "{} {}",
+        value.attr("class").unwrap_or_default(),
+        value.attr("id").unwrap_or_default()
+    )
+    .to_ascii_lowercase();
+    UNLIKELY.iter().any(|u| marks.contains(u)) && !MAYBE_CONTENT.iter().any(|m| marks.contains(m))
+}
+
+/// Lists of links, such as tag clouds and "read more" lists, are navigation in disguise.
+pub(crate) fn is_link_list(element: ElementRef<'_>) -> bool {
+    matches!(element.value().name(), "ul" | "ol" | "li" | "div") && link_density(element) > 0.5
+}
+
+fn in_boilerplate(element: ElementRef<'_>) -> bool {
+    is_boilerplate(element)
+        || element
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .any(is_boilerplate)
+}
+
+/// Share of the text of `element` that is in links.
+fn link_density(element: ElementRef<'_>) -> f64 {
+    let total = element.text().map(str::len).sum::<usize>();
+    if total == 0 {
+        return 0.0;
+    }
+    let linked = element
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|e| e.value().name() == "a")
+        .flat_map(|a| a.text())
+        .map(str::len)
+        .sum::<usize>();
+    linked as f64 / total as f64
+}
+
+/// Append the text blocks of `element` to `blocks`. Runs of inline content between blocks count
+/// as paragraphs of their own.
+fn render(element: ElementRef<'_>, blocks: &mut Vec<String>) {
+    if is_boilerplate(element) {
+        return;
+    }
+    match element.value().name() {
+        "pre" => {
+            let text = element.text().collect::<String>();
+            let text = text.trim_matches('\n').trim_end();
+            if !text.is_empty() {
+                blocks.push(text.to_string());
+            }
+            return;
+        }
+        "hr" | "img" | "br" => return,
+        _ => {}
+    }
+    if is_link_list(element) {
+        return;
+    }
+
+    let mut inline = String::new();
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => inline.push_str(text),
+            Node::Element(_) => {
+                let child = ElementRef::wrap(child).expect("element nodes wrap");
+                if is_block(child) {
+                    flush(&mut inline, blocks);
+                    render(child, blocks);
+                } else if !is_boilerplate(child) {
+                    inline_text(child, &mut inline);
+                }
+            }
+            _ => {}
+        }
+    }
+    flush(&mut inline, blocks);
+}
+
+fn inline_text(element: ElementRef<'_>, out: &mut String) {
+    if element.value().name() == "br" {
+        out.push(' ');
+        return;
+    }
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(_) => {
+                let child = ElementRef::wrap(child).expect("element nodes wrap");
+                if !is_boilerplate(child) {
+                    inline_text(child, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn flush(inline: &mut String, blocks: &mut Vec<String>) {
+    let text = normalise(inline);
+    if !text.is_empty() {
+        blocks.push(text);
+    }
+    inline.clear();
+}
+
+fn normalise(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
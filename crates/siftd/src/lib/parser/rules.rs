@@ -0,0 +1,354 @@
+//! Per-site extraction rules, for sites that defeat the generic heuristics.
+//!
+//! Rules are read from a JSON file at startup and handed to [`Content::parse`], which has
+//! `HtmlParser` follow the first rule matching the page URL before falling back to its
+//! heuristics. A rule applies to pages on any of its `hosts` (`*.example.org` matching subdomains
+//! of `example.org`), or whose URL matches any of its `urls` patterns, in which `*` stands for
+//! anything. Everything else is a list of CSS selectors, tried in order:
+//!
+//! ```json
+//! [
+//!   {
+//!     "hosts": ["example.com", "*.example.org"],
+//!     "urls": ["https://example.net/blog/*"],
+//!     "content": ["div.post-body"],
+//!     "strip": [".newsletter-signup", "figure.advert"],
+//!     "title": ["h1.headline"],
+//!     "author": [".byline a[rel=author]"],
+//!     "date": ["time.published"],
+//!     "next_page": ["a.pagination-next"]
+//!   }
+//! ]
+//! ```
+//!
+//! `content` is every element matched by the first selector that matches any, and `strip`
+//! removes every element matched by any selector before anything else is looked at. Dates are
+//! taken from `datetime` or `content` attributes where present, and authors from `content`.
+//!
+//! [`Content::parse`]: crate::content::Content::parse
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use scraper::{ElementRef, Html, Selector};
+use serde::Deserialize;
+use thiserror::Error;
+use url::Url;
+
+#[derive(Debug, Error)]
+pub enum RulesError {
+    #[error("reading site rules from {} failed", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("site rules are malformed")]
+    Json(#[from] serde_json::Error),
+    /// Rules are numbered from 1, in file order.
+    #[error("site rule {rule} has neither hosts nor urls")]
+    Unanchored { rule: usize },
+    #[error("site rule {rule} has invalid selector {selector:?}: {reason}")]
+    Selector {
+        rule: usize,
+        selector: String,
+        reason: String,
+    },
+}
+
+/// A rule as written in the rules file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    #[serde(default)]
+    hosts: Vec<String>,
+    #[serde(default)]
+    urls: Vec<String>,
+    #[serde(default)]
+    content: Vec<String>,
+    #[serde(default)]
+    strip: Vec<String>,
+    #[serde(default)]
+    title: Vec<String>,
+    #[serde(default)]
+    author: Vec<String>,
+    #[serde(default)]
+    date: Vec<String>,
+    #[serde(default)]
+    next_page: Vec<String>,
+}
+
+/// Extraction rule for one site, see the module documentation.
+#[derive(Debug, Clone)]
+pub struct SiteRule {
+    hosts: Vec<String>,
+    urls: Vec<String>,
+    content: Vec<Selector>,
+    strip: Vec<Selector>,
+    title: Vec<Selector>,
+    author: Vec<Selector>,
+    date: Vec<Selector>,
+    next_page: Vec<Selector>,
+}
+
+impl SiteRule {
+    fn compile(number: usize, raw: RawRule) -> Result<Self, RulesError> {
+        if raw.hosts.is_empty() && raw.urls.is_empty() {
+            return Err(RulesError::Unanchored { rule: number });
+        }
+        let selectors = |list: Vec<String>| {
+            list.into_iter()
+                .map(|s| {
+                    Selector::parse(&s).map_err(|e| RulesError::Selector {
+                        rule: number,
+                        reason: e.to_string(),
+                        selector: s.clone(),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self {
+            hosts: raw.hosts.iter().map(|h| h.to_ascii_lowercase()).collect(),
+            urls: raw.urls,
+            content: selectors(raw.content)?,
+            strip: selectors(raw.strip)?,
+            title: selectors(raw.title)?,
+            author: selectors(raw.author)?,
+            date: selectors(raw.date)?,
+            next_page: selectors(raw.next_page)?,
+        })
+    }
+
+    pub fn matches(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        self.hosts
+            .iter()
+            .any(|pattern| match pattern.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|sub| sub.ends_with('.')),
+                None => host == *pattern,
+            })
+            || self.urls.iter().any(|pattern| glob(pattern, url.as_str()))
+    }
+
+    /// Remove the elements to strip from `doc`.
+    pub fn strip(&self, doc: &mut Html) {
+        let ids = self
+            .strip
+            .iter()
+            .flat_map(|selector| doc.select(selector).map(|e| e.id()))
+            .collect::<Vec<_>>();
+        for id in ids {
+            if let Some(mut node) = doc.tree.get_mut(id) {
+                node.detach();
+            }
+        }
+    }
+
+    /// The main content of `doc`, if a content selector matches anything.
+    pub fn content<'a>(&self, doc: &'a Html) -> Option<Vec<ElementRef<'a>>> {
+        self.content.iter().find_map(|selector| {
+            let nodes = doc.select(selector).collect::<Vec<_>>();
+            (!nodes.is_empty()).then_some(nodes)
+        })
+    }
+
+    pub fn title(&self, doc: &Html) -> Option<String> {
+        first(doc, &self.title, &[])
+    }
+
+    pub fn author(&self, doc: &Html) -> Option<String> {
+        first(doc, &self.author, &["content"])
+    }
+
+    /// Publication date as written in the page, to be parsed by the caller.
+    pub fn date(&self, doc: &Html) -> Option<String> {
+        first(doc, &self.date, &["datetime", "content"])
+    }
+
+    /// Whether the rule says where multi-page articles continue, in which case it is trusted to.
+    pub fn has_next_page(&self) -> bool {
+        !self.next_page.is_empty()
+    }
+
+    /// URL of the next page of a multi-page article, resolved against `base`.
+    pub fn next_page(&self, doc: &Html, base: &Url) -> Option<Url> {
+        self.next_page.iter().find_map(|selector| {
+            doc.select(selector)
+                .filter_map(|e| e.value().attr("href"))
+                .find_map(|href| base.join(href.trim()).ok())
+        })
+    }
+}
+
+/// The value of the first of `attrs` on the first element any of `selectors` matches, or else
+/// its text; skipping elements with neither.
+fn first(doc: &Html, selectors: &[Selector], attrs: &[&str]) -> Option<String> {
+    selectors
+        .iter()
+        .flat_map(|selector| doc.select(selector))
+        .find_map(|element| {
+            let value = attrs
+                .iter()
+                .find_map(|attr| element.value().attr(attr))
+                .map(str::to_string)
+                .unwrap_or_else(|| element.text().collect());
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            (!value.is_empty()).then_some(value)
+        })
+}
+
+/// Whether `text` matches `pattern` in full, `*` in the pattern matching any run of characters.
+fn glob(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Every rule in a rules file, in file order.
+#[derive(Debug, Clone, Default)]
+pub struct SiteRules {
+    rules: Vec<SiteRule>,
+}
+
+impl SiteRules {
+    pub fn load(path: &Path) -> Result<Self, RulesError> {
+        let json = fs::read_to_string(path).map_err(|source| RulesError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&json)
+    }
+
+    pub fn parse(json: &str) -> Result<Self, RulesError> {
+        let raw: Vec<RawRule> = serde_json::from_str(json)?;
+        let rules = raw
+            .into_iter()
+            .enumerate()
+            .map(|(index, rule)| SiteRule::compile(index + 1, rule))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The first rule applying to `url`.
+    pub fn find(&self, url: &Url) -> Option<&SiteRule> {
+        self.rules.iter().find(|rule| rule.matches(url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn globs_match_in_full() {
+        let cases = [
+            ("https://example.com/", "https://example.com/", true),
+            ("https://example.com/", "https://example.com/a", false),
+            ("https://example.com/*", "https://example.com/", true),
+            ("https://example.com/*", "https://example.com/a/b?c", true),
+            ("*/blog/*", "https://example.com/blog/post", true),
+            ("*/blog/*", "https://example.com/news/post", false),
+            (
+                "https://*.example.com/*.html",
+                "https://www.example.com/a.html",
+                true,
+            ),
+            (
+                "https://*.example.com/*.html",
+                "https://www.example.com/a.htm",
+                false,
+            ),
+            ("*", "", true),
+            ("a*a", "a", false),
+            ("a*b*b", "ab", false),
+            ("a*b*b", "abb", true),
+            ("a**b", "ab", true),
+        ];
+        for (pattern, text, expected) in cases {
+            assert_eq!(glob(pattern, text), expected, "{pattern} on {text}");
+        }
+    }
+
+    #[test]
+    fn rules_match_hosts_and_url_patterns() {
+        let rules = SiteRules::parse(
+            r#"[
+                {"hosts": ["Example.com", "*.example.org"], "title": ["h1"]},
+                {"urls": ["https://example.net/blog/*"], "title": ["h2"]},
+                {"hosts": ["example.com"], "title": ["h3"]}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 3);
+        // Each rule picks a different heading, which tells them apart.
+        let doc = Html::parse_document("<h1>0</h1><h2>1</h2><h3>2</h3>");
+        let found = |u: &str| rules.find(&url(u)).and_then(|rule| rule.title(&doc));
+        // The first matching rule wins, and hosts are matched whatever their case.
+        assert_eq!(found("https://EXAMPLE.com/a"), Some("0".to_string()));
+        assert_eq!(found("https://www.example.com/a"), None);
+        assert_eq!(found("https://www.example.org/a"), Some("0".to_string()));
+        assert_eq!(found("https://a.b.example.org/a"), Some("0".to_string()));
+        // The wildcard only covers subdomains, not the domain or lookalikes.
+        assert_eq!(found("https://example.org/a"), None);
+        assert_eq!(found("https://badexample.org/a"), None);
+        assert_eq!(
+            found("https://example.net/blog/post"),
+            Some("1".to_string())
+        );
+        assert_eq!(found("https://example.net/about"), None);
+    }
+
+    #[test]
+    fn malformed_rules_are_refused() {
+        assert!(matches!(
+            SiteRules::parse(r#"[{"hosts": ["a.com"]}, {"content": ["div"]}]"#),
+            Err(RulesError::Unanchored { rule: 2 })
+        ));
+        match SiteRules::parse(r#"[{"hosts": ["a.com"], "strip": ["div", "p >"]}]"#) {
+            Err(RulesError::Selector { rule, selector, .. }) => {
+                assert_eq!(rule, 1);
+                assert_eq!(selector, "p >");
+            }
+            other => panic!("expected a selector error, got {other:?}"),
+        }
+        let unknown = SiteRules::parse(r#"[{"hosts": ["a.com"], "body": ["div"]}]"#);
+        match unknown {
+            Err(RulesError::Json(e)) => assert!(e.to_string().contains("unknown field `body`")),
+            other => panic!("expected a JSON error, got {other:?}"),
+        }
+        assert!(matches!(
+            SiteRules::parse(r#"{"hosts": ["a.com"]}"#),
+            Err(RulesError::Json(_))
+        ));
+        assert!(SiteRules::parse("[]").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,286 @@
+//! Politeness towards the hosts fetched from.
+//!
+//! Every fetch through [`Content::fetch`] first takes a [`HostPermit`] from the [`HostLimiter`]
+//! of the [`HttpClient`] it is given, which caps both the rate of requests to a host, with a token
+//! bucket, and the number in flight. A host answering `429 Too Many Requests`, or
+//! `503 Service Unavailable` with `Retry-After`, is left alone until it asks to be retried.
+//!
+//! [`Content::fetch`]: crate::content::Content::fetch
+//! [`HttpClient`]: crate::http::HttpClient
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use reqwest::{
+    header::{self, HeaderMap},
+    StatusCode,
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{debug, info};
+use url::Url;
+
+/// Limits on the requests made to one host.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HostLimits {
+    /// Sustained requests per second.
+    pub rate: f64,
+    /// Requests that may be made at once after a quiet spell, beyond the sustained rate.
+    pub burst: u32,
+    pub max_in_flight: usize,
+}
+
+impl HostLimits {
+    pub const DEFAULT_RATE: f64 = 1.0;
+    pub const DEFAULT_BURST: u32 = 4;
+    pub const DEFAULT_MAX_IN_FLIGHT: usize = 2;
+}
+
+impl Default for HostLimits {
+    fn default() -> Self {
+        Self {
+            rate: Self::DEFAULT_RATE,
+            burst: Self::DEFAULT_BURST,
+            max_in_flight: Self::DEFAULT_MAX_IN_FLIGHT,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HostState {
+    limits: HostLimits,
+    /// Tokens in the bucket as of `updated`; negative when requests are waiting on it.
+    tokens: f64,
+    updated: Instant,
+    in_flight: Arc<Semaphore>,
+    deferred_until: Option<Instant>,
+}
+
+impl HostState {
+    /// Longest a request waits for a token, however slow the rate.
+    const MAX_WAIT: Duration = Duration::from_secs(60 * 60);
+
+    fn new(limits: HostLimits) -> Self {
+        Self {
+            limits,
+            tokens: f64::from(limits.burst.max(1)),
+            updated: Instant::now(),
+            in_flight: Arc::new(Semaphore::new(limits.max_in_flight.max(1))),
+            deferred_until: None,
+        }
+    }
+
+    /// Take a token, returning how long to wait until it is actually available.
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let rate = self.limits.rate.max(f64::MIN_POSITIVE);
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(f64::from(self.limits.burst.max(1)));
+        self.updated = now;
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::try_from_secs_f64(-self.tokens / rate)
+                .map_or(Self::MAX_WAIT, |wait| wait.min(Self::MAX_WAIT))
+        }
+    }
+}
+
+/// Permission to make one request to a host, held until its response has been read.
+pub struct HostPermit {
+    _in_flight: OwnedSemaphorePermit,
+}
+
+/// Per-host rate and concurrency limits, see the module documentation.
+#[derive(Debug)]
+pub struct HostLimiter {
+    default: HostLimits,
+    overrides: HashMap<String, HostLimits>,
+    max_defer: Duration,
+    hosts: Mutex<HashMap<String, HostState>>,
+}
+
+impl HostLimiter {
+    /// How long a host answering 429 without `Retry-After` is left alone.
+    pub const DEFAULT_DEFER: Duration = Duration::from_secs(60);
+    /// Longest a host is left alone, whatever its `Retry-After` says.
+    pub const DEFAULT_MAX_DEFER: Duration = Duration::from_secs(60 * 60);
+
+    pub fn new(default: HostLimits) -> Self {
+        Self {
+            default,
+            overrides: HashMap::new(),
+            max_defer: Self::DEFAULT_MAX_DEFER,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Use `limits` for `host` instead of the default. Subdomains are not included.
+    pub fn with_override(mut self, host: impl Into<String>, limits: HostLimits) -> Self {
+        self.overrides
+            .insert(host.into().to_ascii_lowercase(), limits);
+        self
+    }
+
+    pub fn with_max_defer(mut self, max_defer: Duration) -> Self {
+        self.max_defer = max_defer;
+        self
+    }
+
+    pub fn limits(&self, host: &str) -> HostLimits {
+        self.overrides.get(host).copied().unwrap_or(self.default)
+    }
+
+    /// Wait until a request to `url`'s host is allowed, and count it as in flight until the
+    /// permit is dropped.
+    pub async fn acquire(&self, url: &Url) -> HostPermit {
+        let host = host(url);
+        let semaphore = self.with_host(&host, |state| state.in_flight.clone());
+        let in_flight = semaphore
+            .acquire_owned()
+            .await
+            .expect("the semaphore is never closed");
+
+        // Tokens are only taken once the host is no longer deferred, so that requests held up by
+        // a deferral are spread out afterwards; but deferrals may also come in while waiting for
+        // a token, from requests already in flight.
+        let mut reserved = false;
+        loop {
+            let now = Instant::now();
+            let deferred = self.with_host(&host, |state| state.deferred_until.filter(|t| *t > now));
+            if let Some(until) = deferred {
+                debug!(%host, wait_ms = (until - now).as_millis() as u64, "host deferred");
+                tokio::time::sleep_until(until.into()).await;
+                continue;
+            }
+            if reserved {
+                break;
+            }
+            reserved = true;
+            let wait = self.with_host(&host, |state| state.reserve(now));
+            if !wait.is_zero() {
+                debug!(%host, wait_ms = wait.as_millis() as u64, "waiting for rate limit");
+                tokio::time::sleep(wait).await;
+            }
+        }
+        HostPermit {
+            _in_flight: in_flight,
+        }
+    }
+
+    /// Note a response from `url`'s host, deferring it if it asked to be left alone. Returns
+    /// for how long.
+    pub fn observe(&self, url: &Url, status: StatusCode, headers: &HeaderMap) -> Option<Duration> {
+        let retry_after = retry_after(headers, Utc::now());
+        let defer = match status {
+            StatusCode::TOO_MANY_REQUESTS => retry_after.unwrap_or(Self::DEFAULT_DEFER),
+            StatusCode::SERVICE_UNAVAILABLE => retry_after?,
+            _ => return None,
+        }
+        .min(self.max_defer);
+        let host = host(url);
+        info!(%host, %status, defer_secs = defer.as_secs(), "host deferred");
+        let until = Instant::now() + defer;
+        self.with_host(&host, |state| {
+            state.deferred_until = Some(state.deferred_until.map_or(until, |t| t.max(until)));
+        });
+        Some(defer)
+    }
+
+    fn with_host<T>(&self, host: &str, f: impl FnOnce(&mut HostState) -> T) -> T {
+        let mut hosts = self.hosts.lock().unwrap();
+        let state = hosts
+            .entry(host.to_string())
+            .or_insert_with(|| HostState::new(self.limits(host)));
+        f(state)
+    }
+}
+
+impl Default for HostLimiter {
+    fn default() -> Self {
+        Self::new(HostLimits::default())
+    }
+}
+
+/// Delay asked for by a `Retry-After` header, in seconds or as an HTTP date.
+fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let value = headers.get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&Utc) - now).to_std().unwrap_or_default())
+}
+
+fn host(url: &Url) -> String {
+    url.host_str().unwrap_or_default().to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(retry_after: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RETRY_AFTER, retry_after.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn retry_after_takes_seconds_or_dates() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+        let cases = [
+            ("120", Some(Duration::from_secs(120))),
+            (" 0 ", Some(Duration::ZERO)),
+            (
+                "Wed, 21 Oct 2015 07:30:30 GMT",
+                Some(Duration::from_secs(150)),
+            ),
+            ("Wed, 21 Oct 2015 07:28:00 GMT", Some(Duration::ZERO)),
+            ("Wed, 21 Oct 2015 06:00:00 GMT", Some(Duration::ZERO)),
+            ("-5", None),
+            ("1.5", None),
+            ("soon", None),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(retry_after(&headers(value), now), expected, "{value}");
+        }
+        assert_eq!(retry_after(&HeaderMap::new(), now), None);
+    }
+
+    #[test]
+    fn bursts_then_waits_at_the_rate() {
+        let mut state = HostState::new(HostLimits {
+            rate: 2.0,
+            burst: 3,
+            max_in_flight: 1,
+        });
+        let start = state.updated;
+        let waits = (0..5).map(|_| state.reserve(start)).collect::<Vec<_>>();
+        assert_eq!(waits, [0, 0, 0, 500, 1000].map(Duration::from_millis),);
+
+        // A quiet spell refills no more than the burst.
+        let later = start + Duration::from_secs(60);
+        let waits = (0..4).map(|_| state.reserve(later)).collect::<Vec<_>>();
+        assert_eq!(waits, [0, 0, 0, 500].map(Duration::from_millis));
+    }
+
+    #[test]
+    fn waits_are_capped_however_slow_the_rate() {
+        for rate in [0.0, -1.0, 1e-300] {
+            let mut state = HostState::new(HostLimits {
+                rate,
+                burst: 1,
+                max_in_flight: 1,
+            });
+            let now = state.updated;
+            assert_eq!(state.reserve(now), Duration::ZERO);
+            assert_eq!(state.reserve(now), HostState::MAX_WAIT, "{rate}");
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Ranking unread entries into a reading list.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use rand::Rng;
+use serde::Serialize;
+use tracing::debug;
+
+use crate::{
+    graph::{FastPpr, SimilarityGraph, TransitionGraph},
+    storage::{EntryFilter, Page, Storage, StorageError, StoredEntry},
+};
+
+/// Scores unread entries by how close they are to liked entries and how far from disliked
+/// ones in the similarity graph, and by how fresh they are.
+///
+/// Closeness to a set of entries is their Personalised PageRank, each entry weighted by how
+/// recently it was liked or disliked, relative to the uniform 1/n. Since PageRank is linear in
+/// its sources, this is the sum of the PageRanks personalised to each entry. It enters the score
+/// as `ln(1 + x)`, so that one very close entry does not drown everything else out.
+#[derive(Debug, Clone, Copy)]
+pub struct Recommender {
+    pub ppr: FastPpr,
+    /// Time after which a like or dislike counts for half as much.
+    pub interaction_half_life: TimeDelta,
+    /// Time after publication after which an entry is half as fresh.
+    pub freshness_half_life: TimeDelta,
+    pub like_weight: f64,
+    pub dislike_weight: f64,
+    pub freshness_weight: f64,
+    /// How many of the most recently stored unread entries are considered.
+    pub max_candidates: u32,
+}
+
+/// A recommended entry, and how its score came about.
+#[derive(Debug, Serialize)]
+pub struct Recommendation {
+    pub score: f64,
+    pub breakdown: ScoreBreakdown,
+    pub entry: StoredEntry,
+}
+
+/// Weighted terms of a score, which is `liked - disliked + freshness`.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ScoreBreakdown {
+    pub liked: f64,
+    pub disliked: f64,
+    pub freshness: f64,
+}
+
+impl ScoreBreakdown {
+    pub fn score(&self) -> f64 {
+        self.liked - self.disliked + self.freshness
+    }
+}
+
+impl Recommender {
+    pub const DEFAULT_INTERACTION_HALF_LIFE: TimeDelta = TimeDelta::days(30);
+    pub const DEFAULT_FRESHNESS_HALF_LIFE: TimeDelta = TimeDelta::days(2);
+    pub const DEFAULT_MAX_CANDIDATES: u32 = Page::MAX_LIMIT;
+    /// Likes and dislikes older than this many half-lives are ignored.
+    const HORIZON_HALF_LIVES: i32 = 10;
+
+    /// The `limit` best unread entries, best first. Entries already liked or disliked are left
+    /// out, as are near-duplicates.
+    pub async fn recommend(
+        &self,
+        storage: &Storage,
+        graph: &SimilarityGraph,
+        limit: usize,
+    ) -> Result<Vec<Recommendation>, StorageError> {
+        let now = Utc::now();
+        let interactions = storage.interactions();
+        let horizon = now - self.interaction_half_life * Self::HORIZON_HALF_LIVES;
+        let all = Page::new(Page::MAX_LIMIT, 0);
+        let liked = self.decay(interactions.liked_since(horizon, all).await?, now);
+        let disliked = self.decay(interactions.disliked_since(horizon, all).await?, now);
+
+        let filter = EntryFilter {
+            unread: true,
+            ..EntryFilter::default()
+        };
+        let candidates = storage
+            .entries()
+            .list(&filter, Page::new(self.max_candidates, 0))
+            .await?
+            .into_iter()
+            .filter(|e| e.state().liked_at().is_none() && e.state().disliked_at().is_none())
+            .collect::<Vec<_>>();
+        let ids = candidates.iter().map(StoredEntry::id).collect::<Vec<_>>();
+        debug!(
+            candidates = ids.len(),
+            liked = liked.len(),
+            disliked = disliked.len(),
+            "recommending"
+        );
+
+        // Estimating takes a reverse push per candidate, so it runs on a snapshot of the graph,
+        // off the async workers and without holding up writers.
+        let transitions = TransitionGraph::from_knn(&*graph.read().await);
+        let recommender = *self;
+        let (closeness_liked, closeness_disliked) = tokio::task::spawn_blocking(move || {
+            let mut rng = rand::rng();
+            (
+                recommender.closeness(&transitions, &liked, &ids, &mut rng),
+                recommender.closeness(&transitions, &disliked, &ids, &mut rng),
+            )
+        })
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+
+        let mut recommendations = candidates
+            .into_iter()
+            .zip(closeness_liked.into_iter().zip(closeness_disliked))
+            .map(|(entry, (liked, disliked))| {
+                let freshness = entry
+                    .entry()
+                    .metadata()
+                    .published_time()
+                    .map(|published| half_lives(now - published, self.freshness_half_life))
+                    .unwrap_or(0.0);
+                let breakdown = ScoreBreakdown {
+                    liked: self.like_weight * liked.ln_1p(),
+                    disliked: self.dislike_weight * disliked.ln_1p(),
+                    freshness: self.freshness_weight * freshness,
+                };
+                Recommendation {
+                    score: breakdown.score(),
+                    breakdown,
+                    entry,
+                }
+            })
+            .collect::<Vec<_>>();
+        recommendations.sort_by(|a, b| b.score.total_cmp(&a.score));
+        recommendations.truncate(limit);
+        Ok(recommendations)
+    }
+
+    /// Weight each interaction by its age.
+    fn decay(
+        &self,
+        interactions: Vec<(i64, DateTime<Utc>)>,
+        now: DateTime<Utc>,
+    ) -> Vec<(i64, f64)> {
+        interactions
+            .into_iter()
+            .map(|(id, at)| (id, half_lives(now - at, self.interaction_half_life)))
+            .collect()
+    }
+
+    /// Closeness of each of `targets` to the weighted `sources`, relative to a uniform
+    /// PageRank, in the order of `targets`.
+    fn closeness(
+        &self,
+        graph: &TransitionGraph,
+        sources: &[(i64, f64)],
+        targets: &[i64],
+        rng: &mut impl Rng,
+    ) -> Vec<f64> {
+        let total = sources
+            .iter()
+            .filter(|(id, _)| graph.contains(*id))
+            .map(|(_, w)| w)
+            .sum::<f64>();
+        if total <= 0.0 {
+            return vec![0.0; targets.len()];
+        }
+        let ranks = self.ppr.estimate_many(graph, sources, targets, rng);
+        let scale = total * graph.len() as f64;
+        targets
+            .iter()
+            .map(|t| ranks.get(t).map(|r| (r * scale).max(0.0)).unwrap_or(0.0))
+            .collect()
+    }
+}
+
+impl Default for Recommender {
+    fn default() -> Self {
+        Self {
+            ppr: FastPpr::default(),
+            interaction_half_life: Self::DEFAULT_INTERACTION_HALF_LIFE,
+            freshness_half_life: Self::DEFAULT_FRESHNESS_HALF_LIFE,
+            like_weight: 1.0,
+            dislike_weight: 1.0,
+            freshness_weight: 1.0,
+            max_candidates: Self::DEFAULT_MAX_CANDIDATES,
+        }
+    }
+}
+
+/// `0.5^(age / half_life)`, with negative ages counting as 0.
+fn half_lives(age: TimeDelta, half_life: TimeDelta) -> f64 {
+    let age = age.num_seconds().max(0) as f64;
+    let half_life = half_life.num_seconds().max(1) as f64;
+    0.5f64.powf(age / half_life)
+}
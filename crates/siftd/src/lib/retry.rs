@@ -0,0 +1,97 @@
+//! Spacing out retries of transient failures.
+
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Exponential backoff with full jitter: retry `n`, counting from 0, waits a uniformly random
+/// time up to `base · 2^n`, capped at `max`, so that clients failing together do not retry
+/// together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    pub retries: u32,
+    pub base: Duration,
+    pub max: Duration,
+}
+
+impl Backoff {
+    pub const DEFAULT_RETRIES: u32 = 3;
+    pub const DEFAULT_BASE: Duration = Duration::from_millis(500);
+    pub const DEFAULT_MAX: Duration = Duration::from_secs(30);
+
+    /// Never retry.
+    pub fn none() -> Self {
+        Self {
+            retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Time to wait before retry `retry`, or `None` once retries are used up.
+    pub fn delay(&self, retry: u32, rng: &mut impl Rng) -> Option<Duration> {
+        if retry >= self.retries {
+            return None;
+        }
+        let ceiling = self
+            .base
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max);
+        Some(ceiling.mul_f64(rng.random::<f64>()))
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            retries: Self::DEFAULT_RETRIES,
+            base: Self::DEFAULT_BASE,
+            max: Self::DEFAULT_MAX,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn delays_grow_up_to_the_cap() {
+        let backoff = Backoff {
+            retries: 40,
+            base: Duration::from_millis(100),
+            max: Duration::from_secs(5),
+        };
+        let mut rng = StdRng::seed_from_u64(19);
+        let mut previous_longest = Duration::ZERO;
+        for retry in 0..40 {
+            let ceiling = Duration::from_millis(100)
+                .saturating_mul(2u32.saturating_pow(retry))
+                .min(backoff.max);
+            let delays = (0..200)
+                .map(|_| backoff.delay(retry, &mut rng).unwrap())
+                .collect::<Vec<_>>();
+            assert!(delays.iter().all(|&d| d <= ceiling), "retry {retry}");
+            let longest = *delays.iter().max().unwrap();
+            // Jitter spreads the delays over the whole range.
+            assert!(longest > ceiling.mul_f64(0.9), "retry {retry}");
+            assert!(*delays.iter().min().unwrap() < ceiling.mul_f64(0.1));
+            if ceiling < backoff.max {
+                assert!(longest > previous_longest, "retry {retry}");
+            }
+            previous_longest = longest;
+        }
+        assert_eq!(backoff.delay(40, &mut rng), None);
+    }
+
+    #[test]
+    fn retries_run_out() {
+        let mut rng = StdRng::seed_from_u64(19);
+        assert_eq!(Backoff::none().delay(0, &mut rng), None);
+        let backoff = Backoff::default();
+        assert!((0..Backoff::DEFAULT_RETRIES).all(|r| backoff.delay(r, &mut rng).is_some()));
+        assert_eq!(backoff.delay(Backoff::DEFAULT_RETRIES, &mut rng), None);
+        assert_eq!(backoff.delay(u32::MAX, &mut rng), None);
+    }
+}
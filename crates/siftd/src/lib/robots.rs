@@ -0,0 +1,417 @@
+//! Honouring `robots.txt`, as specified by RFC 9309, and the non-standard `Crawl-delay`.
+//!
+//! Each origin's `robots.txt` is fetched on first use and cached. [`Content::fetch`] consults the
+//! [`RobotsCache`] of the [`HttpClient`] it is given before every request.
+//!
+//! [`Content::fetch`]: crate::content::Content::fetch
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use reqwest::StatusCode;
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::http::{self, HttpClient};
+
+/// Rules of one `robots.txt` that apply to one user agent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Robots {
+    /// `(allow, pattern)` pairs, in file order.
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// Allows everything, as when a site has no `robots.txt`.
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Allows nothing, as when a site fails to serve its `robots.txt`.
+    pub fn disallow_all() -> Self {
+        Self {
+            rules: vec![(false, "/".to_string())],
+            crawl_delay: None,
+        }
+    }
+
+    /// The rules of `robots.txt` body `text` for `user_agent`, a product token such as `sift`.
+    /// Every group naming the token applies; the `*` group applies only when none does.
+    pub fn parse(text: &str, user_agent: &str) -> Self {
+        let user_agent = user_agent.to_ascii_lowercase();
+        let mut specific = Self::default();
+        let mut fallback = Self::default();
+        let mut matched_specific = false;
+
+        // Agents of the group being read, and whether its rules have started.
+        let mut agents = Vec::<String>::new();
+        let mut in_rules = false;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (key, value) = (key.trim().to_ascii_lowercase(), value.trim());
+            if key == "user-agent" {
+                if in_rules {
+                    agents.clear();
+                    in_rules = false;
+                }
+                agents.push(value.to_ascii_lowercase());
+                continue;
+            }
+            if agents.is_empty() || !matches!(key.as_str(), "allow" | "disallow" | "crawl-delay") {
+                continue;
+            }
+            in_rules = true;
+            let is_specific = agents.contains(&user_agent);
+            let target = if is_specific {
+                matched_specific = true;
+                &mut specific
+            } else if agents.contains(&"*".to_string()) {
+                &mut fallback
+            } else {
+                continue;
+            };
+            if key == "crawl-delay" {
+                let secs = value.parse::<f64>().ok();
+                if let Some(secs) = secs.filter(|s| s.is_finite() && *s >= 0.0) {
+                    // Too long to represent is as good as forever; it is capped when honoured.
+                    let delay = Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX);
+                    target.crawl_delay = Some(delay);
+                }
+            } else if !value.is_empty() {
+                target.rules.push((key == "allow", value.to_string()));
+            }
+        }
+
+        if matched_specific {
+            specific
+        } else {
+            fallback
+        }
+    }
+
+    /// Whether `url` may be fetched. The longest matching pattern decides, allowing on ties.
+    pub fn allowed(&self, url: &Url) -> bool {
+        let path = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+        if path == "/robots.txt" {
+            return true;
+        }
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| matches(pattern, &path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// Whether `path` matches `pattern`, where `*` matches any run of characters and a trailing `$`
+/// anchors the end.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts = parts.collect::<Vec<_>>();
+    for (i, part) in parts.iter().enumerate() {
+        let last = i + 1 == parts.len();
+        if last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+#[derive(Debug)]
+struct Cached {
+    robots: Robots,
+    expires: Instant,
+    /// Earliest time the next fetch from the origin may start, under its crawl delay.
+    next_fetch: Instant,
+}
+
+/// Per-origin cache of [`Robots`] for one user agent.
+#[derive(Debug)]
+pub struct RobotsCache {
+    user_agent: String,
+    ttl: Duration,
+    max_crawl_delay: Duration,
+    origins: Mutex<HashMap<String, Cached>>,
+}
+
+impl RobotsCache {
+    pub const DEFAULT_USER_AGENT: &str = "sift";
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+    /// Crawl delays are capped, so that one site cannot stall fetches from it indefinitely.
+    pub const DEFAULT_MAX_CRAWL_DELAY: Duration = Duration::from_secs(30);
+    /// How long a `robots.txt` the server fails to serve is taken to disallow everything.
+    const ERROR_TTL: Duration = Duration::from_secs(60 * 60);
+    /// Longest `robots.txt` read; the rest is ignored.
+    const MAX_BYTES: usize = 500 * 1024;
+
+    pub fn new(user_agent: impl Into<String>) -> Self {
+        Self {
+            user_agent: user_agent.into(),
+            ttl: Self::DEFAULT_TTL,
+            max_crawl_delay: Self::DEFAULT_MAX_CRAWL_DELAY,
+            origins: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn with_max_crawl_delay(mut self, max_crawl_delay: Duration) -> Self {
+        self.max_crawl_delay = max_crawl_delay;
+        self
+    }
+
+    pub fn user_agent(&self) -> &str {
+        &self.user_agent
+    }
+
+    /// Whether `url` may be fetched. If so, waits out the origin's crawl delay first, and counts
+    /// the fetch against it. Fails if the origin's `robots.txt` cannot be fetched at all, in which
+    /// case nothing may be fetched from it until it can.
+    pub async fn admit(&self, http: &HttpClient, url: &Url) -> Result<bool, reqwest::Error> {
+        let origin = url.origin().ascii_serialization();
+        let cached = {
+            let origins = self.origins.lock().unwrap();
+            origins
+                .get(&origin)
+                .filter(|c| c.expires > Instant::now())
+                .map(|c| c.robots.clone())
+        };
+        let robots = match cached {
+            Some(robots) => robots,
+            None => {
+                let (robots, ttl) = self.fetch(http, url).await?;
+                let mut origins = self.origins.lock().unwrap();
+                let now = Instant::now();
+                let next_fetch = origins.get(&origin).map_or(now, |c| c.next_fetch);
+                origins.insert(
+                    origin.clone(),
+                    Cached {
+                        robots: robots.clone(),
+                        expires: now + ttl,
+                        next_fetch,
+                    },
+                );
+                robots
+            }
+        };
+        if !robots.allowed(url) {
+            debug!(%url, user_agent = %self.user_agent, "disallowed by robots.txt");
+            return Ok(false);
+        }
+
+        if let Some(delay) = robots.crawl_delay() {
+            let delay = delay.min(self.max_crawl_delay);
+            let wait = {
+                let mut origins = self.origins.lock().unwrap();
+                let now = Instant::now();
+                match origins.get_mut(&origin) {
+                    Some(cached) => {
+                        let start = cached.next_fetch.max(now);
+                        cached.next_fetch = start + delay;
+                        start - now
+                    }
+                    None => Duration::ZERO,
+                }
+            };
+            if !wait.is_zero() {
+                debug!(%origin, wait_ms = wait.as_millis() as u64, "waiting out crawl delay");
+                tokio::time::sleep(wait).await;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Fetch the `robots.txt` for `url`'s origin, and how long to keep it. A missing file allows
+    /// everything, and one the server fails to serve disallows everything for a while. Failing
+    /// to reach the server is an error, and is not kept.
+    async fn fetch(
+        &self,
+        http: &HttpClient,
+        url: &Url,
+    ) -> Result<(Robots, Duration), reqwest::Error> {
+        let mut robots_url = url.clone();
+        robots_url.set_path("/robots.txt");
+        robots_url.set_query(None);
+        robots_url.set_fragment(None);
+
+        // An unreachable origin disallows everything, as RFC 9309 requires; the fetch fails
+        // with the reason, and is retried like any other.
+        let mut response = http
+            .client()
+            .get(robots_url.as_str())
+            .send()
+            .await
+            .inspect_err(|e| warn!(url = %robots_url, error = %e, "fetching robots.txt failed"))?;
+        let status = response.status();
+        if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+            debug!(url = %robots_url, %status, "no robots.txt");
+            return Ok((Robots::allow_all(), self.ttl));
+        }
+        if !status.is_success() {
+            warn!(url = %robots_url, %status, "robots.txt unavailable");
+            return Ok((Robots::disallow_all(), Self::ERROR_TTL));
+        }
+        let body = http::read_body(&mut response, Self::MAX_BYTES)
+            .await
+            .inspect_err(|e| warn!(url = %robots_url, error = %e, "reading robots.txt failed"))?;
+        let robots = Robots::parse(&String::from_utf8_lossy(&body.bytes), &self.user_agent);
+        debug!(url = %robots_url, rules = robots.rules.len(), "robots.txt fetched");
+        Ok((robots, self.ttl))
+    }
+}
+
+impl Default for RobotsCache {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_USER_AGENT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "\
+# Comments and unknown lines are skipped.
+Sitemap: https://example.com/sitemap.xml
+
+User-agent: *
+Disallow: /private/
+Crawl-delay: 5
+
+User-agent: OtherBot
+User-agent: Sift
+Disallow: /drafts/
+Allow: /drafts/public
+Crawl-delay: 1.5
+";
+
+    fn allowed(robots: &Robots, path: &str) -> bool {
+        robots.allowed(
+            &Url::parse("https://example.com")
+                .unwrap()
+                .join(path)
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn groups_naming_the_agent_replace_the_fallback() {
+        let sift = Robots::parse(ROBOTS, "sift");
+        assert!(allowed(&sift, "/private/page"));
+        assert!(!allowed(&sift, "/drafts/one"));
+        assert_eq!(sift.crawl_delay(), Some(Duration::from_millis(1500)));
+
+        let other = Robots::parse(ROBOTS, "somebot");
+        assert!(!allowed(&other, "/private/page"));
+        assert!(allowed(&other, "/drafts/one"));
+        assert_eq!(other.crawl_delay(), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn crawl_delays_are_read_whatever_their_size() {
+        let cases = [
+            ("2", Some(Duration::from_secs(2))),
+            ("0.25", Some(Duration::from_millis(250))),
+            ("1e300", Some(Duration::MAX)),
+            ("-1", None),
+            ("inf", None),
+            ("NaN", None),
+            ("soon", None),
+        ];
+        for (value, expected) in cases {
+            let robots = Robots::parse(&format!("User-agent: *\nCrawl-delay: {value}\n"), "sift");
+            assert_eq!(robots.crawl_delay(), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn every_group_naming_the_agent_applies() {
+        let robots = Robots::parse(
+            "User-agent: sift\nDisallow: /a\n\nUser-agent: *\nDisallow: /b\n\n\
+             User-agent: sift\nDisallow: /c\n",
+            "sift",
+        );
+        assert!(!allowed(&robots, "/a"));
+        assert!(allowed(&robots, "/b"));
+        assert!(!allowed(&robots, "/c"));
+    }
+
+    #[test]
+    fn the_longest_match_decides_allowing_on_ties() {
+        let robots = Robots::parse(
+            "User-agent: *\nDisallow: /shop\nAllow: /shop/cart\nDisallow: /same\nAllow: /same\n\
+             Disallow: /*?session=\n",
+            "sift",
+        );
+        let cases = [
+            ("/", true),
+            ("/shop", false),
+            ("/shop/item", false),
+            ("/shop/cart", true),
+            ("/shop/cart/checkout", true),
+            ("/same/page", true),
+            ("/page?session=1", false),
+            ("/page?id=1", true),
+            ("/robots.txt", true),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(allowed(&robots, path), expected, "{path}");
+        }
+        assert!(allowed(&Robots::allow_all(), "/anything"));
+        assert!(!allowed(&Robots::disallow_all(), "/anything"));
+        assert!(allowed(&Robots::disallow_all(), "/robots.txt"));
+    }
+
+    #[test]
+    fn patterns_match_prefixes_wildcards_and_anchored_ends() {
+        let cases = [
+            ("/", "/anything", true),
+            ("/fish", "/fish.html", true),
+            ("/fish", "/Fish", false),
+            ("/fish/", "/fish", false),
+            ("/*.php", "/index.php", true),
+            ("/*.php", "/folder/index.php?x=1", true),
+            ("/*.php$", "/index.php", true),
+            ("/*.php$", "/index.php?x=1", false),
+            ("/fish*", "/fishheads", true),
+            ("/*/edit", "/posts/1/edit", true),
+            ("/*/edit", "/posts/1", false),
+            ("/a*b*c$", "/axbyc", true),
+            ("/a*b*c$", "/axbycd", false),
+            ("/exact$", "/exact", true),
+            ("/exact$", "/exactly", false),
+        ];
+        for (pattern, path, expected) in cases {
+            assert_eq!(matches(pattern, path), expected, "{pattern} on {path}");
+        }
+    }
+}
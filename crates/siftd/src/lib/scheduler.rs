@@ -0,0 +1,287 @@
+//! Deciding when to refresh each feed.
+//!
+//! Posts are modelled as a Poisson process whose rate is learnt per feed: the time between
+//! posts is smoothed with an exponentially weighted moving average, and a feed is polled about
+//! once per expected post, within configured bounds and no more often than the feed asks for.
+
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use tracing::{debug, info, warn};
+
+use crate::{
+    feed::RefreshHints,
+    fetcher::{FeedRefresh, FetchQueue, Priority},
+    storage::{Storage, StorageError},
+};
+
+/// What the scheduler knows about one feed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedSchedule {
+    pub feed_id: i64,
+    /// Smoothed time between posts, in seconds, once two posts have been seen.
+    pub mean_gap_secs: Option<f64>,
+    pub last_post_at: Option<DateTime<Utc>>,
+    pub last_refresh_at: Option<DateTime<Utc>>,
+    pub next_refresh_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Scheduler {
+    pub min_interval: TimeDelta,
+    pub max_interval: TimeDelta,
+    /// Weight of the newest gap in the moving average, between 0 and 1.
+    pub smoothing: f64,
+    /// Expected number of new posts per refresh to aim for.
+    pub posts_per_refresh: f64,
+}
+
+impl Scheduler {
+    pub const DEFAULT_MIN_INTERVAL: TimeDelta = TimeDelta::minutes(15);
+    pub const DEFAULT_MAX_INTERVAL: TimeDelta = TimeDelta::days(1);
+    /// Hinted intervals beyond this are taken to be mistakes.
+    const MAX_HINTED_INTERVAL: TimeDelta = TimeDelta::weeks(1);
+    /// Longest the scheduler sleeps without checking for new feeds.
+    const POLL_INTERVAL: Duration = Duration::from_secs(60);
+    const BATCH: u32 = 64;
+
+    /// Fold a successful refresh at `now` into `previous`, the feed's schedule so far, and pick
+    /// the next refresh. `post_times` holds the publication time of every dated item the feed
+    /// lists; those after the last post seen are new, so that items listed again, whether or not
+    /// they could be fetched, count once. Items dated in the future count once they are not.
+    pub fn update(
+        &self,
+        feed_id: i64,
+        previous: Option<&FeedSchedule>,
+        now: DateTime<Utc>,
+        hints: &RefreshHints,
+        post_times: &[DateTime<Utc>],
+    ) -> FeedSchedule {
+        let mut mean_gap = previous.and_then(|p| p.mean_gap_secs);
+        let mut last_post = previous.and_then(|p| p.last_post_at);
+
+        let mut posts = post_times
+            .iter()
+            .copied()
+            .filter(|t| *t <= now && last_post.is_none_or(|last| *t > last))
+            .collect::<Vec<_>>();
+        posts.sort();
+        for post in &posts {
+            if let Some(last) = last_post {
+                let gap = (*post - last).num_seconds() as f64;
+                if gap > 0.0 {
+                    mean_gap = Some(self.smooth(mean_gap, gap));
+                }
+            }
+            last_post = Some(last_post.map_or(*post, |last| last.max(*post)));
+        }
+
+        // Silence for longer than the usual gap is evidence that posts became rarer. It only
+        // stretches this interval: the silence so far is smoothed in afresh on every refresh
+        // rather than stored, which would count it again each time, and the gap it ends in is
+        // smoothed in once the next post arrives.
+        let mut expected_gap = mean_gap;
+        if let (true, Some(gap), Some(last)) = (posts.is_empty(), mean_gap, last_post) {
+            let silence = (now - last).num_seconds() as f64;
+            if silence > gap {
+                expected_gap = Some(self.smooth(Some(gap), silence));
+            }
+        }
+
+        let next_refresh_at = now + self.interval(expected_gap, hints);
+        debug!(
+            feed_id,
+            new_posts = posts.len(),
+            mean_gap_secs = ?mean_gap,
+            %next_refresh_at,
+            "feed rescheduled"
+        );
+        FeedSchedule {
+            feed_id,
+            mean_gap_secs: mean_gap,
+            last_post_at: last_post,
+            last_refresh_at: Some(now),
+            next_refresh_at,
+        }
+    }
+
+    /// Time until the next refresh of a feed posting every `mean_gap` seconds on average.
+    /// Without an estimate, the geometric mean of the bounds is used.
+    pub fn interval(&self, mean_gap: Option<f64>, hints: &RefreshHints) -> TimeDelta {
+        let (min, max) = (
+            self.min_interval.num_seconds().max(1) as f64,
+            self.max_interval.num_seconds().max(1) as f64,
+        );
+        let secs = mean_gap
+            .map(|gap| gap * self.posts_per_refresh)
+            .unwrap_or_else(|| (min * max).sqrt())
+            .clamp(min, max.max(min));
+        let interval = TimeDelta::seconds(secs as i64);
+        match hints.min_interval() {
+            Some(hinted) => interval.max(hinted.min(Self::MAX_HINTED_INTERVAL)),
+            None => interval,
+        }
+    }
+
+    /// Record a successful refresh of feed `feed_id`, listing items published at `post_times`,
+    /// and store its next refresh time.
+    pub async fn observe(
+        &self,
+        storage: &Storage,
+        feed_id: i64,
+        hints: &RefreshHints,
+        post_times: &[DateTime<Utc>],
+    ) -> Result<FeedSchedule, StorageError> {
+        let store = storage.schedules();
+        let previous = store.get(feed_id).await?;
+        let schedule = self.update(feed_id, previous.as_ref(), Utc::now(), hints, post_times);
+        store.put(&schedule).await?;
+        Ok(schedule)
+    }
+
+    /// Queue refreshes of feeds as they fall due, forever. A queued feed is pushed back by the
+    /// maximum interval, so that it is retried then if the refresh fails; a successful refresh
+    /// reschedules it through [`Self::observe`].
+    pub async fn run(self, storage: Storage, queue: FetchQueue) {
+        info!(
+            min_interval = %self.min_interval,
+            max_interval = %self.max_interval,
+            "scheduler started"
+        );
+        let store = storage.schedules();
+        loop {
+            let now = Utc::now();
+            match store.due(now, Self::BATCH).await {
+                Ok(due) => {
+                    for (feed_id, url) in due {
+                        if let Err(e) = store.postpone(feed_id, now + self.max_interval).await {
+                            warn!(feed_id, error = %e, "postponing feed refresh failed");
+                            continue;
+                        }
+                        debug!(feed_id, %url, "feed due");
+                        queue.push(Box::new(FeedRefresh::new(url)), Priority::NORMAL);
+                    }
+                }
+                Err(e) => warn!(error = %e, "listing due feeds failed"),
+            }
+
+            let sleep = match store.next_due().await {
+                Ok(Some(next)) => (next - Utc::now())
+                    .to_std()
+                    .unwrap_or_default()
+                    .min(Self::POLL_INTERVAL),
+                Ok(None) => Self::POLL_INTERVAL,
+                Err(e) => {
+                    warn!(error = %e, "looking up next due feed failed");
+                    Self::POLL_INTERVAL
+                }
+            };
+            tokio::time::sleep(sleep.max(Duration::from_secs(1))).await;
+        }
+    }
+
+    fn smooth(&self, mean: Option<f64>, sample: f64) -> f64 {
+        match mean {
+            Some(mean) => (1.0 - self.smoothing) * mean + self.smoothing * sample,
+            None => sample,
+        }
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self {
+            min_interval: Self::DEFAULT_MIN_INTERVAL,
+            max_interval: Self::DEFAULT_MAX_INTERVAL,
+            smoothing: 0.3,
+            posts_per_refresh: 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn silence_does_not_ratchet_the_mean_gap() {
+        let scheduler = Scheduler::default();
+        let start = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        let hints = RefreshHints::default();
+        let hour = TimeDelta::hours(1);
+
+        // Posts every hour for a while.
+        let mut schedule = scheduler.update(1, None, start, &hints, &[start]);
+        for i in 1..=5 {
+            let now = start + hour * i;
+            schedule = scheduler.update(1, Some(&schedule), now, &hints, &[now]);
+        }
+        assert_eq!(schedule.mean_gap_secs, Some(3600.0));
+
+        // Then nothing for a few hours, refreshed every 15 minutes.
+        let last_post = start + hour * 5;
+        let mut intervals = Vec::new();
+        for i in 1..=12 {
+            let now = last_post + TimeDelta::minutes(15) * i;
+            schedule = scheduler.update(1, Some(&schedule), now, &hints, &[]);
+            intervals.push(schedule.next_refresh_at - now);
+        }
+        assert_eq!(schedule.mean_gap_secs, Some(3600.0));
+        // Three hours of silence, smoothed in once.
+        assert_eq!(*intervals.last().unwrap(), TimeDelta::seconds(5760));
+        assert!(intervals.windows(2).all(|w| w[0] <= w[1]));
+
+        // The gap the silence ends in is smoothed in when the next post arrives.
+        let now = last_post + hour * 3;
+        schedule = scheduler.update(1, Some(&schedule), now, &hints, &[now]);
+        assert_eq!(
+            schedule.mean_gap_secs,
+            Some(0.7 * 3600.0 + 0.3 * 3.0 * 3600.0)
+        );
+    }
+
+    #[test]
+    fn items_listed_again_count_once() {
+        let scheduler = Scheduler::default();
+        let start = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        let hints = RefreshHints::default();
+        let day = TimeDelta::days(1);
+        let listed = [start - day * 2, start - day];
+
+        let mut schedule = scheduler.update(1, None, start, &hints, &listed);
+        assert_eq!(schedule.mean_gap_secs, Some(86400.0));
+        assert_eq!(schedule.last_post_at, Some(start - day));
+
+        // The same items, say because one of them keeps failing, are not new posts; nor is an
+        // item dated before the last post seen.
+        for i in 1..=8 {
+            let now = start + TimeDelta::minutes(15) * i;
+            let listed = [start - day * 3, start - day * 2, start - day];
+            schedule = scheduler.update(1, Some(&schedule), now, &hints, &listed);
+        }
+        assert_eq!(schedule.mean_gap_secs, Some(86400.0));
+        assert_eq!(schedule.last_post_at, Some(start - day));
+
+        // An item from the future counts once it has been published.
+        let now = start + TimeDelta::hours(3);
+        let listed = [start - day, start + day];
+        schedule = scheduler.update(1, Some(&schedule), now, &hints, &listed);
+        assert_eq!(schedule.last_post_at, Some(start - day));
+        let now = start + day;
+        schedule = scheduler.update(1, Some(&schedule), now, &hints, &listed);
+        assert_eq!(schedule.last_post_at, Some(start + day));
+        assert_eq!(
+            schedule.mean_gap_secs,
+            Some(0.7 * 86400.0 + 0.3 * 2.0 * 86400.0)
+        );
+        schedule = scheduler.update(1, Some(&schedule), now + day, &hints, &listed);
+        assert_eq!(
+            schedule.mean_gap_secs,
+            Some(0.7 * 86400.0 + 0.3 * 2.0 * 86400.0)
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use chrono::Utc;
+use sqlx::SqlitePool;
+use tracing::debug;
+
+use crate::{embedding::Embedding, storage::StorageError};
+
+#[derive(Clone, Debug)]
+pub struct EmbeddingStore {
+    pool: SqlitePool,
+}
+
+impl EmbeddingStore {
+    pub(crate) fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Store the vector of entry `entry_id` under `model_id`, replacing any previous one.
+    pub async fn put(
+        &self,
+        entry_id: i64,
+        model_id: &str,
+        embedding: &Embedding,
+    ) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO embeddings (entry_id, model_id, dimension, vector, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (entry_id, model_id) DO UPDATE SET
+                 dimension = excluded.dimension,
+                 vector = excluded.vector,
+                 created_at = excluded.created_at",
+        )
+        .bind(entry_id)
+        .bind(model_id)
+        .bind(embedding.dimension() as i64)
+        .bind(embedding.to_bytes())
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        debug!(entry_id, model_id, "embedding stored");
+        Ok(())
+    }
+
+    pub async fn get(
+        &self,
+        entry_id: i64,
+        model_id: &str,
+    ) -> Result<Option<Embedding>, StorageError> {
+        let vector: Option<Vec<u8>> = sqlx::query_scalar(
+            "SELECT vector FROM embeddings WHERE entry_id = ?1 AND model_id = ?2",
+        )
+        .bind(entry_id)
+        .bind(model_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        vector.as_deref().map(decode).transpose()
+    }
+
+    /// Every stored vector of `model_id`, by entry id.
+    pub async fn all(&self, model_id: &str) -> Result<Vec<(i64, Embedding)>, StorageError> {
+        let rows: Vec<(i64, Vec<u8>)> = sqlx::query_as(
+            "SELECT entry_id, vector FROM embeddings WHERE model_id = ?1 ORDER BY entry_id",
+        )
+        .bind(model_id)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|(id, v)| Ok((id, decode(&v)?)))
+            .collect()
+    }
+
+    /// Ids and plain text of entries without a vector under `model_id`, oldest first.
+    pub async fn missing(
+        &self,
+        model_id: &str,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<(i64, String)>, StorageError> {
+        let rows = sqlx::query_as(
+            "SELECT e.id, COALESCE(e.text, e.content) FROM entries e
+             WHERE NOT EXISTS (
+                 SELECT 1 FROM embeddings x WHERE x.entry_id = e.id AND x.model_id = ?1
+             )
+             ORDER BY e.id LIMIT ?2 OFFSET ?3",
+        )
+        .bind(model_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
+}
+
+fn decode(bytes: &[u8]) -> Result<Embedding, StorageError> {
+    Embedding::from_bytes(bytes).ok_or_else(|| StorageError::Decode {
+        table: "embeddings",
+        reason: format!("vector of {} bytes is not a list of f32", bytes.len()),
+    })
+}
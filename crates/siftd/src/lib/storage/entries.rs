@@ -0,0 +1,443 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use tracing::debug;
+use url::Url;
+
+use crate::{
+    canonical,
+    dedup::{Fingerprint, NearDuplicates},
+    entry::Entry,
+    interaction::InteractionState,
+    metadata::Metadata,
+    storage::{Page, StorageError},
+};
+
+#[derive(Clone, Debug)]
+pub struct EntryStore {
+    pool: SqlitePool,
+    near_duplicates: NearDuplicates,
+}
+
+/// An entry together with the id storage assigned to it.
+#[derive(Debug, Serialize)]
+pub struct StoredEntry {
+    id: i64,
+    /// Identity key of the entry, see `canonical::canonicalise`.
+    canonical_url: Url,
+    /// The entry this one is a near-duplicate of.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicate_of: Option<i64>,
+    /// What the user has done with the entry.
+    #[serde(skip_serializing_if = "InteractionState::is_empty")]
+    state: InteractionState,
+    #[serde(flatten)]
+    entry: Entry,
+}
+
+impl StoredEntry {
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    pub fn canonical_url(&self) -> &Url {
+        &self.canonical_url
+    }
+
+    pub fn duplicate_of(&self) -> Option<i64> {
+        self.duplicate_of
+    }
+
+    pub fn state(&self) -> &InteractionState {
+        &self.state
+    }
+
+    pub fn entry(&self) -> &Entry {
+        &self.entry
+    }
+
+    pub fn into_entry(self) -> Entry {
+        self.entry
+    }
+}
+
+/// Restrictions on which entries to list. Unset fields do not restrict anything, except that
+/// near-duplicates are left out unless `include_duplicates` is set or `duplicate_of` is given.
+#[derive(Debug, Clone, Default)]
+pub struct EntryFilter {
+    pub include_duplicates: bool,
+    pub duplicate_of: Option<i64>,
+    pub origin: Option<String>,
+    pub author: Option<String>,
+    pub published_after: Option<DateTime<Utc>>,
+    pub published_before: Option<DateTime<Utc>>,
+    pub unread: bool,
+    pub bookmarked: bool,
+    pub liked_since: Option<DateTime<Utc>>,
+    pub disliked_since: Option<DateTime<Utc>>,
+}
+
+#[derive(sqlx::FromRow)]
+struct EntryRow {
+    id: i64,
+    url: String,
+    canonical_url: String,
+    duplicate_of: Option<i64>,
+    /// JSON array of every URL the entry was observed under.
+    aliases: String,
+    title: String,
+    origin: String,
+    author: String,
+    content: String,
+    text: String,
+    summary: Option<String>,
+    thumbnail_url: Option<String>,
+    published_time: Option<DateTime<Utc>>,
+    updated_time: Option<DateTime<Utc>>,
+    read_at: Option<DateTime<Utc>>,
+    bookmarked_at: Option<DateTime<Utc>>,
+    liked_at: Option<DateTime<Utc>>,
+    disliked_at: Option<DateTime<Utc>>,
+}
+
+const SELECT_ENTRY: &str = "SELECT e.id, e.url, e.canonical_url, e.duplicate_of,
+        (SELECT json_group_array(a.url) FROM entry_aliases a WHERE a.entry_id = e.id) AS aliases,
+        e.title, e.origin, e.author, e.content, COALESCE(e.text, e.content) AS text,
+        m.summary, m.thumbnail_url, m.published_time, m.updated_time,
+        s.read_at, s.bookmarked_at, s.liked_at, s.disliked_at
+    FROM entries e
+    LEFT JOIN entry_metadata m ON m.entry_id = e.id
+    LEFT JOIN entry_state s ON s.entry_id = e.id";
+
+impl EntryStore {
+    pub(crate) fn new(pool: SqlitePool, near_duplicates: NearDuplicates) -> Self {
+        Self {
+            pool,
+            near_duplicates,
+        }
+    }
+
+    /// Insert the entry, or overwrite the stored one it is a duplicate of, _i.e._ one that any of
+    /// its URLs, once canonicalised, has been seen under. Metadata fields missing from `entry`
+    /// keep their stored value. Returns the entry as stored, with an id that is stable
+    /// across upserts.
+    pub async fn upsert(
+        &self,
+        entry: &Entry,
+        feed_id: Option<i64>,
+    ) -> Result<StoredEntry, StorageError> {
+        let now = Utc::now();
+        // Take the write lock up front: a deferred transaction that reads first cannot wait for
+        // a concurrent writer when upgrading, and fails with "database is locked" instead.
+        let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+
+        // Every URL the entry was observed under, and the identity keys they map to. A match on
+        // any of them means we have seen this entry before.
+        let observed = std::iter::once(entry.url())
+            .chain(entry.canonical_url())
+            .chain(entry.aliases())
+            .collect::<Vec<_>>();
+        let keys = observed
+            .iter()
+            .map(|u| canonical::canonicalise(u).to_string())
+            .collect::<Vec<_>>();
+        let canonical_url =
+            canonical::canonicalise(entry.canonical_url().unwrap_or(entry.url())).to_string();
+
+        let mut lookup = QueryBuilder::<Sqlite>::new(
+            "SELECT entry_id FROM entry_aliases WHERE canonical_url IN (",
+        );
+        let mut keys_list = lookup.separated(", ");
+        for key in &keys {
+            keys_list.push_bind(key);
+        }
+        lookup.push(") OR url IN (");
+        let mut urls_list = lookup.separated(", ");
+        for url in &observed {
+            urls_list.push_bind(url.as_str());
+        }
+        lookup.push(") ORDER BY entry_id LIMIT 1");
+        let existing: Option<i64> = lookup.build_query_scalar().fetch_optional(&mut *tx).await?;
+
+        // The URL and identity key of an existing entry are left alone: the entry may be reached
+        // through other aliases, and rewriting them could collide with another entry.
+        let id: i64 = match existing {
+            Some(id) => {
+                sqlx::query(
+                    "UPDATE entries SET
+                         feed_id = COALESCE(?2, feed_id),
+                         title = ?3,
+                         origin = ?4,
+                         author = ?5,
+                         content = ?6,
+                         text = ?7,
+                         updated_at = ?8
+                     WHERE id = ?1",
+                )
+                .bind(id)
+                .bind(feed_id)
+                .bind(entry.title())
+                .bind(entry.origin())
+                .bind(entry.author())
+                .bind(entry.content())
+                .bind(entry.text())
+                .bind(now)
+                .execute(&mut *tx)
+                .await?;
+                debug!(entry_id = id, "matched existing entry");
+                id
+            }
+            None => {
+                sqlx::query_scalar(
+                    "INSERT INTO entries
+                         (url, canonical_url, feed_id, title, origin, author, content, text, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)
+                     RETURNING id",
+                )
+                .bind(entry.url().as_str())
+                .bind(&canonical_url)
+                .bind(feed_id)
+                .bind(entry.title())
+                .bind(entry.origin())
+                .bind(entry.author())
+                .bind(entry.content())
+                .bind(entry.text())
+                .bind(now)
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+
+        for (url, key) in observed.iter().zip(&keys) {
+            sqlx::query(
+                "INSERT OR IGNORE INTO entry_aliases (url, canonical_url, entry_id, first_seen)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )
+            .bind(url.as_str())
+            .bind(key)
+            .bind(id)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let metadata = entry.metadata();
+        sqlx::query(
+            "INSERT INTO entry_metadata (entry_id, summary, thumbnail_url, published_time, updated_time)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (entry_id) DO UPDATE SET
+                 summary = COALESCE(excluded.summary, entry_metadata.summary),
+                 thumbnail_url = COALESCE(excluded.thumbnail_url, entry_metadata.thumbnail_url),
+                 published_time = COALESCE(excluded.published_time, entry_metadata.published_time),
+                 updated_time = COALESCE(excluded.updated_time, entry_metadata.updated_time)",
+        )
+        .bind(id)
+        .bind(metadata.summary())
+        .bind(metadata.thumbnail_url().map(Url::as_str))
+        .bind(metadata.published_time())
+        .bind(metadata.updated_time())
+        .execute(&mut *tx)
+        .await?;
+
+        self.link_near_duplicate(&mut tx, id, entry.text()).await?;
+
+        tx.commit().await?;
+        debug!(entry_id = id, "entry upserted");
+        self.get(id)
+            .await?
+            .ok_or(StorageError::Query(sqlx::Error::RowNotFound))
+    }
+
+    /// Fingerprint the plain text of entry `id` and mark it as a near-duplicate of the closest
+    /// entry within the configured distance, if any. Only entries that are not duplicates
+    /// themselves are considered, so every group of duplicates has a single primary: the entry
+    /// seen first. Candidates are looked up by fingerprint band where the distance allows it,
+    /// and only compared with every fingerprint where it does not.
+    async fn link_near_duplicate(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Sqlite>,
+        id: i64,
+        content: &str,
+    ) -> Result<(), StorageError> {
+        let fingerprint = self.near_duplicates.fingerprint(content);
+        let bands = fingerprint.map(Fingerprint::bands);
+        let duplicate_of = match fingerprint {
+            Some(fingerprint) => {
+                let mut query = QueryBuilder::<Sqlite>::new(
+                    "SELECT id, fingerprint FROM entries
+                     WHERE fingerprint IS NOT NULL AND duplicate_of IS NULL AND id < ",
+                );
+                query.push_bind(id);
+                if self.near_duplicates.banded() {
+                    query.push(" AND (");
+                    for (i, band) in fingerprint.bands().into_iter().enumerate() {
+                        if i > 0 {
+                            query.push(" OR ");
+                        }
+                        query.push(format_args!("fingerprint_band{i} = "));
+                        query.push_bind(band);
+                    }
+                    query.push(")");
+                }
+                let candidates: Vec<(i64, i64)> =
+                    query.build_query_as().fetch_all(&mut **tx).await?;
+                candidates
+                    .into_iter()
+                    .map(|(other, fp)| (other, Fingerprint::from_i64(fp)))
+                    .filter(|(_, fp)| self.near_duplicates.is_duplicate(&fingerprint, fp))
+                    .min_by_key(|(other, fp)| (fingerprint.distance(fp), *other))
+                    .map(|(other, _)| other)
+            }
+            None => None,
+        };
+
+        sqlx::query(
+            "UPDATE entries SET fingerprint = ?2, duplicate_of = ?3, fingerprint_band0 = ?4,
+                 fingerprint_band1 = ?5, fingerprint_band2 = ?6, fingerprint_band3 = ?7
+             WHERE id = ?1",
+        )
+        .bind(id)
+        .bind(fingerprint.map(Fingerprint::to_i64))
+        .bind(duplicate_of)
+        .bind(bands.map(|b| b[0]))
+        .bind(bands.map(|b| b[1]))
+        .bind(bands.map(|b| b[2]))
+        .bind(bands.map(|b| b[3]))
+        .execute(&mut **tx)
+        .await?;
+
+        if let Some(primary) = duplicate_of {
+            debug!(
+                entry_id = id,
+                duplicate_of = primary,
+                "near-duplicate found"
+            );
+            // Anything collapsed into this entry so far now belongs to its primary instead.
+            sqlx::query("UPDATE entries SET duplicate_of = ?2 WHERE duplicate_of = ?1")
+                .bind(id)
+                .bind(primary)
+                .execute(&mut **tx)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Id of the entry `url` has been seen under, directly or once canonicalised.
+    pub async fn find_by_url(&self, url: &Url) -> Result<Option<i64>, StorageError> {
+        let id = sqlx::query_scalar(
+            "SELECT entry_id FROM entry_aliases WHERE url = ?1 OR canonical_url = ?2
+             ORDER BY entry_id LIMIT 1",
+        )
+        .bind(url.as_str())
+        .bind(canonical::canonicalise(url).to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    pub async fn get(&self, id: i64) -> Result<Option<StoredEntry>, StorageError> {
+        let row: Option<EntryRow> = sqlx::query_as(&format!("{SELECT_ENTRY} WHERE e.id = ?1"))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(EntryRow::into_stored).transpose()
+    }
+
+    /// List entries matching `filter`, most recently created first.
+    pub async fn list(
+        &self,
+        filter: &EntryFilter,
+        page: Page,
+    ) -> Result<Vec<StoredEntry>, StorageError> {
+        let mut query = QueryBuilder::<Sqlite>::new(SELECT_ENTRY);
+        query.push(" WHERE 1 = 1");
+        if let Some(primary) = filter.duplicate_of {
+            query.push(" AND e.duplicate_of = ").push_bind(primary);
+        } else if !filter.include_duplicates {
+            query.push(" AND e.duplicate_of IS NULL");
+        }
+        if let Some(origin) = &filter.origin {
+            query.push(" AND e.origin = ").push_bind(origin);
+        }
+        if let Some(author) = &filter.author {
+            query.push(" AND e.author = ").push_bind(author);
+        }
+        if let Some(after) = filter.published_after {
+            query.push(" AND m.published_time >= ").push_bind(after);
+        }
+        if let Some(before) = filter.published_before {
+            query.push(" AND m.published_time < ").push_bind(before);
+        }
+        if filter.unread {
+            query.push(" AND s.read_at IS NULL");
+        }
+        if filter.bookmarked {
+            query.push(" AND s.bookmarked_at IS NOT NULL");
+        }
+        if let Some(since) = filter.liked_since {
+            query.push(" AND s.liked_at >= ").push_bind(since);
+        }
+        if let Some(since) = filter.disliked_since {
+            query.push(" AND s.disliked_at >= ").push_bind(since);
+        }
+        query
+            .push(" ORDER BY e.id DESC LIMIT ")
+            .push_bind(page.limit())
+            .push(" OFFSET ")
+            .push_bind(page.offset());
+
+        let rows: Vec<EntryRow> = query.build_query_as().fetch_all(&self.pool).await?;
+        rows.into_iter().map(EntryRow::into_stored).collect()
+    }
+}
+
+impl EntryRow {
+    fn into_stored(self) -> Result<StoredEntry, StorageError> {
+        let url = parse_url(&self.url)?;
+        let canonical_url = parse_url(&self.canonical_url)?;
+        let aliases = serde_json::from_str::<Vec<String>>(&self.aliases)
+            .map_err(|e| StorageError::Decode {
+                table: "entry_aliases",
+                reason: e.to_string(),
+            })?
+            .iter()
+            .map(|u| parse_url(u))
+            .collect::<Result<Vec<_>, _>>()?;
+        let thumbnail_url = self.thumbnail_url.as_deref().map(parse_url).transpose()?;
+        let metadata = Metadata::new(
+            self.summary,
+            self.published_time,
+            self.updated_time,
+            thumbnail_url,
+        );
+        let entry = Entry::new(
+            self.title,
+            self.origin,
+            self.author,
+            url,
+            self.content,
+            Some(metadata),
+        )
+        .with_text(self.text)
+        .with_aliases(aliases);
+        Ok(StoredEntry {
+            id: self.id,
+            canonical_url,
+            duplicate_of: self.duplicate_of,
+            state: InteractionState::new(
+                self.read_at,
+                self.bookmarked_at,
+                self.liked_at,
+                self.disliked_at,
+            ),
+            entry,
+        })
+    }
+}
+
+fn parse_url(s: &str) -> Result<Url, StorageError> {
+    Url::parse(s).map_err(|e| StorageError::Decode {
+        table: "entries",
+        reason: format!("invalid URL {s:?}: {e}"),
+    })
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tracing::debug;
+use url::Url;
+
+use crate::{content::ContentError, storage::StorageError};
+
+#[derive(Clone, Debug)]
+pub struct FailureStore {
+    pool: SqlitePool,
+}
+
+/// The last permanent failure to fetch a URL.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct FetchFailure {
+    pub url: String,
+    pub class: String,
+    pub status: Option<u16>,
+    pub message: String,
+    /// Failures in a row, counting this one.
+    pub failures: i64,
+    pub first_failed_at: DateTime<Utc>,
+    pub last_failed_at: DateTime<Utc>,
+}
+
+impl FailureStore {
+    pub(crate) fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Record that fetching the URL of `error` failed with it.
+    pub async fn record(&self, error: &ContentError) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO fetch_failures
+                 (url, class, status, message, first_failed_at, last_failed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+             ON CONFLICT (url) DO UPDATE SET
+                 class = excluded.class,
+                 status = excluded.status,
+                 message = excluded.message,
+                 failures = failures + 1,
+                 last_failed_at = excluded.last_failed_at",
+        )
+        .bind(error.url())
+        .bind(error.class().as_str())
+        .bind(error.status().map(|s| s.as_u16()))
+        .bind(error.to_string())
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        debug!(url = error.url(), class = %error.class(), "fetch failure recorded");
+        Ok(())
+    }
+
+    pub async fn get(&self, url: &Url) -> Result<Option<FetchFailure>, StorageError> {
+        let row = sqlx::query_as(
+            "SELECT url, class, status, message, failures, first_failed_at, last_failed_at
+             FROM fetch_failures WHERE url = ?1",
+        )
+        .bind(url.as_str())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row)
+    }
+
+    /// Forget the failures of `url`, once it has been fetched after all.
+    pub async fn clear(&self, url: &Url) -> Result<(), StorageError> {
+        sqlx::query("DELETE FROM fetch_failures WHERE url = ?1")
+            .bind(url.as_str())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
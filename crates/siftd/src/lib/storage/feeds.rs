@@ -0,0 +1,181 @@
+use chrono::{TimeDelta, Utc};
+use sqlx::SqlitePool;
+use tracing::debug;
+use url::Url;
+
+use crate::{
+    content::Validators,
+    feed::{Feed, RefreshHints},
+    storage::StorageError,
+};
+
+#[derive(Clone, Debug)]
+pub struct FeedStore {
+    pool: SqlitePool,
+}
+
+/// A stored feed, as far as fetching it is concerned.
+#[derive(Debug, Clone)]
+pub struct KnownFeed {
+    pub id: i64,
+    /// Validators of the last response, for a conditional refresh.
+    pub validators: Validators,
+    pub ignore_robots: bool,
+    /// Hints of the last response with a body.
+    pub refresh_hints: RefreshHints,
+}
+
+#[derive(sqlx::FromRow)]
+struct KnownFeedRow {
+    id: i64,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    ignore_robots: bool,
+    ttl_secs: Option<i64>,
+    update_period_secs: Option<i64>,
+    max_age_secs: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct FeedRow {
+    url: String,
+    title: String,
+    site_link: Option<String>,
+}
+
+impl FeedStore {
+    pub(crate) fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Insert the feed, or update its title and site link if its URL is already known. Items are
+    /// not stored here; they become entries once fetched. Returns the feed id.
+    pub async fn upsert(&self, feed: &Feed) -> Result<i64, StorageError> {
+        let now = Utc::now();
+        let id: i64 = sqlx::query_scalar(
+            "INSERT INTO feeds (url, title, site_link, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?4)
+             ON CONFLICT (url) DO UPDATE SET
+                 title = excluded.title,
+                 site_link = excluded.site_link,
+                 updated_at = excluded.updated_at
+             RETURNING id",
+        )
+        .bind(feed.url().as_str())
+        .bind(feed.title())
+        .bind(feed.site_link().map(Url::as_str))
+        .bind(now)
+        .fetch_one(&self.pool)
+        .await?;
+        debug!(feed_id = id, "feed upserted");
+        Ok(id)
+    }
+
+    /// Look up a feed by id. The returned feed has no items.
+    pub async fn get(&self, id: i64) -> Result<Option<Feed>, StorageError> {
+        let row: Option<FeedRow> =
+            sqlx::query_as("SELECT url, title, site_link FROM feeds WHERE id = ?1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+        row.map(FeedRow::into_feed).transpose()
+    }
+
+    /// Id and URL of every feed, oldest first.
+    pub async fn all(&self) -> Result<Vec<(i64, Url)>, StorageError> {
+        let rows: Vec<(i64, String)> = sqlx::query_as("SELECT id, url FROM feeds ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.into_iter()
+            .map(|(id, url)| Ok((id, parse_url(&url)?)))
+            .collect()
+    }
+
+    /// What is known about fetching the feed at `url`, if the feed is stored.
+    pub async fn find_by_url(&self, url: &Url) -> Result<Option<KnownFeed>, StorageError> {
+        let row: Option<KnownFeedRow> = sqlx::query_as(
+            "SELECT id, etag, last_modified, ignore_robots, ttl_secs, update_period_secs,
+                    max_age_secs
+             FROM feeds WHERE url = ?1",
+        )
+        .bind(url.as_str())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|row| KnownFeed {
+            id: row.id,
+            validators: Validators {
+                etag: row.etag,
+                last_modified: row.last_modified,
+            },
+            ignore_robots: row.ignore_robots,
+            refresh_hints: RefreshHints {
+                ttl: row.ttl_secs.map(TimeDelta::seconds),
+                update_period: row.update_period_secs.map(TimeDelta::seconds),
+                max_age: row.max_age_secs.map(TimeDelta::seconds),
+            },
+        }))
+    }
+
+    /// Remember the validators of the latest response for feed `id`.
+    pub async fn set_validators(
+        &self,
+        id: i64,
+        validators: &Validators,
+    ) -> Result<(), StorageError> {
+        sqlx::query("UPDATE feeds SET etag = ?2, last_modified = ?3 WHERE id = ?1")
+            .bind(id)
+            .bind(validators.etag.as_deref())
+            .bind(validators.last_modified.as_deref())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Remember the refresh hints of the latest response with a body for feed `id`.
+    pub async fn set_refresh_hints(
+        &self,
+        id: i64,
+        hints: &RefreshHints,
+    ) -> Result<(), StorageError> {
+        sqlx::query(
+            "UPDATE feeds SET ttl_secs = ?2, update_period_secs = ?3, max_age_secs = ?4
+             WHERE id = ?1",
+        )
+        .bind(id)
+        .bind(hints.ttl.map(|t| t.num_seconds()))
+        .bind(hints.update_period.map(|t| t.num_seconds()))
+        .bind(hints.max_age.map(|t| t.num_seconds()))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Opt feed `id` out of, or back into, `robots.txt` checks.
+    pub async fn set_ignore_robots(
+        &self,
+        id: i64,
+        ignore_robots: bool,
+    ) -> Result<(), StorageError> {
+        sqlx::query("UPDATE feeds SET ignore_robots = ?2 WHERE id = ?1")
+            .bind(id)
+            .bind(ignore_robots)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+impl FeedRow {
+    fn into_feed(self) -> Result<Feed, StorageError> {
+        let url = parse_url(&self.url)?;
+        let site_link = self.site_link.as_deref().map(parse_url).transpose()?;
+        Ok(Feed::new(url, self.title, site_link, Vec::new()))
+    }
+}
+
+fn parse_url(s: &str) -> Result<Url, StorageError> {
+    Url::parse(s).map_err(|e| StorageError::Decode {
+        table: "feeds",
+        reason: format!("invalid URL {s:?}: {e}"),
+    })
+}
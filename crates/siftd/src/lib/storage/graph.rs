@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use sqlx::SqlitePool;
+use tracing::debug;
+
+use crate::{graph::Neighbour, storage::StorageError};
+
+#[derive(Clone, Debug)]
+pub struct GraphStore {
+    pool: SqlitePool,
+}
+
+impl GraphStore {
+    pub(crate) fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// The k the graph of `model_id` was built with, if it was.
+    pub async fn k(&self, model_id: &str) -> Result<Option<usize>, StorageError> {
+        let k: Option<i64> = sqlx::query_scalar("SELECT k FROM knn_graphs WHERE model_id = ?1")
+            .bind(model_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(k.map(|k| k as usize))
+    }
+
+    /// Drop the graph of `model_id` and start an empty one with `k` neighbours per entry.
+    pub async fn reset(&self, model_id: &str, k: usize) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM knn_graphs WHERE model_id = ?1")
+            .bind(model_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("INSERT INTO knn_graphs (model_id, k) VALUES (?1, ?2)")
+            .bind(model_id)
+            .bind(k as i64)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        debug!(model_id, k, "similarity graph reset");
+        Ok(())
+    }
+
+    /// Replace the nearest-neighbour lists of the given entries.
+    pub async fn put(
+        &self,
+        model_id: &str,
+        lists: &[(i64, Vec<Neighbour>)],
+    ) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await?;
+        for (entry_id, list) in lists {
+            sqlx::query("DELETE FROM knn_neighbours WHERE model_id = ?1 AND entry_id = ?2")
+                .bind(model_id)
+                .bind(entry_id)
+                .execute(&mut *tx)
+                .await?;
+            for n in list {
+                sqlx::query(
+                    "INSERT INTO knn_neighbours (model_id, entry_id, neighbour_id, similarity)
+                     VALUES (?1, ?2, ?3, ?4)",
+                )
+                .bind(model_id)
+                .bind(entry_id)
+                .bind(n.id)
+                .bind(n.similarity)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+        tx.commit().await?;
+        debug!(
+            model_id,
+            lists = lists.len(),
+            "nearest-neighbour lists stored"
+        );
+        Ok(())
+    }
+
+    /// Every stored nearest-neighbour list of `model_id`, by entry id.
+    pub async fn all(&self, model_id: &str) -> Result<HashMap<i64, Vec<Neighbour>>, StorageError> {
+        let rows: Vec<(i64, i64, f32)> = sqlx::query_as(
+            "SELECT entry_id, neighbour_id, similarity FROM knn_neighbours WHERE model_id = ?1",
+        )
+        .bind(model_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut lists = HashMap::<i64, Vec<Neighbour>>::new();
+        for (entry_id, id, similarity) in rows {
+            lists
+                .entry(entry_id)
+                .or_default()
+                .push(Neighbour { id, similarity });
+        }
+        Ok(lists)
+    }
+}
@@ -0,0 +1,186 @@
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use tracing::debug;
+
+use crate::{
+    interaction::{Interaction, InteractionEvent, InteractionState},
+    storage::{Page, StorageError},
+};
+
+#[derive(Clone, Debug)]
+pub struct InteractionStore {
+    pool: SqlitePool,
+}
+
+#[derive(sqlx::FromRow)]
+struct StateRow {
+    read_at: Option<DateTime<Utc>>,
+    bookmarked_at: Option<DateTime<Utc>>,
+    liked_at: Option<DateTime<Utc>>,
+    disliked_at: Option<DateTime<Utc>>,
+}
+
+impl InteractionStore {
+    pub(crate) fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Record that `interaction` was done to entry `entry_id` at `at`, or undone if `active` is
+    /// false, and return the resulting state. Doing an interaction the entry is already in keeps
+    /// the time it first entered it. Returns `None` if there is no such entry.
+    pub async fn record(
+        &self,
+        entry_id: i64,
+        interaction: Interaction,
+        active: bool,
+        at: DateTime<Utc>,
+    ) -> Result<Option<InteractionState>, StorageError> {
+        let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+        let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM entries WHERE id = ?1")
+            .bind(entry_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if exists.is_none() {
+            return Ok(None);
+        }
+
+        sqlx::query(
+            "INSERT INTO interactions (entry_id, kind, active, occurred_at)
+             VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(entry_id)
+        .bind(interaction.as_str())
+        .bind(active)
+        .bind(at)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("INSERT OR IGNORE INTO entry_state (entry_id) VALUES (?1)")
+            .bind(entry_id)
+            .execute(&mut *tx)
+            .await?;
+        let column = column(interaction);
+        let update = match (active, interaction.opposite()) {
+            (true, Some(opposite)) => format!(
+                "UPDATE entry_state SET {column} = COALESCE({column}, ?2), {} = NULL
+                 WHERE entry_id = ?1",
+                self::column(opposite)
+            ),
+            (true, None) => format!(
+                "UPDATE entry_state SET {column} = COALESCE({column}, ?2) WHERE entry_id = ?1"
+            ),
+            (false, _) => format!("UPDATE entry_state SET {column} = NULL WHERE entry_id = ?1"),
+        };
+        sqlx::query(&update)
+            .bind(entry_id)
+            .bind(at)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        debug!(
+            entry_id,
+            interaction = interaction.as_str(),
+            active,
+            "interaction recorded"
+        );
+        self.state(entry_id).await.map(Some)
+    }
+
+    /// Current state of entry `entry_id`; empty if nothing was ever done to it.
+    pub async fn state(&self, entry_id: i64) -> Result<InteractionState, StorageError> {
+        let row: Option<StateRow> = sqlx::query_as(
+            "SELECT read_at, bookmarked_at, liked_at, disliked_at
+             FROM entry_state WHERE entry_id = ?1",
+        )
+        .bind(entry_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(StateRow::into_state).unwrap_or_default())
+    }
+
+    /// Every interaction with entry `entry_id`, oldest first.
+    pub async fn history(&self, entry_id: i64) -> Result<Vec<InteractionEvent>, StorageError> {
+        let rows: Vec<(String, bool, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT kind, active, occurred_at FROM interactions
+             WHERE entry_id = ?1 ORDER BY id",
+        )
+        .bind(entry_id)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|(kind, active, occurred_at)| {
+                let interaction =
+                    Interaction::parse(&kind).ok_or_else(|| StorageError::Decode {
+                        table: "interactions",
+                        reason: format!("unknown interaction {kind:?}"),
+                    })?;
+                Ok(InteractionEvent {
+                    interaction,
+                    active,
+                    occurred_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Ids of entries currently liked, and when they were, liked at or after `since`, most
+    /// recent first.
+    pub async fn liked_since(
+        &self,
+        since: DateTime<Utc>,
+        page: Page,
+    ) -> Result<Vec<(i64, DateTime<Utc>)>, StorageError> {
+        self.since(Interaction::Like, since, page).await
+    }
+
+    /// Ids of entries currently disliked, and when they were, disliked at or after `since`, most
+    /// recent first.
+    pub async fn disliked_since(
+        &self,
+        since: DateTime<Utc>,
+        page: Page,
+    ) -> Result<Vec<(i64, DateTime<Utc>)>, StorageError> {
+        self.since(Interaction::Dislike, since, page).await
+    }
+
+    async fn since(
+        &self,
+        interaction: Interaction,
+        since: DateTime<Utc>,
+        page: Page,
+    ) -> Result<Vec<(i64, DateTime<Utc>)>, StorageError> {
+        let column = column(interaction);
+        let rows = sqlx::query_as(&format!(
+            "SELECT entry_id, {column} FROM entry_state
+             WHERE {column} >= ?1 ORDER BY {column} DESC LIMIT ?2 OFFSET ?3"
+        ))
+        .bind(since)
+        .bind(page.limit())
+        .bind(page.offset())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows)
+    }
+}
+
+impl StateRow {
+    fn into_state(self) -> InteractionState {
+        InteractionState::new(
+            self.read_at,
+            self.bookmarked_at,
+            self.liked_at,
+            self.disliked_at,
+        )
+    }
+}
+
+/// Column of `entry_state` recording `interaction`.
+pub(crate) fn column(interaction: Interaction) -> &'static str {
+    match interaction {
+        Interaction::Read => "read_at",
+        Interaction::Bookmark => "bookmarked_at",
+        Interaction::Like => "liked_at",
+        Interaction::Dislike => "disliked_at",
+    }
+}
@@ -0,0 +1,142 @@
+use std::str::FromStr;
+
+use sqlx::{
+    migrate::MigrateError,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    SqlitePool,
+};
+use thiserror::Error;
+use tracing::info;
+
+use crate::dedup::NearDuplicates;
+
+pub use crate::storage::{
+    embeddings::EmbeddingStore,
+    entries::{EntryFilter, EntryStore, StoredEntry},
+    failures::{FailureStore, FetchFailure},
+    feeds::{FeedStore, KnownFeed},
+    graph::GraphStore,
+    interactions::InteractionStore,
+    schedules::ScheduleStore,
+};
+
+mod embeddings;
+mod entries;
+mod failures;
+mod feeds;
+mod graph;
+mod interactions;
+mod schedules;
+
+/// Handle to the SQLite database holding feeds and entries. Cheap to clone.
+#[derive(Clone, Debug)]
+pub struct Storage {
+    pool: SqlitePool,
+    near_duplicates: NearDuplicates,
+}
+
+impl Storage {
+    /// Open (creating if necessary) the database at `url` and bring its schema up to date.
+    pub async fn connect(url: &str) -> Result<Self, StorageError> {
+        let options = SqliteConnectOptions::from_str(url)
+            .map_err(StorageError::Connect)?
+            .create_if_missing(true)
+            .foreign_keys(true)
+            .journal_mode(SqliteJournalMode::Wal);
+
+        // Every connection to an in-memory database gets its own database, so stick to one.
+        let max_connections = if url.contains(":memory:") { 1 } else { 8 };
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await
+            .map_err(StorageError::Connect)?;
+
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .map_err(StorageError::Migrate)?;
+        info!(%url, "storage ready");
+
+        Ok(Self {
+            pool,
+            near_duplicates: NearDuplicates::default(),
+        })
+    }
+
+    /// Use `near_duplicates` to decide which entries to collapse into one another.
+    pub fn with_near_duplicates(mut self, near_duplicates: NearDuplicates) -> Self {
+        self.near_duplicates = near_duplicates;
+        self
+    }
+
+    pub fn entries(&self) -> EntryStore {
+        EntryStore::new(self.pool.clone(), self.near_duplicates)
+    }
+
+    pub fn embeddings(&self) -> EmbeddingStore {
+        EmbeddingStore::new(self.pool.clone())
+    }
+
+    pub fn failures(&self) -> FailureStore {
+        FailureStore::new(self.pool.clone())
+    }
+
+    pub fn feeds(&self) -> FeedStore {
+        FeedStore::new(self.pool.clone())
+    }
+
+    pub fn graph(&self) -> GraphStore {
+        GraphStore::new(self.pool.clone())
+    }
+
+    pub fn interactions(&self) -> InteractionStore {
+        InteractionStore::new(self.pool.clone())
+    }
+
+    pub fn schedules(&self) -> ScheduleStore {
+        ScheduleStore::new(self.pool.clone())
+    }
+}
+
+/// Pagination parameters. The limit is capped at `Page::MAX_LIMIT`.
+#[derive(Debug, Clone, Copy)]
+pub struct Page {
+    limit: u32,
+    offset: u32,
+}
+
+impl Page {
+    pub const DEFAULT_LIMIT: u32 = 50;
+    pub const MAX_LIMIT: u32 = 500;
+
+    pub fn new(limit: u32, offset: u32) -> Self {
+        Self { limit, offset }
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit.min(Self::MAX_LIMIT)
+    }
+
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_LIMIT, 0)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Failed to open database")]
+    Connect(#[source] sqlx::Error),
+    #[error("Failed to migrate database")]
+    Migrate(#[source] MigrateError),
+    #[error("Database query failed")]
+    Query(#[from] sqlx::Error),
+    #[error("Malformed row in {table}: {reason}")]
+    Decode { table: &'static str, reason: String },
+}
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use tracing::debug;
+use url::Url;
+
+use crate::{scheduler::FeedSchedule, storage::StorageError};
+
+#[derive(Clone, Debug)]
+pub struct ScheduleStore {
+    pool: SqlitePool,
+}
+
+#[derive(sqlx::FromRow)]
+struct ScheduleRow {
+    feed_id: i64,
+    mean_gap_secs: Option<f64>,
+    last_post_at: Option<DateTime<Utc>>,
+    last_refresh_at: Option<DateTime<Utc>>,
+    next_refresh_at: DateTime<Utc>,
+}
+
+impl ScheduleStore {
+    pub(crate) fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get(&self, feed_id: i64) -> Result<Option<FeedSchedule>, StorageError> {
+        let row: Option<ScheduleRow> = sqlx::query_as(
+            "SELECT feed_id, mean_gap_secs, last_post_at, last_refresh_at, next_refresh_at
+             FROM feed_schedules WHERE feed_id = ?1",
+        )
+        .bind(feed_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(ScheduleRow::into_schedule))
+    }
+
+    pub async fn put(&self, schedule: &FeedSchedule) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO feed_schedules
+                 (feed_id, mean_gap_secs, last_post_at, last_refresh_at, next_refresh_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (feed_id) DO UPDATE SET
+                 mean_gap_secs = excluded.mean_gap_secs,
+                 last_post_at = excluded.last_post_at,
+                 last_refresh_at = excluded.last_refresh_at,
+                 next_refresh_at = excluded.next_refresh_at",
+        )
+        .bind(schedule.feed_id)
+        .bind(schedule.mean_gap_secs)
+        .bind(schedule.last_post_at)
+        .bind(schedule.last_refresh_at)
+        .bind(schedule.next_refresh_at)
+        .execute(&self.pool)
+        .await?;
+        debug!(
+            feed_id = schedule.feed_id,
+            next_refresh_at = %schedule.next_refresh_at,
+            "feed schedule stored"
+        );
+        Ok(())
+    }
+
+    /// Move the next refresh of feed `feed_id` to `at`, leaving what was learnt alone.
+    pub async fn postpone(&self, feed_id: i64, at: DateTime<Utc>) -> Result<(), StorageError> {
+        sqlx::query(
+            "INSERT INTO feed_schedules (feed_id, next_refresh_at) VALUES (?1, ?2)
+             ON CONFLICT (feed_id) DO UPDATE SET next_refresh_at = excluded.next_refresh_at",
+        )
+        .bind(feed_id)
+        .bind(at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Id and URL of feeds due for a refresh at `now`, most overdue first. Feeds never
+    /// scheduled are due straight away.
+    pub async fn due(
+        &self,
+        now: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<(i64, Url)>, StorageError> {
+        let rows: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT f.id, f.url FROM feeds f
+             LEFT JOIN feed_schedules s ON s.feed_id = f.id
+             WHERE s.next_refresh_at IS NULL OR s.next_refresh_at <= ?1
+             ORDER BY s.next_refresh_at IS NOT NULL, s.next_refresh_at
+             LIMIT ?2",
+        )
+        .bind(now)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|(id, url)| {
+                let url = Url::parse(&url).map_err(|e| StorageError::Decode {
+                    table: "feeds",
+                    reason: format!("invalid URL {url:?}: {e}"),
+                })?;
+                Ok((id, url))
+            })
+            .collect()
+    }
+
+    /// When the next scheduled refresh is due, if any feed is scheduled.
+    pub async fn next_due(&self) -> Result<Option<DateTime<Utc>>, StorageError> {
+        let next = sqlx::query_scalar("SELECT MIN(next_refresh_at) FROM feed_schedules")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(next)
+    }
+}
+
+impl ScheduleRow {
+    fn into_schedule(self) -> FeedSchedule {
+        FeedSchedule {
+            feed_id: self.feed_id,
+            mean_gap_secs: self.mean_gap_secs,
+            last_post_at: self.last_post_at,
+            last_refresh_at: self.last_refresh_at,
+            next_refresh_at: self.next_refresh_at,
+        }
+    }
+}
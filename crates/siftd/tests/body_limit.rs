@@ -0,0 +1,111 @@
+//! Response size limits, against a stub server on a local port.
+//!
+//! The stub serves bodies of a given length at `/bytes/<n>`, and endless ones at `/endless`
+//! and `/robots.txt`, which only a client that stops reading at its limit gets through.
+
+use std::{convert::Infallible, time::Duration};
+
+use axum::{body::Body, extract::Path, routing::get, Router};
+use bytes::Bytes;
+use futures::stream::{self, StreamExt};
+use libsift::{
+    content::{Content, ContentError, ErrorClass, Unfetched},
+    http::{HttpClient, HttpConfig},
+};
+use url::Url;
+
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+fn endless(prefix: &'static str, filler: &'static [u8]) -> Body {
+    let prefix = stream::once(async move { Ok::<_, Infallible>(Bytes::from(prefix)) });
+    Body::from_stream(prefix.chain(stream::repeat(Ok(Bytes::from(filler)))))
+}
+
+/// Serve the stub on a free port, returning its root.
+async fn serve() -> Url {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let app = Router::new()
+        .route(
+            "/bytes/{n}",
+            get(|Path(n): Path<usize>| async move { "x".repeat(n) }),
+        )
+        .route(
+            "/endless",
+            get(|| async { endless("<html><body>", b"<p>more</p>") }),
+        )
+        .route(
+            "/robots.txt",
+            get(|| async {
+                endless(
+                    "User-agent: *\nDisallow: /bytes/\n",
+                    b"# A comment that goes on and on.\n",
+                )
+            }),
+        );
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    Url::parse(&format!("http://{address}/")).unwrap()
+}
+
+fn http() -> HttpClient {
+    HttpConfig {
+        max_body_bytes: MAX_BODY_BYTES,
+        ..HttpConfig::default()
+    }
+    .build()
+    .unwrap()
+}
+
+async fn fetch(http: &HttpClient, url: Url) -> Result<(), ContentError> {
+    let fetching = Content::<Unfetched>::new(url, None)
+        .with_ignore_robots(true)
+        .fetch(http);
+    tokio::time::timeout(Duration::from_secs(10), fetching)
+        .await
+        .expect("the body is read past the limit")
+        .map(drop)
+}
+
+#[tokio::test]
+async fn bodies_up_to_the_limit_are_read() {
+    let root = serve().await;
+    let url = root.join(&format!("bytes/{MAX_BODY_BYTES}")).unwrap();
+    fetch(&http(), url).await.unwrap();
+}
+
+#[tokio::test]
+async fn longer_bodies_are_refused() {
+    let root = serve().await;
+    let http = http();
+
+    // Refused on its Content-Length.
+    let url = root.join(&format!("bytes/{}", MAX_BODY_BYTES + 1)).unwrap();
+    let error = fetch(&http, url).await.unwrap_err();
+    assert_eq!(error.class(), ErrorClass::BodyTooLarge);
+    assert!(!error.is_transient());
+
+    // Refused while reading, without a Content-Length, rather than read in full.
+    let error = fetch(&http, root.join("endless").unwrap())
+        .await
+        .unwrap_err();
+    assert_eq!(error.class(), ErrorClass::BodyTooLarge);
+}
+
+#[tokio::test]
+async fn robots_txt_is_read_up_to_its_limit() {
+    let root = serve().await;
+    let http = http();
+    let admit = |path: &str| {
+        let url = root.join(path).unwrap();
+        let http = &http;
+        async move {
+            let admitting = http.robots().admit(http, &url);
+            tokio::time::timeout(Duration::from_secs(10), admitting)
+                .await
+                .expect("robots.txt is read past its limit")
+                .unwrap()
+        }
+    };
+    assert!(!admit("bytes/10").await);
+    assert!(admit("endless").await);
+}
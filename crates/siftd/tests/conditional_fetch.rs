@@ -0,0 +1,159 @@
+//! Conditional fetches against a stub server on a local port.
+//!
+//! The stub serves a single article, versioned by an ETag and a Last-Modified date, and answers
+//! 304 to requests whose `If-None-Match` or `If-Modified-Since` matches the current version.
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::TimeDelta;
+use libsift::{
+    content::{Content, FetchOutcome, Unfetched, Validators},
+    http::HttpClient,
+};
+use url::Url;
+
+const LAST_MODIFIED: &str = "Tue, 14 Oct 2025 08:00:00 GMT";
+
+/// `If-None-Match` and `If-Modified-Since` of a request.
+type Conditions = (Option<String>, Option<String>);
+
+#[derive(Clone)]
+struct Stub {
+    /// ETag of the current version.
+    etag: Arc<Mutex<String>>,
+    /// Conditions of every request received, in order.
+    conditions: Arc<Mutex<Vec<Conditions>>>,
+}
+
+impl Default for Stub {
+    fn default() -> Self {
+        Self {
+            etag: Arc::new(Mutex::new("\"v1\"".to_string())),
+            conditions: Default::default(),
+        }
+    }
+}
+
+async fn article(State(stub): State<Stub>, headers: HeaderMap) -> Response {
+    let get = |name| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let if_none_match = get(header::IF_NONE_MATCH);
+    let if_modified_since = get(header::IF_MODIFIED_SINCE);
+    stub.conditions
+        .lock()
+        .unwrap()
+        .push((if_none_match.clone(), if_modified_since.clone()));
+
+    let etag = stub.etag.lock().unwrap().clone();
+    // As RFC 9110 asks, If-Modified-Since is ignored when If-None-Match is sent.
+    let unchanged = match &if_none_match {
+        Some(tag) => *tag == etag,
+        None => if_modified_since.as_deref() == Some(LAST_MODIFIED),
+    };
+    if unchanged {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [
+                (header::ETAG, etag),
+                (header::CACHE_CONTROL, "max-age=1200".to_string()),
+            ],
+        )
+            .into_response();
+    }
+    (
+        [
+            (header::ETAG, etag),
+            (header::LAST_MODIFIED, LAST_MODIFIED.to_string()),
+            (header::CACHE_CONTROL, "max-age=600".to_string()),
+        ],
+        Html("<html><head><title>Article</title></head><body><p>Text.</p></body></html>"),
+    )
+        .into_response()
+}
+
+/// Serve `stub` on a free port, returning the article's URL.
+async fn serve(stub: Stub) -> Url {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let app = Router::new()
+        .route("/article", get(article))
+        .with_state(stub);
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    Url::parse(&format!("http://{address}/article")).unwrap()
+}
+
+async fn fetch(http: &HttpClient, url: &Url, validators: &Validators) -> FetchOutcome {
+    Content::<Unfetched>::new(url.clone(), None)
+        .with_ignore_robots(true)
+        .fetch_if_modified(http, validators)
+        .await
+        .unwrap()
+}
+
+fn modified(outcome: FetchOutcome) -> Validators {
+    match outcome {
+        FetchOutcome::Modified(content) => content.validators(),
+        FetchOutcome::NotModified { .. } => panic!("expected the content, got 304"),
+    }
+}
+
+fn not_modified(outcome: FetchOutcome) -> Option<TimeDelta> {
+    match outcome {
+        FetchOutcome::NotModified { max_age } => max_age,
+        FetchOutcome::Modified(_) => panic!("expected 304, got the content"),
+    }
+}
+
+#[tokio::test]
+async fn validators_of_the_last_response_make_the_next_fetch_conditional() {
+    let stub = Stub::default();
+    let url = serve(stub.clone()).await;
+    let http = HttpClient::default();
+
+    let validators = modified(fetch(&http, &url, &Validators::default()).await);
+    assert_eq!(
+        validators,
+        Validators {
+            etag: Some("\"v1\"".to_string()),
+            last_modified: Some(LAST_MODIFIED.to_string()),
+        }
+    );
+
+    let max_age = not_modified(fetch(&http, &url, &validators).await);
+    assert_eq!(max_age, Some(TimeDelta::minutes(20)));
+
+    *stub.etag.lock().unwrap() = "\"v2\"".to_string();
+    let validators = modified(fetch(&http, &url, &validators).await);
+    assert_eq!(validators.etag.as_deref(), Some("\"v2\""));
+
+    let conditions = stub.conditions.lock().unwrap().clone();
+    let v1 = (Some("\"v1\"".to_string()), Some(LAST_MODIFIED.to_string()));
+    assert_eq!(conditions, [(None, None), v1.clone(), v1]);
+}
+
+#[tokio::test]
+async fn a_last_modified_date_alone_is_sent() {
+    let stub = Stub::default();
+    let url = serve(stub.clone()).await;
+    let http = HttpClient::default();
+    let validators = Validators {
+        etag: None,
+        last_modified: Some(LAST_MODIFIED.to_string()),
+    };
+
+    not_modified(fetch(&http, &url, &validators).await);
+
+    let conditions = stub.conditions.lock().unwrap().clone();
+    assert_eq!(conditions, [(None, Some(LAST_MODIFIED.to_string()))]);
+}
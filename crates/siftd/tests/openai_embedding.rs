@@ -0,0 +1,147 @@
+//! `OpenAiEmbedder` against a stub embedding server on a local port.
+//!
+//! The stub embeds every input as a one-dimensional vector holding the input's length, so that
+//! tests can tell which input an embedding belongs to. It answers with the data reversed, and
+//! may fail a number of requests first or number the data wrongly.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use libsift::{
+    embedding::{Embedder, OpenAiEmbedder},
+    retry::Backoff,
+};
+use serde_json::{json, Value};
+use url::Url;
+
+#[derive(Clone, Default)]
+struct Stub {
+    /// Statuses to fail the next requests with, in order.
+    failures: Arc<Mutex<Vec<StatusCode>>>,
+    /// Number of inputs of every request received, in order.
+    batches: Arc<Mutex<Vec<usize>>>,
+    /// Whether to number every datum 0.
+    repeat_index: bool,
+}
+
+async fn embeddings(
+    State(stub): State<Stub>,
+    Json(request): Json<Value>,
+) -> (StatusCode, Json<Value>) {
+    let inputs = request["input"].as_array().cloned().unwrap_or_default();
+    stub.batches.lock().unwrap().push(inputs.len());
+    let failure = {
+        let mut failures = stub.failures.lock().unwrap();
+        (!failures.is_empty()).then(|| failures.remove(0))
+    };
+    if let Some(status) = failure {
+        return (status, Json(json!({ "error": "try again" })));
+    }
+    let data = inputs
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(index, input)| {
+            let index = if stub.repeat_index { 0 } else { index };
+            let length = input.as_str().unwrap_or_default().len();
+            json!({ "index": index, "embedding": [length as f32] })
+        })
+        .collect::<Vec<_>>();
+    (StatusCode::OK, Json(json!({ "data": data })))
+}
+
+/// Serve `stub` on a free port, returning the API root to give the embedder.
+async fn serve(stub: Stub) -> Url {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let app = Router::new()
+        .route("/v1/embeddings", post(embeddings))
+        .with_state(stub);
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    Url::parse(&format!("http://{address}/v1")).unwrap()
+}
+
+fn embedder(base_url: &Url) -> OpenAiEmbedder {
+    OpenAiEmbedder::new(reqwest::Client::new(), base_url, "stub").with_backoff(Backoff {
+        retries: 2,
+        base: Duration::from_millis(1),
+        max: Duration::from_millis(5),
+    })
+}
+
+fn texts(lengths: &[usize]) -> Vec<String> {
+    lengths.iter().map(|n| "x".repeat(*n)).collect()
+}
+
+fn lengths(embeddings: &[libsift::embedding::Embedding]) -> Vec<usize> {
+    embeddings
+        .iter()
+        .map(|e| e.as_slice()[0] as usize)
+        .collect()
+}
+
+#[tokio::test]
+async fn embeddings_come_back_in_input_order_across_batches() {
+    let stub = Stub::default();
+    let embedder = embedder(&serve(stub.clone()).await).with_batch_size(2);
+
+    let inputs = [3, 1, 4, 1, 5];
+    let embeddings = embedder.embed(&texts(&inputs)).await.unwrap();
+
+    assert_eq!(lengths(&embeddings), inputs);
+    assert_eq!(*stub.batches.lock().unwrap(), [2, 2, 1]);
+}
+
+#[tokio::test]
+async fn rate_limits_and_server_errors_are_retried() {
+    let stub = Stub::default();
+    stub.failures
+        .lock()
+        .unwrap()
+        .extend([StatusCode::TOO_MANY_REQUESTS, StatusCode::BAD_GATEWAY]);
+    let embedder = embedder(&serve(stub.clone()).await);
+
+    let embeddings = embedder.embed(&texts(&[2, 7])).await.unwrap();
+
+    assert_eq!(lengths(&embeddings), [2, 7]);
+    assert_eq!(stub.batches.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn retries_run_out() {
+    let stub = Stub::default();
+    stub.failures
+        .lock()
+        .unwrap()
+        .extend([StatusCode::SERVICE_UNAVAILABLE; 3]);
+    let embedder = embedder(&serve(stub.clone()).await);
+
+    assert!(embedder.embed(&texts(&[1])).await.is_err());
+    assert_eq!(stub.batches.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let stub = Stub::default();
+    stub.failures.lock().unwrap().push(StatusCode::BAD_REQUEST);
+    let embedder = embedder(&serve(stub.clone()).await);
+
+    assert!(embedder.embed(&texts(&[1])).await.is_err());
+    assert_eq!(stub.batches.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn every_input_must_come_back_once() {
+    let stub = Stub {
+        repeat_index: true,
+        ..Stub::default()
+    };
+    let embedder = embedder(&serve(stub.clone()).await);
+
+    assert!(embedder.embed(&texts(&[1, 2])).await.is_err());
+    // A malformed response is not worth retrying.
+    assert_eq!(stub.batches.lock().unwrap().len(), 1);
+}
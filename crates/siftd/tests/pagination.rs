@@ -0,0 +1,101 @@
+//! Stitching multi-page articles, served by a stub site on a local port.
+//!
+//! Every `tests/fixtures/pagination/<path>.html` is served at `/<path>`, and anything else is
+//! missing, `robots.txt` included. `<name>.md` is the Markdown expected from the article starting
+//! at `/<name>`, and `<name>.txt` the plain text. Run with `UPDATE_FIXTURES=1` to rewrite the
+//! expectations after a deliberate change, and review the diff.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use axum::{extract::State, http::StatusCode, response::Html, Router};
+use libsift::{
+    content::{Content, Unfetched},
+    entry::Entry,
+    http::HttpClient,
+    parser::rules::SiteRules,
+};
+use url::Url;
+
+#[derive(Clone, Default)]
+struct Site {
+    /// Paths of every request received, in order.
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/pagination")
+}
+
+async fn page(State(site): State<Site>, uri: axum::http::Uri) -> Result<Html<String>, StatusCode> {
+    let path = uri.path().trim_start_matches('/');
+    site.requests.lock().unwrap().push(path.to_string());
+    fs::read_to_string(fixtures().join(format!("{path}.html")))
+        .map(Html)
+        .map_err(|_| StatusCode::NOT_FOUND)
+}
+
+/// Serve `site` on a free port, returning its root.
+async fn serve(site: Site) -> Url {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let app = Router::new().fallback(page).with_state(site);
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    Url::parse(&format!("http://{address}/")).unwrap()
+}
+
+async fn article(root: &Url, name: &str, max_pages: usize) -> Entry {
+    let http = HttpClient::default();
+    Content::<Unfetched>::new(root.join(name).unwrap(), None)
+        .fetch(&http)
+        .await
+        .unwrap()
+        .parse_pages(&http, &SiteRules::default(), max_pages)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn pages_are_stitched_into_one_entry() {
+    let site = Site::default();
+    let root = serve(site.clone()).await;
+    let update = std::env::var_os("UPDATE_FIXTURES").is_some();
+
+    let entry = article(&root, "story", 10).await;
+
+    for (extension, actual) in [("md", entry.content()), ("txt", entry.text())] {
+        let expected_path = fixtures().join(format!("story.{extension}"));
+        if update {
+            fs::write(&expected_path, format!("{actual}\n")).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&expected_path)
+            .unwrap_or_else(|_| panic!("{} is missing", expected_path.display()));
+        assert_eq!(
+            actual.trim_end(),
+            expected.trim_end(),
+            "{}",
+            expected_path.display()
+        );
+    }
+    assert_eq!(entry.next_page(), None);
+    // The last page's `rel="next"` leads to another article, which is left alone.
+    let requests = site.requests.lock().unwrap().clone();
+    assert_eq!(requests, ["robots.txt", "story", "story/2", "story/3"]);
+}
+
+#[tokio::test]
+async fn stitching_stops_at_the_page_limit() {
+    let site = Site::default();
+    let root = serve(site.clone()).await;
+
+    let entry = article(&root, "story", 2).await;
+
+    assert!(entry.text().contains("timber frame"));
+    assert!(!entry.text().contains("second survey has been carried out"));
+    let requests = site.requests.lock().unwrap().clone();
+    assert_eq!(requests, ["robots.txt", "story", "story/2"]);
+}
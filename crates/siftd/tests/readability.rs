@@ -0,0 +1,71 @@
+//! Regression tests for main content extraction.
+//!
+//! Every `tests/fixtures/readability/<name>.html` is a saved page, taken to be served from
+//! `https://example.com/articles/<name>.html` unless it says otherwise with `<base href>`.
+//! `<name>.txt` is the plain text expected to be extracted from it, and `<name>.md` the Markdown.
+//! Run with `UPDATE_FIXTURES=1` to rewrite the expectations after a deliberate change, and review
+//! the diff.
+
+use std::{fs, path::Path};
+
+use libsift::parser::{markdown, readability};
+use url::Url;
+
+#[test]
+fn fixtures() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/readability");
+    let update = std::env::var_os("UPDATE_FIXTURES").is_some();
+
+    let mut pages = fs::read_dir(&dir)
+        .expect("fixture directory exists")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+        .collect::<Vec<_>>();
+    pages.sort();
+    assert!(!pages.is_empty(), "no fixtures in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for page in &pages {
+        let html = fs::read_to_string(page).unwrap();
+        let doc = scraper::Html::parse_document(&html);
+        let name = page.file_name().unwrap().to_string_lossy();
+        let url = Url::parse("https://example.com/articles/")
+            .unwrap()
+            .join(&name)
+            .unwrap();
+        let base = markdown::base_url(&doc, &url);
+
+        let main = readability::extract(&doc);
+        let outputs = [
+            ("txt", readability::text(&main)),
+            ("md", markdown::render(&main, &base)),
+        ];
+        for (extension, actual) in outputs {
+            let expected_path = page.with_extension(extension);
+            if update {
+                fs::write(&expected_path, format!("{actual}\n")).unwrap();
+            } else if let Some(failure) = compare(&expected_path, &actual) {
+                failures.push(failure);
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} outputs differ from their fixtures:\n\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+fn compare(expected_path: &Path, actual: &str) -> Option<String> {
+    let expected = fs::read_to_string(expected_path)
+        .unwrap_or_else(|_| panic!("{} is missing", expected_path.display()));
+    (actual.trim_end() != expected.trim_end()).then(|| {
+        format!(
+            "{}\n--- expected\n{}\n--- actual\n{}\n",
+            expected_path.display(),
+            expected.trim_end(),
+            actual
+        )
+    })
+}